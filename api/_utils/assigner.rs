@@ -1,31 +1,37 @@
 use crate::_utils::{data, error, logic_circuit};
-use data::get_data;
+use data::Data;
 use error::Error;
 use logic_circuit::LogicCircuit;
 use rand::{
 	distributions::{Distribution, Uniform},
-	prelude::ThreadRng,
+	rngs::StdRng,
+	SeedableRng,
 };
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_ITERATIONS: usize = 6000;
 
 pub struct Layer {
 	nodes: Vec<f64>,
-	rng: ThreadRng,
 	uni: Uniform<f64>,
 }
 
 impl Layer {
-	pub fn init(len: usize) -> Self {
-		let mut rng = rand::thread_rng();
+	pub fn init(len: usize, rng: &mut StdRng) -> Self {
 		let uni = Uniform::new_inclusive(0.0f64, 1.0);
-		let nodes = vec![uni.sample(&mut rng); len];
-		Self { nodes, rng, uni }
+		let nodes = vec![uni.sample(rng); len];
+		Self { nodes, uni }
 	}
 
-	pub fn choose_node(&mut self, bl: &mut HashSet<String>) -> usize {
-		let ch = self.uni.sample(&mut self.rng);
-		let sel = self.get_node_from_prob(ch, bl);
-		self.insert_bl(sel, bl);
+	pub fn choose_node(
+		&mut self,
+		data: &Data,
+		bl: &mut HashSet<String>,
+		rng: &mut StdRng,
+	) -> usize {
+		let ch = self.uni.sample(rng);
+		let sel = self.get_node_from_prob(data, ch, bl);
+		self.insert_bl(data, sel, bl);
 		sel
 	}
 
@@ -36,29 +42,27 @@ impl Layer {
 		*weight += change;
 	}
 
-	pub fn insert_bl(&self, i: usize, bl: &mut HashSet<String>) {
-		let data = get_data();
+	pub fn insert_bl(&self, data: &Data, i: usize, bl: &mut HashSet<String>) {
 		let gene = data.get_gene_at(i);
 		gene.blacklist(bl);
 	}
 
-	pub fn in_bl(&self, i: usize, bl: &HashSet<String>) -> bool {
-		let data = get_data();
+	pub fn in_bl(&self, data: &Data, i: usize, bl: &HashSet<String>) -> bool {
 		let gene = data.get_gene_at(i);
-		gene.is_blacklisted(&bl)
+		gene.is_blacklisted(bl)
 	}
 
-	pub fn get_node_from_prob(&self, ch: f64, bl: &HashSet<String>) -> usize {
+	pub fn get_node_from_prob(&self, data: &Data, ch: f64, bl: &HashSet<String>) -> usize {
 		let mut acc = 0.0;
 		let mut sum: f64 = 0.0;
 		for (i, w) in self.nodes.iter().enumerate() {
-			if self.in_bl(i, &bl) {
+			if self.in_bl(data, i, bl) {
 				continue;
 			}
 			sum += w;
 		}
 		for (i, w) in self.nodes.iter().enumerate() {
-			if self.in_bl(i, &bl) {
+			if self.in_bl(data, i, bl) {
 				continue;
 			}
 			acc += w / sum;
@@ -70,13 +74,15 @@ impl Layer {
 	}
 }
 
-pub struct GeneNetwork {
+pub struct GeneNetwork<'a> {
 	layers: Vec<Layer>,
 	lc: LogicCircuit,
+	data: &'a Data,
 	num_iterations: usize,
+	rng: StdRng,
 }
 
-impl GeneNetwork {
+impl<'a> GeneNetwork<'a> {
 	pub fn out_error(x: f64) -> f64 {
 		1.0 - (-x / 200.0).exp()
 	}
@@ -86,42 +92,75 @@ impl GeneNetwork {
 		(-i / len).exp()
 	}
 
-	pub fn init(lc: LogicCircuit, num_iterations: usize) -> Result<Self, Error> {
+	// The same `seed` picks the same genes, without one the assignment is
+	// seeded from the OS.
+	pub fn init(
+		lc: LogicCircuit,
+		data: &'a Data,
+		num_iterations: usize,
+		seed: Option<u64>,
+	) -> Result<Self, Error> {
+		let mut rng = match seed {
+			Some(seed) => StdRng::seed_from_u64(seed),
+			None => StdRng::from_entropy(),
+		};
 		let mut layers = Vec::new();
 		for device in lc.devices.iter().rev() {
-			let layer = Layer::init(device.num_biological());
+			let layer = Layer::init(device.num_biological(data), &mut rng);
 			layers.push(layer);
 		}
 		Ok(Self {
 			layers,
 			lc,
+			data,
 			num_iterations,
+			rng,
 		})
 	}
 
 	pub fn fit(&mut self) -> Result<Vec<usize>, Error> {
-		let mut best_score = 0.0;
-		let mut best_sel = Vec::new();
+		let mut top = self.fit_top(1)?;
+		Ok(top.pop().map(|(_, sel)| sel).unwrap_or_default())
+	}
+
+	// The `n` best distinct assignments walked, with their scores, best
+	// first. Ties keep the one found first.
+	pub fn fit_top(&mut self, n: usize) -> Result<Vec<(f64, Vec<usize>)>, Error> {
+		let mut top: Vec<(f64, Vec<usize>)> = Vec::new();
+		// The walk keeps coming back to the same assignments as the weights
+		// settle, and scoring one can mean simulating every stable state.
+		let mut scores: HashMap<Vec<usize>, f64> = HashMap::new();
 		for i in 0..self.num_iterations {
 			let lr = self.lrate(i as f64);
 			let sel_genes = self.walk();
-			let diff_score = self.lc.into_biological(&sel_genes).test();
+			let diff_score = match scores.get(&sel_genes) {
+				Some(score) => *score,
+				None => {
+					let score = self.lc.into_biological(self.data, &sel_genes)?.test();
+					scores.insert(sel_genes.clone(), score);
+					score
+				}
+			};
 
-			if diff_score > best_score {
-				best_score = diff_score;
-				best_sel = sel_genes.clone();
+			if !top.iter().any(|(_, sel)| *sel == sel_genes) {
+				let at = top.iter().position(|(score, _)| diff_score > *score);
+				let at = at.unwrap_or(top.len());
+				if at < n {
+					top.insert(at, (diff_score, sel_genes.clone()));
+					top.truncate(n);
+				}
 			}
 			let out = Self::out_error(diff_score);
 			self.update_weights(lr, out, sel_genes);
 		}
-		Ok(best_sel)
+		Ok(top)
 	}
 
 	pub fn walk(&mut self) -> Vec<usize> {
 		let mut bl: HashSet<String> = self.lc.inputs.iter().map(|x| x.value.to_string()).collect();
 		let mut selected = Vec::new();
 		for layer in &mut self.layers {
-			let sel = layer.choose_node(&mut bl);
+			let sel = layer.choose_node(self.data, &mut bl, &mut self.rng);
 			selected.push(sel);
 		}
 		selected
@@ -1,23 +1,54 @@
-use crate::_utils::{data, error, lexer, logic_circuit, parser};
-use data::get_data;
+use crate::_utils::{
+	data, diagnostic, error, genetic_circuit, lexer, lint, logic_circuit, parser, validate,
+};
+use data::{Data, GeneData, Libraries, Params, Part, PartKind};
+use diagnostic::{Diagnostic, Diagnostics};
 use error::Error;
+use genetic_circuit::{steps_to, MotifKind};
 use lexer::Token;
-use logic_circuit::{Device, Gate, GateKind, Input, LogicCircuit, Output, Testbench};
-use parser::{Def, Enviroment, Implementation, Module, Operation, ParserIter, Test};
-use std::collections::{HashMap, HashSet};
-
+use logic_circuit::{Device, Gate, GateKind, Input, LogicCircuit, Motif, Output, Testbench};
+use parser::{
+	Def, Enviroment, GeneDef, Implementation, LogicOp, Module, Operation, Ordering, ParserIter,
+	PartDef, Test,
+};
+use std::{
+	borrow::Cow,
+	collections::{HashMap, HashSet},
+};
+use validate::Severity;
+
+pub const DEFAULT_DURATION: u32 = 1000;
+// Exhaustive tests go through every combination of this many inputs at most.
+const MAX_EXHAUSTIVE_INPUTS: usize = 16;
+// Exhaustive tests move on to the next combination once every gene has gone
+// this fraction of the way to its new steady state.
+const SETTLE_FRACTION: f64 = 0.99;
+const MIN_HOLD: u32 = 200;
+
+/// Checks the definitions of a [`ParserIter`] against a library and builds
+/// the [`LogicCircuit`] of the `main` module, with its testbenches.
 pub struct LogicCircuitBuilder<'a> {
 	parse_iter: ParserIter<'a>,
 	impl_tree: HashMap<String, Implementation>,
 	test_tree: HashMap<String, Test>,
 	env_tree: HashMap<String, Enviroment>,
 	mod_tree: HashMap<String, Module>,
+	libraries: &'a Libraries,
+	library: &'a Data,
+	part_defs: Vec<(Token, Part)>,
+	gene_defs: Vec<(Token, GeneData)>,
+	diagnostics: Diagnostics,
 }
 
 impl<'a> LogicCircuitBuilder<'a> {
-	pub fn new(parse_iter: ParserIter<'a>) -> Self {
+	pub fn new(parse_iter: ParserIter<'a>, libraries: &'a Libraries, library: &'a Data) -> Self {
 		Self {
 			parse_iter,
+			libraries,
+			library,
+			part_defs: Vec::new(),
+			gene_defs: Vec::new(),
+			diagnostics: Diagnostics::new(),
 			impl_tree: HashMap::new(),
 			test_tree: HashMap::new(),
 			env_tree: HashMap::new(),
@@ -29,25 +60,73 @@ impl<'a> LogicCircuitBuilder<'a> {
 		match token.value.as_str() {
 			"not" => Ok(GateKind::Not),
 			"nor" => Ok(GateKind::Nor),
-			_ => Err(Error::UnexpectedToken(
-				token.value.to_string(),
-				token.pos,
-				token.value.len(),
-			)),
+			_ => Err(Error::UnexpectedToken {
+				token: token.value.to_string(),
+				span: token.span.clone(),
+			}),
+		}
+	}
+
+	pub fn get_num_genes(lop: &LogicOp) -> Result<usize, Error> {
+		match lop.symbol.value.as_str() {
+			"osc" => {
+				let stages = &lop.args[0];
+				match stages.value.parse::<usize>() {
+					Ok(n) if n >= 3 && n % 2 == 1 => Ok(n),
+					_ => Err(Error::UnexpectedToken {
+						token: stages.value.to_string(),
+						span: stages.span.clone(),
+					}),
+				}
+			}
+			"pulse" => Ok(3),
+			_ => Ok(1),
 		}
 	}
 
-	fn check_implementation_errors(&mut self, imp: Implementation) -> Result<(), Error> {
+	// The circuit is built from the `main` definition of each kind.
+	fn get_main<'t, T>(tree: &'t HashMap<String, T>, kind: &str) -> Result<&'t T, Error> {
+		tree.get("main").ok_or_else(|| Error::Undefined {
+			kind: kind.to_string(),
+			name: "main".to_string(),
+		})
+	}
+
+	// Envs compile against the library named after `for`, or the library of
+	// the request when they don't name one.
+	fn get_library(&self, env: &Enviroment) -> Result<&'a Data, Error> {
+		match &env.library {
+			Some(name) => self.libraries.require(&name.value).map_err(|e| match e {
+				Error::UnknownLibrary { .. } => Error::NotFound {
+					name: name.value.to_string(),
+					span: name.span.clone(),
+				},
+				e => e,
+			}),
+			None => Ok(self.library),
+		}
+	}
+
+	fn check_implementation_errors(&mut self, imp: Implementation) -> Result<(), Diagnostic> {
 		Error::already_exists(self.impl_tree.contains_key(&imp.name.value), &imp.name)?;
-		Error::not_found(!self.mod_tree.contains_key(&imp.name.value), &imp.name)?;
+		Diagnostic::not_found(
+			!self.mod_tree.contains_key(&imp.name.value),
+			&imp.name,
+			self.mod_tree.keys().map(String::as_str),
+		)?;
 
-		let data = get_data();
-		if imp.body.len() > data.genes_len() {
-			return Err(Error::NotEnoughGenes);
+		let diag = &mut self.diagnostics;
+		for op in &imp.body {
+			if let Operation::Logic(lop) = op {
+				diag.check(Self::get_num_genes(lop).map(|_| ()));
+			}
 		}
 
 		let module = self.mod_tree.get(&imp.name.value).unwrap();
-		Error::invalid_number_of_args(module.outs.len() != 1, &module.name)?;
+		diag.check(Error::invalid_number_of_args(
+			module.outs.len() != 1,
+			&module.name,
+		));
 
 		let mut pmap = HashSet::new();
 		let mut rmap = HashSet::new();
@@ -61,45 +140,67 @@ impl<'a> LogicCircuitBuilder<'a> {
 		}
 
 		let mut vmap = HashSet::new();
-		let mut vunused = HashMap::new();
+		let mut regs = HashMap::new();
 		for op in &imp.body {
 			match op {
+				Operation::Register(reg) => {
+					diag.check(Error::already_exists(
+						vmap.contains(&reg.var.value)
+							|| pmap.contains(&reg.var.value)
+							|| regs.contains_key(&reg.var.value),
+						&reg.var,
+					));
+					regs.insert(reg.var.value.to_string(), reg.var.clone());
+				}
 				Operation::Logic(lop) => {
-					let kind = Self::get_gate_kind(&lop.symbol)?;
-					match kind {
-						GateKind::Not => {
-							Error::invalid_number_of_args(lop.args.len() != 1, &lop.symbol)?
-						}
-						GateKind::Nor => {
-							Error::invalid_number_of_args(lop.args.len() != 2, &lop.symbol)?
-						}
+					let num_args = match lop.symbol.value.as_str() {
+						"osc" => 0,
+						"pulse" => 1,
+						_ => match Self::get_gate_kind(&lop.symbol) {
+							Ok(GateKind::Not) => 1,
+							Ok(GateKind::Nor) => 2,
+							Err(e) => {
+								diag.error(e);
+								continue;
+							}
+						},
 					};
-					Error::already_exists(
+					let wires = if num_args == 0 {
+						&[][..]
+					} else {
+						&lop.args[..]
+					};
+					diag.check(Error::invalid_number_of_args(
+						wires.len() != num_args,
+						&lop.symbol,
+					));
+					diag.check(Error::already_exists(
 						vmap.contains(&lop.var.value) || pmap.contains(&lop.var.value),
 						&lop.var,
-					)?;
+					));
 					rmap.remove(&lop.var.value);
-					for arg in &lop.args {
-						vunused.remove(&arg.value);
-						Error::not_found(
-							!vmap.contains(&arg.value) && !pmap.contains(&arg.value),
-							&arg,
-						)?;
+					for arg in wires {
+						let in_scope = vmap
+							.iter()
+							.chain(&pmap)
+							.chain(regs.keys())
+							.map(String::as_str);
+						diag.check(Diagnostic::not_found(
+							!vmap.contains(&arg.value)
+								&& !pmap.contains(&arg.value)
+								&& !regs.contains_key(&arg.value),
+							arg,
+							in_scope,
+						));
 					}
 
 					vmap.insert(lop.var.value.to_string());
-					vunused.insert(lop.var.value.to_string(), lop.var.clone());
 				}
 			}
 		}
 
-		for arg in &module.outs {
-			vunused.remove(&arg.value);
-		}
-
-		// TODO: add warning instead of error
-		for (_, var) in vunused {
-			Error::not_used(true, &var)?;
+		for (name, reg) in &regs {
+			diag.check(Error::not_found(!vmap.contains(name), reg));
 		}
 
 		self.impl_tree.insert(imp.name.value.to_string(), imp);
@@ -107,15 +208,20 @@ impl<'a> LogicCircuitBuilder<'a> {
 		Ok(())
 	}
 
-	pub fn check_test_errors(&mut self, test: Test) -> Result<(), Error> {
+	pub fn check_test_errors(&mut self, test: Test) -> Result<(), Diagnostic> {
 		Error::already_exists(self.test_tree.contains_key(&test.name.value), &test.name)?;
-		Error::not_found(
+		Diagnostic::not_found(
 			!self.mod_tree.contains_key(&test.module.value),
 			&test.module,
+			self.mod_tree.keys().map(String::as_str),
+		)?;
+		Diagnostic::not_found(
+			!self.env_tree.contains_key(&test.env.value),
+			&test.env,
+			self.env_tree.keys().map(String::as_str),
 		)?;
-		Error::not_found(!self.env_tree.contains_key(&test.name.value), &test.name)?;
 
-		let env = self.env_tree.get(&test.name.value).unwrap();
+		let env = self.env_tree.get(&test.env.value).unwrap();
 		let inputs = &env.ins;
 		let mut pmap = HashSet::new();
 		for inp in inputs {
@@ -123,20 +229,27 @@ impl<'a> LogicCircuitBuilder<'a> {
 		}
 
 		let module = self.mod_tree.get(&test.module.value).unwrap();
-		let env = self.env_tree.get(&test.name.value).unwrap();
 
+		let diag = &mut self.diagnostics;
 		let same_len = (module.ins.len() == env.ins.len()) && (module.outs.len() == env.outs.len());
-		Error::invalid_number_of_args(!same_len, &test.name)?;
+		diag.check(Error::invalid_number_of_args(!same_len, &test.env));
 
 		let mut at_set = HashSet::new();
 		for bp in &test.body {
-			Error::already_exists(at_set.contains(&bp.time), &bp.symbol)?;
+			diag.check(Error::already_exists(at_set.contains(&bp.time), &bp.symbol));
 			at_set.insert(bp.time);
 
 			let mut assm = HashSet::new();
 			for ass in &bp.assignments {
-				Error::already_exists(assm.contains(&ass.iden.value), &ass.iden)?;
-				Error::not_found(!pmap.contains(&ass.iden.value), &ass.iden)?;
+				diag.check(Error::already_exists(
+					assm.contains(&ass.iden.value),
+					&ass.iden,
+				));
+				diag.check(Diagnostic::not_found(
+					!pmap.contains(&ass.iden.value),
+					&ass.iden,
+					pmap.iter().map(String::as_str),
+				));
 				assm.insert(ass.iden.value.to_string());
 			}
 		}
@@ -145,19 +258,20 @@ impl<'a> LogicCircuitBuilder<'a> {
 		Ok(())
 	}
 
-	pub fn check_module_error(&mut self, module: Module) -> Result<(), Error> {
+	pub fn check_module_error(&mut self, module: Module) -> Result<(), Diagnostic> {
 		Error::already_exists(self.mod_tree.contains_key(&module.name.value), &module.name)?;
 		let ins = &module.ins;
 		let outs = &module.outs;
 		let mut arg_map = HashSet::new();
 		let mut ret_map = HashSet::new();
+		let diag = &mut self.diagnostics;
 		for arg in ins {
-			Error::already_exists(arg_map.contains(&arg.value), &arg)?;
+			diag.check(Error::already_exists(arg_map.contains(&arg.value), arg));
 			arg_map.insert(arg.value.to_string());
 		}
 
 		for arg in outs {
-			Error::already_exists(ret_map.contains(&arg.value), &arg)?;
+			diag.check(Error::already_exists(ret_map.contains(&arg.value), arg));
 			ret_map.insert(arg.value.to_string());
 		}
 
@@ -165,52 +279,271 @@ impl<'a> LogicCircuitBuilder<'a> {
 		Ok(())
 	}
 
-	pub fn check_enviroment_error(&mut self, env: Enviroment) -> Result<(), Error> {
+	pub fn check_enviroment_error(&mut self, env: Enviroment) -> Result<(), Diagnostic> {
 		Error::already_exists(self.env_tree.contains_key(&env.name.value), &env.name)?;
 
-		let data = get_data();
+		// Kept even with an unknown library, so its tests aren't errors too.
+		let data = match self.get_library(&env) {
+			Ok(data) => data,
+			Err(e) => {
+				let unknown = matches!(e, Error::NotFound { .. });
+				let mut diag = Diagnostic::error(e);
+				if let (true, Some(name)) = (unknown, &env.library) {
+					let names = self.libraries.names();
+					diag = diag.with_suggestions(name, names.iter().map(String::as_str));
+				}
+				self.env_tree.insert(env.name.value.to_string(), env);
+				return Err(diag);
+			}
+		};
 
 		let ins = &env.ins;
 		let outs = &env.outs;
 		let mut arg_map = HashSet::new();
 		let mut ret_map = HashSet::new();
+		let diag = &mut self.diagnostics;
 		for arg in ins {
-			Error::already_exists(arg_map.contains(&arg.value), &arg)?;
-			Error::not_found(!data.has_signal(&arg.value), &arg)?;
+			diag.check(Error::already_exists(arg_map.contains(&arg.value), arg));
+			diag.check(Diagnostic::not_found(
+				!data.has_signal(&arg.value),
+				arg,
+				data.signals.keys().map(String::as_str),
+			));
 			arg_map.insert(arg.value.to_string());
 		}
 
 		for arg in outs {
-			Error::already_exists(ret_map.contains(&arg.value), &arg)?;
-			Error::not_found(!data.has_actuator(&arg.value), &arg)?;
+			diag.check(Error::already_exists(ret_map.contains(&arg.value), arg));
+			let defined = self
+				.part_defs
+				.iter()
+				.any(|(_, part)| part.name == arg.value && part.kind == PartKind::Actuator);
+			let actuators = data
+				.parts
+				.values()
+				.chain(self.part_defs.iter().map(|(_, part)| part))
+				.filter(|part| part.kind == PartKind::Actuator)
+				.map(|part| part.name.as_str());
+			diag.check(Diagnostic::not_found(
+				!data.has_actuator(&arg.value) && !defined,
+				arg,
+				actuators,
+			));
 			ret_map.insert(arg.value.to_string());
 		}
 		self.env_tree.insert(env.name.value.to_string(), env);
 		Ok(())
 	}
 
-	pub fn build_parse_tree(&mut self) -> Result<(), Error> {
+	pub fn check_part_error(&mut self, def: PartDef) -> Result<(), Diagnostic> {
+		let exists = self
+			.part_defs
+			.iter()
+			.any(|(name, _)| name.value == def.name.value);
+		Error::already_exists(exists, &def.name)?;
+
+		let kind = PartKind::from_name(&def.kind.value).ok_or_else(|| Error::UnexpectedToken {
+			token: def.kind.value.to_string(),
+			span: def.kind.span.clone(),
+		})?;
+		let seq: String = def.seq.value.split_whitespace().collect();
+		let part = Part {
+			kind,
+			name: def.name.value.to_string(),
+			seq,
+		};
+		self.part_defs.push((def.name, part));
+		Ok(())
+	}
+
+	pub fn check_gene_error(&mut self, def: GeneDef) -> Result<(), Diagnostic> {
+		let exists = self
+			.gene_defs
+			.iter()
+			.any(|(name, _)| name.value == def.name.value);
+		Error::already_exists(exists, &def.name)?;
+		Error::invalid_definition(def.promoter.is_none(), &def.name, "missing promoter")?;
+		Error::invalid_definition(def.parts.is_none(), &def.name, "missing parts")?;
+
+		let mut params = HashMap::new();
+		for param in &def.params {
+			self.diagnostics.check(Error::already_exists(
+				params.contains_key(&param.name.value),
+				&param.name,
+			));
+			params.insert(param.name.value.to_string(), param.value);
+		}
+		for name in &["ymax", "ymin", "K", "n", "decay"] {
+			let message = format!("missing param {}", name);
+			Error::invalid_definition(!params.contains_key(*name), &def.name, &message)?;
+		}
+
+		let gene = GeneData {
+			name: def.name.value.to_string(),
+			parts: def
+				.parts
+				.unwrap()
+				.iter()
+				.map(|part| part.value.to_string())
+				.collect(),
+			promoter: def.promoter.unwrap().value,
+			params: Params {
+				ymax: params["ymax"],
+				ymin: params["ymin"],
+				k: params["K"],
+				n: params["n"],
+				decay: params["decay"],
+			},
+		};
+		self.gene_defs.push((def.name, gene));
+		Ok(())
+	}
+
+	// Parses and checks the whole source, with every diagnostic found when
+	// there are errors. Checks of a definition stop at errors they can't go
+	// past, like a test of an unknown module, but the other definitions are
+	// still checked.
+	pub fn build_parse_tree(&mut self) -> Result<(), Vec<Diagnostic>> {
 		while let Some(res) = self.parse_iter.next() {
-			let res = res?;
-			match res {
-				Def::Implementation(imp) => self.check_implementation_errors(imp)?,
-				Def::Test(test) => self.check_test_errors(test)?,
-				Def::Module(module) => self.check_module_error(module)?,
-				Def::Enviroment(env) => self.check_enviroment_error(env)?,
-			}
+			let res = match res {
+				Ok(Def::Implementation(imp)) => self.check_implementation_errors(imp),
+				Ok(Def::Test(test)) => self.check_test_errors(test),
+				Ok(Def::Module(module)) => self.check_module_error(module),
+				Ok(Def::Enviroment(env)) => self.check_enviroment_error(env),
+				Ok(Def::Part(part)) => self.check_part_error(part),
+				Ok(Def::Gene(gene)) => self.check_gene_error(gene),
+				Err(e) => Err(e.into()),
+			};
+			self.diagnostics.check(res);
 		}
 
+		if self.diagnostics.has_errors() {
+			return Err(self.take_diagnostics());
+		}
+		self.lint();
 		Ok(())
 	}
 
-	fn build_devices(&self, imp: &Implementation) -> Vec<Device> {
+	// Lints only run on sources without errors, as they assume every name
+	// resolves.
+	fn lint(&mut self) {
+		let mut impls: Vec<&Implementation> = self.impl_tree.values().collect();
+		impls.sort_by_key(|imp| imp.name.span.start);
+		for imp in impls {
+			let module = &self.mod_tree[&imp.name.value];
+			for (lint, error) in lint::lint_implementation(module, imp) {
+				self.diagnostics.lint(lint, error);
+			}
+		}
+
+		// Envs are bound to modules by the tests that use them, and the main
+		// env to the main module.
+		let mut bound = vec![("main", "main")];
+		for test in self.test_tree.values() {
+			bound.push((test.env.value.as_str(), test.module.value.as_str()));
+		}
+		bound.sort_unstable();
+		bound.dedup();
+		let mut lints = Vec::new();
+		for (env, module) in bound {
+			let env = self.env_tree.get(env);
+			let module = self.mod_tree.get(module);
+			let imp = module.and_then(|module| self.impl_tree.get(&module.name.value));
+			if let (Some(env), Some(module), Some(imp)) = (env, module, imp) {
+				lints.extend(lint::lint_signals(env, module, imp));
+			}
+		}
+		lints.sort_by_key(|(_, error)| error.span().map(|span| span.start));
+		lints.dedup_by(|a, b| a.1.span() == b.1.span());
+		for (lint, error) in lints {
+			self.diagnostics.lint(lint, error);
+		}
+	}
+
+	// Warnings of a successful build, or everything found in a failed one.
+	pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+		std::mem::take(&mut self.diagnostics).into_vec()
+	}
+
+	fn build_oscillator(output: &str, stages: usize) -> (Vec<Device>, Motif) {
+		let wires: Vec<String> = (0..stages)
+			.map(|i| match i {
+				0 => output.to_string(),
+				i => format!("{}.{}", output, i),
+			})
+			.collect();
+		let devices = (0..stages)
+			.map(|i| {
+				Device::Gate(Gate {
+					output: wires[i].to_string(),
+					kind: GateKind::Not,
+					inputs: vec![wires[(i + stages - 1) % stages].to_string()],
+				})
+			})
+			.collect();
+		let motif = Motif {
+			kind: MotifKind::Oscillator,
+			output: output.to_string(),
+			input: None,
+			wires,
+		};
+		(devices, motif)
+	}
+
+	fn build_pulse(output: &str, input: &str) -> (Vec<Device>, Motif) {
+		let fast = format!("{}.0", output);
+		let slow = format!("{}.1", output);
+		let devices = vec![
+			Device::Gate(Gate {
+				output: fast.to_string(),
+				kind: GateKind::Not,
+				inputs: vec![input.to_string()],
+			}),
+			Device::Gate(Gate {
+				output: slow.to_string(),
+				kind: GateKind::Not,
+				inputs: vec![fast.to_string()],
+			}),
+			Device::Gate(Gate {
+				output: output.to_string(),
+				kind: GateKind::Nor,
+				inputs: vec![fast.to_string(), slow.to_string()],
+			}),
+		];
+		let motif = Motif {
+			kind: MotifKind::Pulse,
+			output: output.to_string(),
+			input: Some(input.to_string()),
+			wires: vec![output.to_string(), fast, slow],
+		};
+		(devices, motif)
+	}
+
+	fn build_devices(&self, imp: &Implementation) -> Result<(Vec<Device>, Vec<Motif>), Error> {
 		let mut devices = Vec::new();
+		let mut motifs = Vec::new();
 		for op in &imp.body {
 			match op {
+				Operation::Register(_) => {}
+				Operation::Logic(gop) if gop.symbol.value == "osc" => {
+					let stages = Self::get_num_genes(gop)?;
+					let (batch, motif) = Self::build_oscillator(&gop.var.value, stages);
+					devices.extend(batch);
+					motifs.push(motif);
+				}
+				Operation::Logic(gop) if gop.symbol.value == "pulse" => {
+					let input = gop.args.first().ok_or_else(|| Error::InvalidNumberOfArgs {
+						name: gop.symbol.value.to_string(),
+						span: gop.symbol.span.clone(),
+					})?;
+					let (batch, motif) = Self::build_pulse(&gop.var.value, &input.value);
+					devices.extend(batch);
+					motifs.push(motif);
+				}
 				Operation::Logic(gop) => {
 					let inputs: Vec<String> =
 						gop.args.iter().map(|v| v.value.to_string()).collect();
-					let kind = Self::get_gate_kind(&gop.symbol).unwrap();
+					let kind = Self::get_gate_kind(&gop.symbol)?;
 					devices.push(Device::Gate(Gate {
 						output: gop.var.value.to_string(),
 						kind,
@@ -220,53 +553,282 @@ impl<'a> LogicCircuitBuilder<'a> {
 			}
 		}
 
-		devices
+		Ok((devices, motifs))
 	}
 
-	pub fn build_testbench(&mut self) -> Testbench {
-		let main_test = self.test_tree.get("main").unwrap();
+	pub fn build_exhaustive_testbench(
+		name: &str,
+		env: &Enviroment,
+		hold: u32,
+		ordering: &Ordering,
+	) -> Result<Testbench, Error> {
+		let num_inputs = env.ins.len();
+		let duration = 1u32
+			.checked_shl(num_inputs as u32)
+			.filter(|_| num_inputs <= MAX_EXHAUSTIVE_INPUTS)
+			.and_then(|num_states| num_states.checked_mul(hold).map(|d| (num_states, d)));
+		let (num_states, duration) = duration.ok_or_else(|| Error::InvalidDefinition {
+			name: env.name.value.to_string(),
+			span: env.name.span.clone(),
+			reason: "too many inputs to try every combination".to_string(),
+		})?;
 		let mut at_bp = HashMap::new();
-		for bp in &main_test.body {
+		for state in 0..num_states {
+			let code = match ordering {
+				Ordering::Binary => state,
+				Ordering::Gray => state ^ (state >> 1),
+			};
+			let mut assigns = HashMap::new();
+			for (i, inp) in env.ins.iter().enumerate() {
+				let bit = (code >> (num_inputs - 1 - i)) & 1;
+				assigns.insert(inp.value.to_string(), bit == 1);
+			}
+			at_bp.insert(state * hold, assigns);
+		}
+
+		Ok(Testbench {
+			name: name.to_string(),
+			breakpoints: at_bp,
+			duration,
+			levels: HashMap::new(),
+		})
+	}
+
+	// Steps an exhaustive test holds each combination for unless it sets its
+	// own: enough for a change to ripple through the deepest chain of gates,
+	// each as slow as the slowest gene of the library.
+	pub fn settle_hold(data: &Data, devices: &[Device]) -> u32 {
+		let mut depths: HashMap<&str, u32> = HashMap::new();
+		for device in devices {
+			if let Device::Gate(gate) = device {
+				// Wires driven later on, through a feedback loop, count as inputs.
+				let depth = gate
+					.inputs
+					.iter()
+					.filter_map(|wire| depths.get(wire.as_str()))
+					.max()
+					.map_or(1, |depth| depth + 1);
+				depths.insert(&gate.output, depth);
+			}
+		}
+		let depth = depths.values().max().cloned().unwrap_or(1);
+		let slowest = data
+			.genes
+			.iter()
+			.map(|gene| gene.params.decay)
+			.fold(1.0, f64::min);
+		let steps = steps_to(SETTLE_FRACTION, slowest).ceil() as u32;
+		depth.saturating_mul(steps).max(MIN_HOLD)
+	}
+
+	// The levels each input of the main env is driven with in a test bound to
+	// `env`: those of the signal in the same position of `env`, from its own
+	// library, so the test is simulated with the sensors of its env.
+	fn build_levels(
+		&self,
+		env: &Enviroment,
+		main_env: &Enviroment,
+	) -> Result<HashMap<String, (f64, f64)>, Error> {
+		let same_len = env.ins.len() == main_env.ins.len() && env.outs.len() == main_env.outs.len();
+		Error::invalid_number_of_args(!same_len, &env.name)?;
+		let data = self.get_library(env)?;
+		let mut levels = HashMap::new();
+		for (from, to) in env.ins.iter().zip(&main_env.ins) {
+			Error::not_found(!data.has_signal(&from.value), from)?;
+			let signal = data.get_signal(&from.value);
+			levels.insert(to.value.to_string(), (signal.rpu_off, signal.rpu_on));
+		}
+		Ok(levels)
+	}
+
+	pub fn build_testbench(
+		&self,
+		test: &Test,
+		main_env: &Enviroment,
+		hold: u32,
+	) -> Result<Testbench, Error> {
+		let env = self
+			.env_tree
+			.get(&test.env.value)
+			.ok_or_else(|| Error::Undefined {
+				kind: "env".to_string(),
+				name: test.env.value.to_string(),
+			})?;
+		let levels = self.build_levels(env, main_env)?;
+		if let Some(exhaustive) = &test.exhaustive {
+			let testbench = Self::build_exhaustive_testbench(
+				&test.name.value,
+				main_env,
+				exhaustive.hold.unwrap_or(hold),
+				&exhaustive.ordering,
+			)?;
+			return Ok(Testbench {
+				levels,
+				..testbench
+			});
+		}
+
+		// Tests drive the signals of their own env, while the circuit is built
+		// from the main env, so assignments are mapped over by input position.
+		let signals: HashMap<&str, &str> = env
+			.ins
+			.iter()
+			.zip(main_env.ins.iter())
+			.map(|(from, to)| (from.value.as_str(), to.value.as_str()))
+			.collect();
+
+		let mut at_bp = HashMap::new();
+		for bp in &test.body {
 			let mut assigns = HashMap::new();
 			for ass in &bp.assignments {
-				assigns.insert(ass.iden.value.to_string(), ass.value);
+				let signal = signals.get(ass.iden.value.as_str());
+				let signal = signal.ok_or_else(|| Error::NotFound {
+					name: ass.iden.value.to_string(),
+					span: ass.iden.span.clone(),
+				})?;
+				assigns.insert(signal.to_string(), ass.value);
 			}
 			at_bp.insert(bp.time, assigns);
 		}
 
-		Testbench { breakpoints: at_bp }
+		Ok(Testbench {
+			name: test.name.value.to_string(),
+			breakpoints: at_bp,
+			duration: DEFAULT_DURATION,
+			levels,
+		})
 	}
 
-	pub fn build_logic_circut(&mut self) -> LogicCircuit {
-		let main_mod = self.mod_tree.get("main").unwrap();
-		let main_env = self.env_tree.get("main").unwrap();
-		let main_impl = self.impl_tree.get("main").unwrap();
-		let devices = self.build_devices(&main_impl);
-
-		let mut inputs = Vec::new();
-		let mut outputs = Vec::new();
-		for i in 0..main_mod.ins.len() {
-			let mod_inp = &main_mod.ins[i];
-			let env_inp = &main_env.ins[i];
-			inputs.push(Input {
-				name: mod_inp.value.to_string(),
-				value: env_inp.value.to_string(),
+	pub fn build_testbenches(&self, module: &str, hold: u32) -> Result<Vec<Testbench>, Error> {
+		let main_env = Self::get_main(&self.env_tree, "env")?;
+		let mut testbenches: Vec<Testbench> = self
+			.test_tree
+			.values()
+			.filter(|test| test.module.value == module)
+			.map(|test| self.build_testbench(test, main_env, hold))
+			.collect::<Result<_, _>>()?;
+
+		if testbenches.is_empty() {
+			let testbench =
+				Self::build_exhaustive_testbench(module, main_env, hold, &Ordering::Binary)?;
+			testbenches.push(testbench);
+		}
+
+		testbenches.sort_by(|a, b| a.name.cmp(&b.name));
+		Ok(testbenches)
+	}
+
+	// The library of the main env, extended with the parts and genes defined
+	// in the source. Those only live for this compile and go through the same
+	// validation as the loaded libraries.
+	pub fn build_library(&self) -> Result<Cow<'a, Data>, Error> {
+		let main_env = Self::get_main(&self.env_tree, "env")?;
+		let library = self.get_library(main_env)?;
+		if self.part_defs.is_empty() && self.gene_defs.is_empty() {
+			return Ok(Cow::Borrowed(library));
+		}
+
+		let mut data = library.clone();
+		for (name, part) in &self.part_defs {
+			Error::already_exists(data.parts.contains_key(&part.name), name)?;
+			data.parts.insert(part.name.to_string(), part.clone());
+		}
+		for (name, gene) in &self.gene_defs {
+			let exists = data.genes.iter().any(|known| known.name == gene.name);
+			Error::already_exists(exists, name)?;
+
+			// New repressors and promoters go after the known ones.
+			let num_gates = data.rules.gates.len() as u32;
+			data.rules.gates.entry(gene.group()).or_insert(num_gates);
+			let num_promoters = data.rules.promoters.len() as u32;
+			data.rules
+				.promoters
+				.entry(gene.promoter.to_string())
+				.or_insert(num_promoters);
+			data.genes.push(gene.clone());
+		}
+
+		let report = data.validate();
+		for issue in &report.issues {
+			if issue.severity != Severity::Error {
+				continue;
+			}
+			let defs = self.part_defs.iter().map(|(name, _)| name);
+			let mut defs = defs.chain(self.gene_defs.iter().map(|(name, _)| name));
+			return match defs.find(|name| name.value == issue.item) {
+				Some(name) => Err(Error::InvalidDefinition {
+					name: name.value.to_string(),
+					span: name.span.clone(),
+					reason: issue.message.to_string(),
+				}),
+				None => Err(Error::InvalidLibrary {
+					library: data.name,
+					errors: report.errors(),
+				}),
+			};
+		}
+		Ok(Cow::Owned(data))
+	}
+
+	pub fn build_logic_circut(&mut self, data: &Data) -> Result<LogicCircuit, Error> {
+		let main_mod = Self::get_main(&self.mod_tree, "mod")?;
+		let main_env = Self::get_main(&self.env_tree, "env")?;
+		let main_impl = Self::get_main(&self.impl_tree, "impl")?;
+		let same_len =
+			main_mod.ins.len() == main_env.ins.len() && main_mod.outs.len() == main_env.outs.len();
+		Error::invalid_number_of_args(!same_len, &main_env.name)?;
+
+		let mut num_gates = 0;
+		for op in &main_impl.body {
+			if let Operation::Logic(lop) = op {
+				num_gates += Self::get_num_genes(lop)?;
+			}
+		}
+		// Genes of a group share a repressor, so each gate needs a group of its
+		// own, and the groups of the input signals are taken.
+		let signals: HashSet<&str> = main_env.ins.iter().map(|inp| inp.value.as_str()).collect();
+		let groups: HashSet<String> = data
+			.genes
+			.iter()
+			.map(GeneData::group)
+			.filter(|group| !signals.contains(group.as_str()))
+			.collect();
+		if num_gates > groups.len() {
+			return Err(Error::NotEnoughGenes {
+				needed: num_gates,
+				available: groups.len(),
 			});
 		}
-		for i in 0..main_mod.outs.len() {
-			let mod_out = &main_mod.outs[i];
-			let env_out = &main_env.outs[i];
-			outputs.push(Output {
+
+		let (devices, motifs) = self.build_devices(main_impl)?;
+
+		let inputs = main_mod
+			.ins
+			.iter()
+			.zip(&main_env.ins)
+			.map(|(mod_inp, env_inp)| Input {
+				name: mod_inp.value.to_string(),
+				value: env_inp.value.to_string(),
+			})
+			.collect();
+		let outputs = main_mod
+			.outs
+			.iter()
+			.zip(&main_env.outs)
+			.map(|(mod_out, env_out)| Output {
 				name: mod_out.value.to_string(),
 				value: env_out.value.to_string(),
-			});
-		}
-		let testbench = self.build_testbench();
-		LogicCircuit {
+			})
+			.collect();
+		let hold = Self::settle_hold(data, &devices);
+		let testbenches = self.build_testbenches("main", hold)?;
+		Ok(LogicCircuit {
 			devices,
 			inputs,
 			outputs,
-			testbench,
-		}
+			testbenches,
+			motifs,
+			library: data.name.to_string(),
+		})
 	}
 }
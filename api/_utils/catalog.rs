@@ -0,0 +1,267 @@
+use crate::_utils::{
+	data::{Data, Params, PartKind},
+	error::Error,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Debug)]
+pub struct SignalEntry {
+	pub name: String,
+	pub promoter: String,
+	pub rpu_off: f64,
+	pub rpu_on: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ActuatorEntry {
+	pub name: String,
+	pub length: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PartEntry {
+	pub name: String,
+	pub kind: Option<PartKind>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Variant {
+	pub name: String,
+	pub promoter: String,
+	pub params: Params,
+	pub parts: Vec<PartEntry>,
+}
+
+// Genes of one group are variants of the same repressor, only one of them
+// can be assigned per circuit.
+#[derive(Serialize, Debug)]
+pub struct Family {
+	pub group: String,
+	pub variants: Vec<Variant>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Catalog {
+	pub library: String,
+	pub signals: Vec<SignalEntry>,
+	pub actuators: Vec<ActuatorEntry>,
+	pub families: Vec<Family>,
+}
+
+#[derive(Default, Debug)]
+pub struct Filter {
+	// One of `signals`, `actuators` or `genes`, all of them when `None`.
+	pub kind: Option<String>,
+	// Case insensitive substring of the entry name, or of the group for genes.
+	pub query: Option<String>,
+}
+
+impl Filter {
+	pub fn new(kind: Option<&str>, query: Option<&str>) -> Result<Self, Error> {
+		let kind = kind.map(str::to_lowercase);
+		if let Some(kind) = &kind {
+			if !["signals", "actuators", "genes"].contains(&kind.as_str()) {
+				return Err(Error::InvalidOption {
+					option: "kind".to_string(),
+					reason: "expected signals, actuators or genes".to_string(),
+				});
+			}
+		}
+		Ok(Self {
+			kind,
+			query: query.map(str::to_lowercase),
+		})
+	}
+
+	fn wants(&self, kind: &str) -> bool {
+		self.kind.as_deref().is_none_or(|k| k == kind)
+	}
+
+	fn matches(&self, names: &[&str]) -> bool {
+		match &self.query {
+			Some(query) => names.iter().any(|name| name.to_lowercase().contains(query)),
+			None => true,
+		}
+	}
+}
+
+impl Data {
+	// Everything a source can name, sorted by name.
+	pub fn catalog(&self, filter: &Filter) -> Catalog {
+		let mut signals: Vec<SignalEntry> = self
+			.signals
+			.values()
+			.filter(|sig| filter.wants("signals") && filter.matches(&[&sig.name]))
+			.map(|sig| SignalEntry {
+				name: sig.name(),
+				promoter: sig.promoter(),
+				rpu_off: sig.rpu_off,
+				rpu_on: sig.rpu_on,
+			})
+			.collect();
+		signals.sort_by(|a, b| a.name.cmp(&b.name));
+
+		let mut actuators: Vec<ActuatorEntry> = self
+			.parts
+			.values()
+			.filter(|part| part.kind == PartKind::Actuator)
+			.filter(|part| filter.wants("actuators") && filter.matches(&[&part.name]))
+			.map(|part| ActuatorEntry {
+				name: part.name.to_string(),
+				length: part.seq.len(),
+			})
+			.collect();
+		actuators.sort_by(|a, b| a.name.cmp(&b.name));
+
+		let mut families: BTreeMap<String, Vec<Variant>> = BTreeMap::new();
+		for gene in &self.genes {
+			let group = gene.group();
+			if !filter.wants("genes") || !filter.matches(&[&gene.name, &group]) {
+				continue;
+			}
+			let parts = gene
+				.parts
+				.iter()
+				.map(|name| PartEntry {
+					name: name.to_string(),
+					kind: self.parts.get(name).map(|part| part.kind.clone()),
+				})
+				.collect();
+			families.entry(group).or_default().push(Variant {
+				name: gene.name.to_string(),
+				promoter: gene.promoter.to_string(),
+				params: gene.params.clone(),
+				parts,
+			});
+		}
+		let families = families
+			.into_iter()
+			.map(|(group, mut variants)| {
+				variants.sort_by(|a, b| a.name.cmp(&b.name));
+				Family { group, variants }
+			})
+			.collect();
+
+		Catalog {
+			library: self.name.to_string(),
+			signals,
+			actuators,
+			families,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::_utils::{
+		data::{GeneData, Part},
+		genetic_circuit::Signal,
+	};
+
+	fn params() -> Params {
+		Params {
+			ymax: 2.1,
+			ymin: 0.007,
+			k: 0.1,
+			n: 2.8,
+			decay: 0.1,
+		}
+	}
+
+	fn part(kind: PartKind, name: &str) -> (String, Part) {
+		let part = Part {
+			kind,
+			name: name.to_string(),
+			seq: "ATGC".to_string(),
+		};
+		(name.to_string(), part)
+	}
+
+	fn gene(name: &str, parts: &[&str]) -> GeneData {
+		GeneData {
+			name: name.to_string(),
+			parts: parts.iter().map(|part| part.to_string()).collect(),
+			promoter: format!("p{}", name),
+			params: params(),
+		}
+	}
+
+	// Two variants of SrpR, one of them with a part the library lacks, and
+	// one of AmtR.
+	fn library() -> Data {
+		let mut data = Data::new();
+		data.signals.insert(
+			"LacI".to_string(),
+			Signal {
+				name: "LacI".to_string(),
+				promoter: "pTac".to_string(),
+				rpu_off: 0.0034,
+				rpu_on: 2.8,
+			},
+		);
+		data.parts.extend([
+			part(PartKind::Actuator, "YFP"),
+			part(PartKind::Cds, "SrpR"),
+			part(PartKind::Cds, "AmtR"),
+			part(PartKind::Rbs, "S4"),
+		]);
+		data.genes = vec![
+			gene("S4_SrpR", &["S4", "SrpR"]),
+			gene("S1_SrpR", &["S1", "SrpR"]),
+			gene("A1_AmtR", &["S4", "AmtR"]),
+		];
+		data
+	}
+
+	fn names(catalog: &Catalog) -> Vec<String> {
+		let signals = catalog.signals.iter().map(|sig| sig.name.to_string());
+		let actuators = catalog.actuators.iter().map(|act| act.name.to_string());
+		let genes = catalog
+			.families
+			.iter()
+			.flat_map(|family| family.variants.iter().map(|gene| gene.name.to_string()));
+		signals.chain(actuators).chain(genes).collect()
+	}
+
+	#[test]
+	fn filters_by_kind_and_query() {
+		let data = library();
+		let all = data.catalog(&Filter::default());
+		assert_eq!(
+			names(&all),
+			["LacI", "YFP", "A1_AmtR", "S1_SrpR", "S4_SrpR"]
+		);
+
+		let genes = data.catalog(&Filter::new(Some("Genes"), None).unwrap());
+		assert_eq!(names(&genes), ["A1_AmtR", "S1_SrpR", "S4_SrpR"]);
+
+		// The query matches the group of a gene as well as its name.
+		let srpr = data.catalog(&Filter::new(None, Some("srpr")).unwrap());
+		assert_eq!(srpr.families.len(), 1);
+		assert_eq!(srpr.families[0].group, "SrpR");
+		assert_eq!(names(&srpr), ["S1_SrpR", "S4_SrpR"]);
+
+		let lac = data.catalog(&Filter::new(Some("signals"), Some("lac")).unwrap());
+		assert_eq!(names(&lac), ["LacI"]);
+		let none = data.catalog(&Filter::new(Some("actuators"), Some("lac")).unwrap());
+		assert!(names(&none).is_empty());
+
+		match Filter::new(Some("parts"), None) {
+			Err(Error::InvalidOption { option, .. }) => assert_eq!(option, "kind"),
+			_ => panic!("accepted an unknown kind"),
+		}
+	}
+
+	#[test]
+	fn lists_missing_parts_without_a_kind() {
+		let catalog = library().catalog(&Filter::new(Some("genes"), Some("S1_")).unwrap());
+		let parts = &catalog.families[0].variants[0].parts;
+		let kinds: Vec<(&str, Option<PartKind>)> = parts
+			.iter()
+			.map(|part| (part.name.as_str(), part.kind.clone()))
+			.collect();
+		assert_eq!(kinds, [("S1", None), ("SrpR", Some(PartKind::Cds))]);
+	}
+}
@@ -1,22 +1,62 @@
-use crate::_utils::genetic_circuit;
+use crate::_utils::{error, genetic_circuit, validate};
+use error::Error;
 use fs_extra::file::read_to_string;
 use genetic_circuit::Signal;
-use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
-use serde_json::from_str;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{from_str, ser::PrettyFormatter, Serializer};
 use std::{
-	collections::{HashMap, HashSet},
-	env,
+	collections::{BTreeMap, HashMap, HashSet},
+	env, fs,
+	path::Path,
 };
+use validate::{IssueKind, Report};
 
-static DATA: Lazy<Data> = Lazy::new(|| {
-	let mut d = Data::new();
-	d.load();
-	d
+pub const DEFAULT_LIBRARY: &str = "default";
+
+static STATIC_DIR: OnceCell<String> = OnceCell::new();
+
+static LIBRARIES: Lazy<Libraries> = Lazy::new(|| {
+	let mut libs = Libraries::new();
+	libs.load();
+	libs
 });
 
-pub fn get_data() -> &'static Lazy<Data> {
-	&DATA
+pub fn get_libraries() -> &'static Lazy<Libraries> {
+	&LIBRARIES
+}
+
+// The directory with the default library, `libraries/` and the user parts,
+// `static/` in the working directory unless set before the libraries load.
+pub fn static_dir() -> String {
+	match STATIC_DIR.get() {
+		Some(dir) => dir.to_string(),
+		None => format!("{}/static", env::current_dir().unwrap().display()),
+	}
+}
+
+/// Sets the directory libraries are loaded from, in place of `static/` in the
+/// working directory. False if the directory was already set.
+pub fn set_static_dir(dir: &str) -> bool {
+	STATIC_DIR.set(dir.to_string()).is_ok()
+}
+
+// Formats like the files in `static/`, indented with tabs.
+pub fn to_json<T: Serialize>(value: &T) -> String {
+	let mut out = Vec::new();
+	let mut ser = Serializer::with_formatter(&mut out, PrettyFormatter::with_indent(b"\t"));
+	value.serialize(&mut ser).unwrap();
+	String::from_utf8(out).unwrap()
+}
+
+pub fn write_json<T: Serialize>(path: &str, value: &T) -> Result<(), Error> {
+	fs::write(path, to_json(value)).map_err(|e| Error::InvalidFile {
+		reason: format!("{}: {}", path, e),
+	})
+}
+
+pub fn user_parts_path() -> String {
+	format!("{}/user_parts.json", static_dir())
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
@@ -32,6 +72,22 @@ pub enum PartKind {
 	Actuator,
 }
 
+impl PartKind {
+	pub fn from_name(kind: &str) -> Option<Self> {
+		match kind.to_lowercase().as_str() {
+			"promoter" => Some(PartKind::Promoter),
+			"cds" => Some(PartKind::Cds),
+			"rbs" => Some(PartKind::Rbs),
+			"ribozyme" => Some(PartKind::Ribozyme),
+			"terminator" => Some(PartKind::Terminator),
+			"scar" | "spacer" => Some(PartKind::Scar),
+			"sgrna" => Some(PartKind::SgRNA),
+			"actuator" => Some(PartKind::Actuator),
+			_ => None,
+		}
+	}
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Part {
 	pub kind: PartKind,
@@ -39,6 +95,26 @@ pub struct Part {
 	pub seq: String,
 }
 
+impl Part {
+	pub fn validate(&self) -> Result<(), Error> {
+		if self.seq.is_empty() {
+			return Err(Error::InvalidSequence {
+				part: self.name.to_string(),
+				position: 0,
+				length: 0,
+			});
+		}
+		match self.seq.find(|c| !"ACGTacgt".contains(c)) {
+			Some(pos) => Err(Error::InvalidSequence {
+				part: self.name.to_string(),
+				position: pos,
+				length: 1,
+			}),
+			None => Ok(()),
+		}
+	}
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Params {
 	pub ymax: f64,
@@ -49,6 +125,13 @@ pub struct Params {
 	pub decay: f64,
 }
 
+impl Params {
+	// Hill repression, from `ymax` at no input down to `ymin`.
+	pub fn transfer(&self, x: f64) -> f64 {
+		self.ymin + (self.ymax - self.ymin) / (1.0 + (x / self.k).powf(self.n))
+	}
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct GeneData {
 	pub name: String,
@@ -75,13 +158,15 @@ impl GeneData {
 	}
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default, Clone)]
 pub struct Rules {
 	pub gates: HashMap<String, u32>,
 	pub promoters: HashMap<String, u32>,
 }
 
+#[derive(Default, Clone)]
 pub struct Data {
+	pub name: String,
 	pub genes: Vec<GeneData>,
 	pub parts: HashMap<String, Part>,
 	pub signals: HashMap<String, Signal>,
@@ -92,6 +177,7 @@ pub struct Data {
 impl Data {
 	pub fn new() -> Self {
 		Self {
+			name: DEFAULT_LIBRARY.to_string(),
 			genes: Vec::new(),
 			parts: HashMap::new(),
 			signals: HashMap::new(),
@@ -103,28 +189,52 @@ impl Data {
 		}
 	}
 
-	pub fn load(&mut self) {
-		let dir = env::current_dir().unwrap();
-		let gates_path = format!("{}/static/genes.json", dir.display());
-		let parts_path = format!("{}/static/parts.json", dir.display());
-		let signals_path = format!("{}/static/signals.json", dir.display());
-		let rules_path = format!("{}/static/rules.json", dir.display());
-		let roadblock_path = format!("{}/static/roadblock.json", dir.display());
-
-		let gates_f = read_to_string(gates_path).unwrap();
-		let parts_f = read_to_string(parts_path).unwrap();
-		let signals_f = read_to_string(signals_path).unwrap();
-		let rules_f = read_to_string(rules_path).unwrap();
-		let roadblock_f = read_to_string(roadblock_path).unwrap();
-
-		let genes: Vec<GeneData> = from_str(&gates_f).unwrap();
-		let parts: HashMap<String, Part> = from_str(&parts_f).unwrap();
-		let signals: HashMap<String, Signal> = from_str(&signals_f).unwrap();
-		let rules: HashMap<String, Vec<String>> = from_str(&rules_f).unwrap();
-		let roadblock: HashSet<String> = from_str(&roadblock_f).unwrap();
-
-		let gate_rules = rules.get("gates").unwrap();
-		let promoter_rules = rules.get("promoters").unwrap();
+	// Loads and validates the library in `dir`. The data is only usable when
+	// the report has no errors.
+	pub fn open(name: &str, dir: &str) -> (Self, Report) {
+		let mut data = Data::new();
+		data.name = name.to_string();
+		let loaded = if Path::new(dir).is_dir() {
+			data.load_dir(dir)
+		} else {
+			Err(Error::MissingLibrary {
+				library: name.to_string(),
+				path: dir.to_string(),
+			})
+		};
+		let report = match loaded {
+			Ok(()) => data.validate(),
+			Err(e) => {
+				let mut report = Report::new(name);
+				report.error(IssueKind::InvalidFile, dir, e.to_string());
+				report
+			}
+		};
+		(data, report)
+	}
+
+	pub fn load_dir(&mut self, dir: &str) -> Result<(), Error> {
+		fn read<T: DeserializeOwned>(path: &str) -> Result<T, Error> {
+			let file = read_to_string(path).map_err(|e| Error::InvalidFile {
+				reason: format!("{}: {}", path, e),
+			})?;
+			from_str(&file).map_err(|e| Error::InvalidFile {
+				reason: format!("{}: {}", path, e),
+			})
+		}
+
+		let genes: Vec<GeneData> = read(&format!("{}/genes.json", dir))?;
+		let parts: HashMap<String, Part> = read(&format!("{}/parts.json", dir))?;
+		let signals: HashMap<String, Signal> = read(&format!("{}/signals.json", dir))?;
+		let rules_path = format!("{}/rules.json", dir);
+		let rules: HashMap<String, Vec<String>> = read(&rules_path)?;
+		let roadblock: HashSet<String> = read(&format!("{}/roadblock.json", dir))?;
+
+		let missing = |key: &str| Error::InvalidFile {
+			reason: format!("{}: missing {}", rules_path, key),
+		};
+		let gate_rules = rules.get("gates").ok_or_else(|| missing("gates"))?;
+		let promoter_rules = rules.get("promoters").ok_or_else(|| missing("promoters"))?;
 		let new_rules: Rules = Rules {
 			gates: gate_rules
 				.iter()
@@ -138,17 +248,88 @@ impl Data {
 				.collect(),
 		};
 
+		let mut parts = parts;
+		let user_parts_path = format!("{}/user_parts.json", dir);
+		if Path::new(&user_parts_path).exists() {
+			let user_parts: HashMap<String, Part> = read(&user_parts_path)?;
+			for (name, part) in user_parts {
+				parts.entry(name).or_insert(part);
+			}
+		}
+
 		self.genes = genes;
 		self.parts = parts;
 		self.signals = signals;
 		self.rules = new_rules;
 		self.roadblock = roadblock;
+		Ok(())
+	}
+
+	// Writes the library in the same layout `load_dir` reads.
+	pub fn save(&self, dir: &str) -> Result<(), Error> {
+		fs::create_dir_all(dir).map_err(|e| Error::InvalidFile {
+			reason: format!("{}: {}", dir, e),
+		})?;
+		fn ordered(rules: &HashMap<String, u32>) -> Vec<&String> {
+			let mut names: Vec<&String> = rules.keys().collect();
+			names.sort_by_key(|name| rules[*name]);
+			names
+		}
+		let mut rules = BTreeMap::new();
+		rules.insert("gates", ordered(&self.rules.gates));
+		rules.insert("promoters", ordered(&self.rules.promoters));
+		let parts: BTreeMap<_, _> = self.parts.iter().collect();
+		let signals: BTreeMap<_, _> = self.signals.iter().collect();
+		let mut roadblock: Vec<_> = self.roadblock.iter().collect();
+		roadblock.sort();
+
+		write_json(&format!("{}/genes.json", dir), &self.genes)?;
+		write_json(&format!("{}/parts.json", dir), &parts)?;
+		write_json(&format!("{}/signals.json", dir), &signals)?;
+		write_json(&format!("{}/rules.json", dir), &rules)?;
+		write_json(&format!("{}/roadblock.json", dir), &roadblock)
+	}
+
+	// Adds the imported parts to a user library, skipping the ones that are
+	// already known with the same sequence. Returns the names of added parts.
+	pub fn import_parts(
+		&self,
+		library: &mut HashMap<String, Part>,
+		parts: Vec<Part>,
+	) -> Result<Vec<String>, Error> {
+		let mut added = Vec::new();
+		for mut part in parts {
+			part.validate()?;
+			part.seq = part.seq.to_uppercase();
+			let existing = self
+				.parts
+				.get(&part.name)
+				.or_else(|| library.get(&part.name));
+			match existing {
+				Some(other)
+					if other.seq.eq_ignore_ascii_case(&part.seq) && other.kind == part.kind =>
+				{
+					continue
+				}
+				Some(_) => return Err(Error::PartCollision { part: part.name }),
+				None => {}
+			}
+			added.push(part.name.to_string());
+			library.insert(part.name.to_string(), part);
+		}
+		Ok(added)
 	}
 
 	pub fn get_part(&self, name: &str) -> &Part {
 		self.parts.get(name).unwrap()
 	}
 
+	// Libraries imported from elsewhere may come without backbones, in which
+	// case the plasmids only hold the circuit itself.
+	pub fn get_backbone(&self, name: &str) -> Vec<&Part> {
+		self.parts.get(name).into_iter().collect()
+	}
+
 	pub fn get_gene_at(&self, i: usize) -> &GeneData {
 		self.genes.get(i).unwrap()
 	}
@@ -181,3 +362,112 @@ impl Data {
 		self.signals.len()
 	}
 }
+
+// Every library is a directory laid out like `static/`. The one in `static/`
+// itself is the default, the others live in `static/libraries/<name>/`.
+// Libraries that fail validation are left out, but keep their report.
+#[derive(Default)]
+pub struct Libraries {
+	libraries: HashMap<String, Data>,
+	reports: HashMap<String, Report>,
+	// Directories of the libraries that don't exist, by library.
+	missing: HashMap<String, String>,
+}
+
+impl Libraries {
+	pub fn new() -> Self {
+		Self {
+			libraries: HashMap::new(),
+			reports: HashMap::new(),
+			missing: HashMap::new(),
+		}
+	}
+
+	pub fn load(&mut self) {
+		let dir = static_dir();
+		self.load_library(DEFAULT_LIBRARY, &dir);
+
+		let libraries_dir = format!("{}/libraries", dir);
+		let entries = match fs::read_dir(&libraries_dir) {
+			Ok(entries) => entries,
+			Err(_) => return,
+		};
+		for entry in entries.flatten() {
+			if !entry.path().is_dir() {
+				continue;
+			}
+			let name = entry.file_name().to_string_lossy().to_string();
+			self.load_library(&name, &entry.path().display().to_string());
+		}
+	}
+
+	pub fn load_library(&mut self, name: &str, dir: &str) {
+		if !Path::new(dir).is_dir() {
+			self.missing.insert(name.to_string(), dir.to_string());
+			return;
+		}
+		let (data, report) = Data::open(name, dir);
+		if report.is_valid() {
+			self.insert(data);
+		}
+		self.reports.insert(name.to_string(), report);
+	}
+
+	pub fn insert(&mut self, data: Data) {
+		self.libraries.insert(data.name.to_string(), data);
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Data> {
+		self.libraries.get(name)
+	}
+
+	pub fn require(&self, name: &str) -> Result<&Data, Error> {
+		match (self.libraries.get(name), self.reports.get(name)) {
+			(Some(data), _) => Ok(data),
+			(None, Some(report)) => Err(Error::InvalidLibrary {
+				library: name.to_string(),
+				errors: report.errors(),
+			}),
+			(None, None) => Err(match self.missing.get(name) {
+				Some(path) => Error::MissingLibrary {
+					library: name.to_string(),
+					path: path.to_string(),
+				},
+				None => Error::UnknownLibrary {
+					library: name.to_string(),
+				},
+			}),
+		}
+	}
+
+	pub fn report(&self, name: &str) -> Option<&Report> {
+		self.reports.get(name)
+	}
+
+	pub fn names(&self) -> Vec<String> {
+		let mut names: Vec<String> = self.libraries.keys().cloned().collect();
+		names.sort();
+		names
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn missing_library_names_its_directory() {
+		let dir = "/nonexistent/static";
+		let mut libraries = Libraries::new();
+		libraries.load_library(DEFAULT_LIBRARY, dir);
+		match libraries.require(DEFAULT_LIBRARY) {
+			Err(e @ Error::MissingLibrary { .. }) => assert!(e.to_string().contains(dir)),
+			Err(e) => panic!("{}", e),
+			Ok(_) => panic!("loaded a library from {}", dir),
+		}
+
+		let (_, report) = Data::open(DEFAULT_LIBRARY, dir);
+		assert_eq!(report.errors(), 1);
+		assert!(report.issues[0].message.contains(dir));
+	}
+}
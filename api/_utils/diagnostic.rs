@@ -0,0 +1,235 @@
+use crate::_utils::{error::Error, lexer, lint::Lint, span, validate::Severity};
+use lexer::{Token, TokenKind};
+use serde::Serialize;
+use span::Span;
+
+const MAX_SUGGESTIONS: usize = 3;
+
+// An edit an editor can apply, replacing `span` with `replacement`.
+#[derive(Serialize, Debug, Clone)]
+pub struct Fix {
+	pub message: String,
+	pub span: Span,
+	pub replacement: String,
+}
+
+// Edit distance over chars, where swapping two neighbours counts as one edit.
+fn distance(a: &str, b: &str) -> usize {
+	let a: Vec<char> = a.chars().collect();
+	let b: Vec<char> = b.chars().collect();
+	let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+	for (i, row) in rows.iter_mut().enumerate() {
+		row[0] = i;
+	}
+	rows[0] = (0..=b.len()).collect();
+	for i in 1..=a.len() {
+		for j in 1..=b.len() {
+			let cost = (a[i - 1] != b[j - 1]) as usize;
+			let mut d = (rows[i - 1][j] + 1)
+				.min(rows[i][j - 1] + 1)
+				.min(rows[i - 1][j - 1] + cost);
+			if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+				d = d.min(rows[i - 2][j - 2] + 1);
+			}
+			rows[i][j] = d;
+		}
+	}
+	rows[a.len()][b.len()]
+}
+
+// Names in `candidates` close enough to `name` to be a typo of it, closest
+// first. Differences in case alone count as closest.
+pub fn similar<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+	let lower = name.to_lowercase();
+	let limit = (name.chars().count() / 3).max(1);
+	let mut close: Vec<(usize, usize, &str)> = candidates
+		.into_iter()
+		.filter(|candidate| *candidate != name)
+		.map(|candidate| {
+			let folded = distance(&lower, &candidate.to_lowercase());
+			(folded, distance(name, candidate), candidate)
+		})
+		.filter(|(folded, _, _)| *folded <= limit)
+		.collect();
+	close.sort_unstable();
+	close.dedup();
+	close
+		.into_iter()
+		.take(MAX_SUGGESTIONS)
+		.map(|(_, _, candidate)| candidate)
+		.collect()
+}
+
+// An error or warning found in a source. The error is flattened in, so a
+// diagnostic reads as `{ severity, code, message, kind, data }`, with the
+// lint that raised a warning and the rendered snippet when there are any.
+#[derive(Serialize, Debug)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub code: &'static str,
+	pub message: String,
+	#[serde(flatten)]
+	pub error: Error,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub lint: Option<Lint>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub fixes: Vec<Fix>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub rendered: Option<String>,
+}
+
+impl Diagnostic {
+	pub fn error(error: Error) -> Self {
+		Self {
+			severity: Severity::Error,
+			code: error.code(),
+			message: error.message(),
+			error,
+			lint: None,
+			fixes: Vec::new(),
+			rendered: None,
+		}
+	}
+
+	pub fn warning(error: Error) -> Self {
+		Self {
+			severity: Severity::Warning,
+			..Self::error(error)
+		}
+	}
+
+	pub fn lint(lint: Lint, error: Error) -> Self {
+		Self {
+			lint: Some(lint),
+			..Self::warning(error)
+		}
+	}
+
+	// `NotFound` for `token` if `condition`, suggesting the closest of
+	// `candidates`.
+	pub fn not_found<'a>(
+		condition: bool,
+		token: &Token,
+		candidates: impl IntoIterator<Item = &'a str>,
+	) -> Result<(), Self> {
+		Error::not_found(condition, token)
+			.map_err(|error| Self::error(error).with_suggestions(token, candidates))
+	}
+
+	// Fixes replacing `token` with each name of `candidates` similar to it.
+	pub fn with_suggestions<'a>(
+		mut self,
+		token: &Token,
+		candidates: impl IntoIterator<Item = &'a str>,
+	) -> Self {
+		for name in similar(&token.value, candidates) {
+			let replacement = match token.kind {
+				TokenKind::Literal => format!("\"{}\"", name),
+				_ => name.to_string(),
+			};
+			self.fixes.push(Fix {
+				message: format!("did you mean `{}`?", name),
+				span: token.span.clone(),
+				replacement,
+			});
+		}
+		self
+	}
+
+	pub fn is_error(&self) -> bool {
+		self.severity == Severity::Error
+	}
+
+	// The diagnostic with its source line underlined, or on its own line if
+	// it has no span.
+	pub fn render(&self, source: &str) -> String {
+		let level = match self.severity {
+			Severity::Error => format!("error[{}]", self.code),
+			Severity::Warning => format!("warning[{}]", self.code),
+		};
+		let mut out = match self.error.span() {
+			Some(span) => span::render(source, span, &level, &self.message, &self.error.label()),
+			None => format!("{}: {}", level, self.message),
+		};
+		let gutter = self
+			.error
+			.span()
+			.map_or(0, |span| span.line.to_string().len());
+		let gutter = " ".repeat(gutter);
+		for fix in &self.fixes {
+			out += &format!("\n{} = help: {}", gutter, fix.message);
+		}
+		if let Some(lint) = self.lint {
+			out += &format!(
+				"\n{} = note: silence with `#[allow({})]`",
+				gutter,
+				lint.name()
+			);
+		}
+		out
+	}
+
+	// Fills in `rendered` for diagnostics with a span.
+	pub fn with_rendered(mut self, source: &str) -> Self {
+		if self.error.span().is_some() {
+			self.rendered = Some(self.render(source));
+		}
+		self
+	}
+}
+
+// Collects diagnostics so checks can go on past the first error.
+#[derive(Default, Debug)]
+pub struct Diagnostics {
+	items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+	pub fn new() -> Self {
+		Self { items: Vec::new() }
+	}
+
+	pub fn error(&mut self, error: Error) {
+		self.items.push(Diagnostic::error(error));
+	}
+
+	pub fn warning(&mut self, error: Error) {
+		self.items.push(Diagnostic::warning(error));
+	}
+
+	pub fn lint(&mut self, lint: Lint, error: Error) {
+		self.items.push(Diagnostic::lint(lint, error));
+	}
+
+	// Keeps the error of `res`, if any.
+	pub fn check<E: Into<Diagnostic>>(&mut self, res: Result<(), E>) {
+		if let Err(error) = res {
+			self.items.push(error.into());
+		}
+	}
+
+	pub fn has_errors(&self) -> bool {
+		self.items.iter().any(Diagnostic::is_error)
+	}
+
+	pub fn items(&self) -> &[Diagnostic] {
+		&self.items
+	}
+
+	pub fn into_vec(self) -> Vec<Diagnostic> {
+		self.items
+	}
+}
+
+impl From<Error> for Diagnostic {
+	fn from(error: Error) -> Self {
+		Self::error(error)
+	}
+}
+
+// Errors past parsing end the compile on their own.
+impl From<Error> for Vec<Diagnostic> {
+	fn from(error: Error) -> Self {
+		vec![Diagnostic::error(error)]
+	}
+}
@@ -1,7 +1,12 @@
-use crate::_utils::data::PartKind;
+use crate::_utils::{
+	data::{Part, PartKind},
+	error::Error,
+};
 use chrono::Utc;
 use serde::Serialize;
 
+/// The sequences of a circuit, the gates on one plasmid and the outputs on
+/// another, each raw and as a GenBank file.
 #[derive(Serialize, Debug)]
 pub struct Dna {
 	pub raw: String,
@@ -10,46 +15,436 @@ pub struct Dna {
 	pub out_plasmid: String,
 }
 
+#[derive(Serialize, Debug, Clone)]
+pub struct Feature {
+	pub kind: PartKind,
+	pub name: String,
+	pub start: usize,
+	pub end: usize,
+	pub color: String,
+	pub note: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Plasmid {
+	pub name: String,
+	pub definition: String,
+	pub seq: String,
+	pub features: Vec<Feature>,
+}
+
+impl PartKind {
+	pub fn feature_key(&self) -> &'static str {
+		match self {
+			PartKind::Promoter => "promoter",
+			PartKind::Cds => "CDS",
+			PartKind::Rbs => "RBS",
+			PartKind::Terminator => "terminator",
+			_ => "misc_feature",
+		}
+	}
+
+	pub fn from_feature_key(key: &str, qualifiers: &[(String, String)]) -> Option<Self> {
+		let qualifier = |name: &str| {
+			qualifiers
+				.iter()
+				.find(|(key, _)| key == name)
+				.map(|(_, value)| value.as_str())
+		};
+		match key {
+			"promoter" => Some(PartKind::Promoter),
+			"CDS" => Some(PartKind::Cds),
+			"RBS" => Some(PartKind::Rbs),
+			"terminator" => Some(PartKind::Terminator),
+			"regulatory" => match qualifier("regulatory_class")? {
+				"promoter" => Some(PartKind::Promoter),
+				"ribosome_binding_site" => Some(PartKind::Rbs),
+				"terminator" => Some(PartKind::Terminator),
+				_ => None,
+			},
+			"ncRNA" => match qualifier("ncRNA_class")? {
+				"ribozyme" | "autocatalytically_spliced_intron" => Some(PartKind::Ribozyme),
+				"guide_RNA" => Some(PartKind::SgRNA),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+}
+
+struct GenbankFeature {
+	key: String,
+	location: String,
+	qualifiers: Vec<(String, String)>,
+}
+
+fn reverse_complement(seq: &str) -> String {
+	seq.chars()
+		.rev()
+		.map(|c| match c {
+			'A' => 'T',
+			'T' => 'A',
+			'C' => 'G',
+			'G' => 'C',
+			'a' => 't',
+			't' => 'a',
+			'c' => 'g',
+			'g' => 'c',
+			other => other,
+		})
+		.collect()
+}
+
+// Reads `12..40`, `<12..>40`, `7` and `complement(...)` of those, returning the
+// zero based range and whether the feature is on the reverse strand.
+fn parse_location(location: &str) -> Option<(usize, usize, bool)> {
+	if let Some(inner) = location
+		.strip_prefix("complement(")
+		.and_then(|rest| rest.strip_suffix(')'))
+	{
+		let (start, end, _) = parse_location(inner)?;
+		return Some((start, end, true));
+	}
+
+	let bound = |x: &str| {
+		x.trim_matches(|c| c == '<' || c == '>')
+			.parse::<usize>()
+			.ok()
+	};
+	let (start, end) = match location.split_once("..") {
+		Some((start, end)) => (bound(start)?, bound(end)?),
+		None => (bound(location)?, bound(location)?),
+	};
+	if start == 0 || end < start {
+		return None;
+	}
+	Some((start - 1, end, false))
+}
+
+impl Part {
+	// Reads every record of a GenBank file and turns the features with a known
+	// key into parts, named after their /label (or /gene, /product). Features
+	// without a name or spanning more than one range (`join(...)`) are
+	// skipped, with a warning for each.
+	pub fn from_genbank(text: &str) -> Result<(Vec<Part>, Vec<String>), Error> {
+		let mut parts = Vec::new();
+		let mut warnings = Vec::new();
+		for record in text.split("\n//").filter(|r| !r.trim().is_empty()) {
+			let mut features: Vec<GenbankFeature> = Vec::new();
+			let mut seq = String::new();
+			let mut section = "";
+			for line in record.lines() {
+				if !line.starts_with(' ') && !line.trim().is_empty() {
+					section = line.split_whitespace().next().unwrap_or("");
+					continue;
+				}
+
+				match section {
+					"FEATURES" if !line.get(5..21).unwrap_or("").trim().is_empty() => {
+						let key = line[5..21].trim().to_string();
+						features.push(GenbankFeature {
+							key,
+							location: line[21..].trim().to_string(),
+							qualifiers: Vec::new(),
+						});
+					}
+					"FEATURES" if line.get(21..).is_some() => {
+						let feature = features.last_mut().ok_or_else(|| Error::InvalidFile {
+							reason: "qualifier before feature".into(),
+						})?;
+						let value = line[21..].trim();
+						match value.strip_prefix('/') {
+							Some(qualifier) => {
+								let (key, value) =
+									qualifier.split_once('=').unwrap_or((qualifier, ""));
+								feature
+									.qualifiers
+									.push((key.to_string(), value.trim_matches('"').to_string()));
+							}
+							None => match feature.qualifiers.last_mut() {
+								Some((_, last)) => {
+									last.push(' ');
+									last.push_str(value.trim_matches('"'));
+								}
+								None => feature.location.push_str(value),
+							},
+						}
+					}
+					"ORIGIN" => {
+						seq.extend(line.chars().filter(|c| c.is_ascii_alphabetic()));
+					}
+					_ => {}
+				}
+			}
+
+			for GenbankFeature {
+				key,
+				location,
+				qualifiers,
+			} in &features
+			{
+				let kind = match PartKind::from_feature_key(key, qualifiers) {
+					Some(kind) => kind,
+					None => continue,
+				};
+				let name = ["label", "gene", "product", "standard_name"]
+					.iter()
+					.find_map(|name| qualifiers.iter().find(|(key, _)| key == name))
+					.map(|(_, value)| value.to_string());
+				let name = match name {
+					Some(name) => name,
+					None => {
+						warnings.push(format!("{} at {} has no /label, skipped", key, location));
+						continue;
+					}
+				};
+				let bounds = parse_location(location).filter(|(_, end, _)| *end <= seq.len());
+				let (start, end, reverse) = match bounds {
+					Some(bounds) => bounds,
+					None => {
+						warnings.push(format!(
+							"{} has unsupported location {}, skipped",
+							name, location
+						));
+						continue;
+					}
+				};
+
+				let part_seq = &seq[start..end];
+				parts.push(Part {
+					kind,
+					name,
+					seq: if reverse {
+						reverse_complement(part_seq)
+					} else {
+						part_seq.to_string()
+					},
+				});
+			}
+		}
+		Ok((parts, warnings))
+	}
+}
+
+impl Plasmid {
+	pub fn new(name: &str, definition: &str) -> Self {
+		Self {
+			name: name.to_string(),
+			definition: definition.to_string(),
+			seq: String::new(),
+			features: Vec::new(),
+		}
+	}
+
+	pub fn push(&mut self, part: &Part, color: &str, note: Option<String>) {
+		let start = self.seq.len();
+		self.seq.push_str(&part.seq);
+		self.features.push(Feature {
+			kind: part.kind.clone(),
+			name: part.name.to_string(),
+			start,
+			end: self.seq.len(),
+			color: color.to_string(),
+			note,
+		});
+	}
+
+	pub fn push_all(&mut self, parts: Vec<&Part>) {
+		for part in parts {
+			self.push(part, "white", None);
+		}
+	}
+
+	pub fn to_genbank(&self) -> String {
+		Dna::make_plasmid_title(&self.name, &self.definition, self.seq.len())
+			+ &self
+				.features
+				.iter()
+				.map(Dna::make_plasmid_part)
+				.collect::<String>()
+			+ &Dna::make_plasmid_dna(&self.seq)
+	}
+}
+
 impl Dna {
+	pub fn from_plasmids(gates: &Plasmid, output: &Plasmid) -> Self {
+		Self {
+			raw: gates.seq.to_string(),
+			plasmid: gates.to_genbank(),
+			out_raw: output.seq.to_string(),
+			out_plasmid: output.to_genbank(),
+		}
+	}
+
 	pub fn make_plasmid_dna(seq: &str) -> String {
-		return "ORIGIN\n".to_string()
+		"ORIGIN\n".to_string()
 			+ &seq
+				.to_lowercase()
 				.as_bytes()
 				.chunks(60)
 				.enumerate()
 				.map(|(i, chunk)| {
 					let ch: Vec<String> = chunk
 						.chunks(10)
-						.map(|x| {
-							let parsed: String = std::str::from_utf8(x).unwrap().to_string();
-							parsed
-						})
+						.map(|x| String::from_utf8_lossy(x).to_string())
 						.collect();
 					let index_fmt = format!("{:>9}", (i * 60) + 1);
-					format!("{} {}", index_fmt, ch.join(" "))
+					format!("{} {}\n", index_fmt, ch.join(" "))
 				})
-				.collect::<Vec<String>>()
-				.join("\n");
+				.collect::<String>()
+			+ "//\n"
 	}
 
-	pub fn make_plasmid_title(name: &str, len: usize) -> String {
+	pub fn make_plasmid_title(name: &str, definition: &str, len: usize) -> String {
+		let date = Utc::now()
+			.date_naive()
+			.format("%d-%b-%Y")
+			.to_string()
+			.to_uppercase();
 		format!(
-            "LOCUS      {}      {} bp ds-Dna      circular      {}\nFEATURES             Location/Qualifiers\n",
-            name,
-            len,
-            Utc::today().format("%e-%b-%Y")
-        )
-	}
-
-	pub fn make_plasmid_part(
-		kind: &PartKind,
-		start: usize,
-		end: usize,
-		label: &str,
-		color: &str,
-	) -> String {
-		return format!("     {:<16}{}..{}\n", format!("{:?}", kind), start + 1, end)
-			+ &format!("                     /label={}\n", label)
-			+ &format!("                     /ApEinfo_fwdcolor={}\n", color);
+			"LOCUS       {:<16} {:>11} bp    DNA     circular SYN {}\n",
+			name, len, date
+		) + &format!("DEFINITION  {}\n", definition)
+			+ "ACCESSION   .\n"
+			+ "VERSION     .\n"
+			+ "KEYWORDS    .\n"
+			+ "SOURCE      synthetic DNA construct\n"
+			+ "  ORGANISM  synthetic DNA construct\n"
+			+ "            .\n"
+			+ "FEATURES             Location/Qualifiers\n"
+			+ &format!("     {:<16}1..{}\n", "source", len)
+			+ "                     /organism=\"synthetic DNA construct\"\n"
+			+ "                     /mol_type=\"other DNA\"\n"
+	}
+
+	pub fn make_plasmid_part(feature: &Feature) -> String {
+		let mut part = format!(
+			"     {:<16}{}..{}\n",
+			feature.kind.feature_key(),
+			feature.start + 1,
+			feature.end
+		) + &format!("                     /label={}\n", feature.name)
+			+ &format!("                     /ApEinfo_fwdcolor={}\n", feature.color);
+		if let Some(note) = &feature.note {
+			part += &format!(
+				"                     /note=\"{}\"\n",
+				note.replace('"', "'")
+			);
+		}
+		part
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::NaiveDate;
+
+	fn part(kind: PartKind, name: &str, seq: &str) -> Part {
+		Part {
+			kind,
+			name: name.to_string(),
+			seq: seq.to_string(),
+		}
+	}
+
+	#[test]
+	fn genbank_round_trip() {
+		let parts = vec![
+			part(
+				PartKind::Promoter,
+				"pTac",
+				"AACGATCGTTGGCTGTGTTGACAATTAATCATCGG",
+			),
+			part(PartKind::Rbs, "B0034", "AAAGAGGAGAAA"),
+			part(
+				PartKind::Cds,
+				"YFP",
+				"ATGGTGAGCAAGGGCGAGGAGCTGTTCACCGGGGTGGTGCCCATCCTGGTCGAGCTGGACGGCGACGTAAACGGC",
+			),
+			part(
+				PartKind::Terminator,
+				"L3S2P21",
+				"CTCGGTACCAAATTCCAGAAAAGAGGCCTCCC",
+			),
+		];
+		let mut plasmid = Plasmid::new("test-plasmid", "Parts of a round trip.");
+		for part in &parts {
+			plasmid.push(part, "#ff0000", Some("y = not(a)".to_string()));
+		}
+		let len = plasmid.seq.len();
+		let text = plasmid.to_genbank();
+
+		let locus = text.lines().next().unwrap();
+		let head = format!(
+			"LOCUS       test-plasmid     {:>11} bp    DNA     circular SYN ",
+			len
+		);
+		let date = locus.strip_prefix(&head).unwrap();
+		assert!(
+			NaiveDate::parse_from_str(date, "%d-%b-%Y").is_ok(),
+			"{}",
+			locus
+		);
+
+		// Feature lines have their key at column 5 and the location at 21.
+		let table = text.split("ORIGIN").next().unwrap();
+		let features: Vec<(&str, &str)> = table
+			.lines()
+			.filter(|line| line.len() > 21 && line.starts_with("     "))
+			.filter(|line| !line[5..21].trim().is_empty())
+			.map(|line| (line[5..21].trim(), line[21..].trim()))
+			.collect();
+		let lengths: usize = parts[..3].iter().map(|part| part.seq.len()).sum();
+		let expected = [
+			("source", format!("1..{}", len)),
+			("promoter", "1..35".to_string()),
+			("RBS", "36..47".to_string()),
+			("CDS", format!("48..{}", lengths)),
+			("terminator", format!("{}..{}", lengths + 1, len)),
+		];
+		let expected: Vec<(&str, &str)> = expected
+			.iter()
+			.map(|(key, location)| (*key, location.as_str()))
+			.collect();
+		assert_eq!(features, expected);
+
+		let origin = text.split("ORIGIN").nth(1).unwrap();
+		let seq: String = origin.chars().filter(char::is_ascii_alphabetic).collect();
+		assert_eq!(seq, plasmid.seq.to_lowercase());
+		assert!(text.ends_with("//\n"));
+
+		let (read, warnings) = Part::from_genbank(&text).unwrap();
+		assert!(warnings.is_empty(), "{:?}", warnings);
+		assert_eq!(read.len(), parts.len());
+		for (read, part) in read.iter().zip(&parts) {
+			assert_eq!(read.kind, part.kind);
+			assert_eq!(read.name, part.name);
+			assert_eq!(read.seq.to_uppercase(), part.seq);
+		}
+	}
+	#[test]
+	fn genbank_skips_unusable_features() {
+		let text = "LOCUS       mixed       28 bp    DNA     linear SYN 01-JAN-2020
+FEATURES             Location/Qualifiers
+     promoter        1..8
+                     /label=\"pA\"
+     CDS             join(9..12,17..20)
+                     /gene=\"split\"
+     terminator      21..24
+                     /note=\"no name\"
+     RBS             complement(13..16)
+                     /product=\"r1\"
+ORIGIN
+        1 ttgacaat atgcaaag gagttaaa tttt
+//
+";
+		let (parts, warnings) = Part::from_genbank(text).unwrap();
+		let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+		assert_eq!(names, ["pA", "r1"]);
+		assert_eq!(parts[1].seq, "cttt");
+		assert_eq!(warnings.len(), 2, "{:?}", warnings);
+		assert!(warnings[0].contains("join(9..12,17..20)"));
+		assert!(warnings[1].starts_with("terminator at 21..24"));
 	}
 }
@@ -1,61 +1,227 @@
-use crate::_utils::lexer;
+use crate::_utils::{lexer, span};
 use lexer::Token;
 use serde::Serialize;
+use span::Span;
+use thiserror::Error as ThisError;
 
-#[derive(Serialize, Debug)]
+// Every variant has a stable code, `E` and its position here, so new ones go
+// at the end. `emc explain <code>` describes each of them at length.
+#[derive(Serialize, ThisError, Debug)]
 #[serde(tag = "kind", content = "data")]
 pub enum Error {
-	UnexpectedToken(String, usize, usize),
-	AlreadyExists(String, usize, usize),
-	NotFound(String, usize, usize),
-	NotUsed(String, usize, usize),
-	NotEnoughGenes,
-	InvalidNumberOfArgs(String, usize, usize),
-	EndOfFile,
+	#[error("unexpected token `{token}` at {span}")]
+	UnexpectedToken { token: String, span: Span },
+	#[error("`{name}` at {span} already exists")]
+	AlreadyExists { name: String, span: Span },
+	#[error("`{name}` at {span} not found")]
+	NotFound { name: String, span: Span },
+	#[error("`{name}` at {span} is never used")]
+	NotUsed { name: String, span: Span },
+	#[error("the circuit needs {needed} genes, but the library has {available} gene groups to pick from")]
+	NotEnoughGenes { needed: usize, available: usize },
+	#[error("invalid number of arguments for `{name}` at {span}")]
+	InvalidNumberOfArgs { name: String, span: Span },
+	#[error("`{name}` at {span} is invalid: {reason}")]
+	InvalidDefinition {
+		name: String,
+		span: Span,
+		reason: String,
+	},
+	#[error("unexpected end of file at {span}")]
+	EndOfFile { span: Span },
+	#[error("`{wire}` at {span} is always {value}")]
+	ConstantOutput {
+		wire: String,
+		span: Span,
+		value: bool,
+	},
+	#[error("`{wire}` at {span} inverts `{input}` twice")]
+	DoubleInversion {
+		wire: String,
+		span: Span,
+		input: String,
+	},
+	#[error("`{wire}` at {span} is {depth} gates deep")]
+	DeepChain {
+		wire: String,
+		span: Span,
+		depth: usize,
+	},
+	#[error("signal `{signal}` at {span} is bound to `{input}`, which is never read")]
+	UnreadSignal {
+		signal: String,
+		span: Span,
+		input: String,
+	},
+	#[error("{kind} `{name}` is not defined")]
+	Undefined { kind: String, name: String },
+	#[error("wire `{wire}` is not driven by an input or a gate")]
+	Undriven { wire: String },
+	#[error("`{name}` is not a signal")]
+	NotASignal { name: String },
+	#[error("request body is not valid {encoding}")]
+	InvalidBody { encoding: String },
+	#[error("invalid file: {reason}")]
+	InvalidFile { reason: String },
+	#[error("part `{part}` has an invalid base at {position}")]
+	InvalidSequence {
+		part: String,
+		position: usize,
+		length: usize,
+	},
+	#[error("part `{part}` already exists with a different sequence")]
+	PartCollision { part: String },
+	#[error("library `{library}` not found")]
+	UnknownLibrary { library: String },
+	#[error("library `{library}` has {errors} errors, run `emc validate-library` for details")]
+	InvalidLibrary { library: String, errors: usize },
+	#[error("{failures} checks of {tests} tests failed")]
+	TestFailed { tests: usize, failures: usize },
+	#[error("invalid option `{option}`: {reason}")]
+	InvalidOption { option: String, reason: String },
+	#[error("library `{library}` not found, {path} is not a directory")]
+	MissingLibrary { library: String, path: String },
+	#[error("measurements can't be fit: {reason}")]
+	InvalidMeasurements { reason: String },
+	#[error("{failed} of {sources} sources failed to compile")]
+	CompileFailed { failed: usize, sources: usize },
 }
 
 impl Error {
 	pub fn already_exists(condition: bool, token: &Token) -> Result<(), Self> {
 		if condition {
-			return Err(Self::AlreadyExists(
-				token.value.to_string(),
-				token.pos,
-				token.value.len(),
-			));
+			return Err(Self::AlreadyExists {
+				name: token.value.to_string(),
+				span: token.span.clone(),
+			});
 		}
 		Ok(())
 	}
 
 	pub fn invalid_number_of_args(condition: bool, token: &Token) -> Result<(), Self> {
 		if condition {
-			return Err(Self::InvalidNumberOfArgs(
-				token.value.to_string(),
-				token.pos,
-				token.value.len(),
-			));
+			return Err(Self::InvalidNumberOfArgs {
+				name: token.value.to_string(),
+				span: token.span.clone(),
+			});
 		}
 		Ok(())
 	}
 
 	pub fn not_found(condition: bool, token: &Token) -> Result<(), Self> {
 		if condition {
-			return Err(Self::NotFound(
-				token.value.to_string(),
-				token.pos,
-				token.value.len(),
-			));
+			return Err(Self::NotFound {
+				name: token.value.to_string(),
+				span: token.span.clone(),
+			});
+		}
+		Ok(())
+	}
+
+	pub fn invalid_definition(condition: bool, token: &Token, message: &str) -> Result<(), Self> {
+		if condition {
+			return Err(Self::InvalidDefinition {
+				name: token.value.to_string(),
+				span: token.span.clone(),
+				reason: message.to_string(),
+			});
 		}
 		Ok(())
 	}
 
 	pub fn not_used(condition: bool, token: &Token) -> Result<(), Self> {
 		if condition {
-			return Err(Self::NotUsed(
-				token.value.to_string(),
-				token.pos,
-				token.value.len(),
-			));
+			return Err(Self::NotUsed {
+				name: token.value.to_string(),
+				span: token.span.clone(),
+			});
 		}
 		Ok(())
 	}
+
+	pub fn span(&self) -> Option<&Span> {
+		match self {
+			Self::UnexpectedToken { span, .. }
+			| Self::AlreadyExists { span, .. }
+			| Self::NotFound { span, .. }
+			| Self::NotUsed { span, .. }
+			| Self::InvalidNumberOfArgs { span, .. }
+			| Self::InvalidDefinition { span, .. }
+			| Self::EndOfFile { span }
+			| Self::ConstantOutput { span, .. }
+			| Self::DoubleInversion { span, .. }
+			| Self::DeepChain { span, .. }
+			| Self::UnreadSignal { span, .. } => Some(span),
+			_ => None,
+		}
+	}
+
+	pub fn code(&self) -> &'static str {
+		match self {
+			Self::UnexpectedToken { .. } => "E0000",
+			Self::AlreadyExists { .. } => "E0001",
+			Self::NotFound { .. } => "E0002",
+			Self::NotUsed { .. } => "E0003",
+			Self::NotEnoughGenes { .. } => "E0004",
+			Self::InvalidNumberOfArgs { .. } => "E0005",
+			Self::InvalidDefinition { .. } => "E0006",
+			Self::EndOfFile { .. } => "E0007",
+			Self::ConstantOutput { .. } => "E0008",
+			Self::DoubleInversion { .. } => "E0009",
+			Self::DeepChain { .. } => "E0010",
+			Self::UnreadSignal { .. } => "E0011",
+			Self::Undefined { .. } => "E0012",
+			Self::Undriven { .. } => "E0013",
+			Self::NotASignal { .. } => "E0014",
+			Self::InvalidBody { .. } => "E0015",
+			Self::InvalidFile { .. } => "E0016",
+			Self::InvalidSequence { .. } => "E0017",
+			Self::PartCollision { .. } => "E0018",
+			Self::UnknownLibrary { .. } => "E0019",
+			Self::InvalidLibrary { .. } => "E0020",
+			Self::TestFailed { .. } => "E0021",
+			Self::InvalidOption { .. } => "E0022",
+			Self::MissingLibrary { .. } => "E0023",
+			Self::InvalidMeasurements { .. } => "E0024",
+			Self::CompileFailed { .. } => "E0025",
+		}
+	}
+
+	// Short text under the carets of a rendered error.
+	pub fn label(&self) -> String {
+		match self {
+			Self::UnexpectedToken { .. } => "unexpected token".to_string(),
+			Self::AlreadyExists { .. } => "defined again here".to_string(),
+			Self::NotFound { .. } => "not found".to_string(),
+			Self::NotUsed { .. } => "never used".to_string(),
+			Self::InvalidNumberOfArgs { .. } => "wrong number of arguments".to_string(),
+			Self::InvalidDefinition { reason, .. } => reason.to_string(),
+			Self::EndOfFile { .. } => "expected more input".to_string(),
+			Self::ConstantOutput { value, .. } => format!("always {}", value),
+			Self::DoubleInversion { input, .. } => format!("same as `{}`", input),
+			Self::DeepChain { depth, .. } => format!("{} gates deep", depth),
+			Self::UnreadSignal { input, .. } => format!("`{}` is never read", input),
+			_ => String::new(),
+		}
+	}
+
+	// The error text without its location, for when that is shown apart.
+	pub fn message(&self) -> String {
+		match self.span() {
+			Some(span) => self.to_string().replace(&format!(" at {}", span), ""),
+			None => self.to_string(),
+		}
+	}
+
+	// The error with its source line underlined, `None` if it has no span.
+	pub fn render(&self, source: &str) -> Option<String> {
+		let span = self.span()?;
+		Some(span::render(
+			source,
+			span,
+			&format!("error[{}]", self.code()),
+			&self.message(),
+			&self.label(),
+		))
+	}
 }
@@ -0,0 +1,414 @@
+use crate::_utils::error::Error;
+use serde::Serialize;
+
+// The long form of an error code, for `emc explain` and `/api/explain`.
+#[derive(Serialize, Debug)]
+pub struct Explanation {
+	pub code: &'static str,
+	pub kind: &'static str,
+	pub text: &'static str,
+}
+
+pub const EXPLANATIONS: [Explanation; 26] = [
+	Explanation {
+		code: "E0000",
+		kind: "UnexpectedToken",
+		text: r#"A token appears where the grammar doesn't allow it.
+
+Erroneous example:
+
+    mod main {
+        in(a, b)
+        out(c);
+    }
+
+`in(a, b)` is missing its `;`, so `out` comes where a `;` was expected.
+
+The same error is raised for values out of range, like an `osc` with an even
+or smaller than 3 number of stages, a negative breakpoint time, or a gene
+param that isn't a number:
+
+    impl main { let c = osc(4); }
+
+Add the missing token or fix the value. Parsing goes on after the error, so
+the other errors of the source are reported too."#,
+	},
+	Explanation {
+		code: "E0001",
+		kind: "AlreadyExists",
+		text: r#"A name is defined twice in the same scope.
+
+Erroneous example:
+
+    impl main {
+        let x = not(a);
+        let x = not(b);
+    }
+
+Wires of an `impl`, args of a `mod` or `env`, definitions like `mod main`,
+parts and genes, and assignments in one breakpoint of a `test` all have to
+be unique. Rename one of them, or remove the duplicate."#,
+	},
+	Explanation {
+		code: "E0002",
+		kind: "NotFound",
+		text: r#"A name is used but never defined.
+
+Erroneous example:
+
+    env main {
+        in(Arac);
+        out(YFP);
+    }
+
+`Arac` is not a signal of the library, `AraC` is. Names are looked up in the
+scope they are used in:
+
+- wires of an `impl` among the inputs of its `mod`, its gates and its `reg`s,
+  and every `reg` among its gates
+- signals and actuators of an `env` in its library and the parts defined in
+  the source
+- the library after `for` among the loaded libraries
+- the `mod` and `env` of a `test` among the definitions of the source
+
+Similar names are suggested as fixes when there are any. `emc catalog`
+lists the signals and actuators of a library."#,
+	},
+	Explanation {
+		code: "E0003",
+		kind: "NotUsed",
+		text: r#"A wire or input is defined but never read.
+
+Erroneous example:
+
+    mod main { in(a, b); out(c); }
+    impl main {
+        let x = not(a);
+        let c = not(b);
+    }
+
+`x` is never read, so its gene only takes up room in the plasmid. As the
+`unused_wire` and `unused_input` lints this is a warning, silenced with
+`#[allow(unused_wire)]` or `#[allow(unused_input)]` on the gate, the `impl`
+or, for inputs, the `mod`."#,
+	},
+	Explanation {
+		code: "E0004",
+		kind: "NotEnoughGenes",
+		text: r#"The circuit needs more genes than the library can give it.
+
+Every gate is built from a repressor gene, and genes of the same group share
+a repressor, so a circuit can use one gene per group. The groups repressed
+by the input signals of the `env` are taken as well. An `osc(n)` needs `n`
+genes and a `pulse` needs 3.
+
+Erroneous example, in a library with 10 usable groups:
+
+    impl main {
+        let o = osc(11);
+    }
+
+The error says how many genes were needed against how many groups were
+available. Use fewer gates, for example by merging `not`s into `nor`s, or
+compile against a library with more gene groups with `for "<library>"`."#,
+	},
+	Explanation {
+		code: "E0005",
+		kind: "InvalidNumberOfArgs",
+		text: r#"A gate, module or env gets the wrong number of arguments.
+
+Erroneous example:
+
+    impl main { let c = nor(a); }
+
+`not` and `pulse` take one wire, `nor` takes two and `osc` takes its number
+of stages. A `mod` needs exactly one output, and the `env` of a test or the
+main `env` needs as many inputs and outputs as the `mod` it is bound to:
+
+    mod main { in(a, b); out(c); }
+    env main { in(LacI); out(YFP); }"#,
+	},
+	Explanation {
+		code: "E0006",
+		kind: "InvalidDefinition",
+		text: r#"A definition is well formed but can't be used.
+
+Erroneous example:
+
+    gene Z1_NewR {
+        promoter pNewR;
+        ymax = 60.5;
+    }
+
+The gene has no `parts` and misses params. Parts and genes defined in a
+source go through the same checks as the libraries: sequences have to be
+DNA, names have to resolve and params have to be in physical ranges.
+
+Exhaustive tests of an env with too many inputs to try every combination
+are reported with this code as well."#,
+	},
+	Explanation {
+		code: "E0007",
+		kind: "EndOfFile",
+		text: r#"The source ends in the middle of a definition.
+
+Erroneous example:
+
+    mod main {
+        in(a, b);
+        out(c);
+
+The closing `}` is missing. Finish the definition or remove it."#,
+	},
+	Explanation {
+		code: "E0008",
+		kind: "ConstantOutput",
+		text: r#"A gate has the same output for every input.
+
+Erroneous example:
+
+    impl main {
+        let x = not(a);
+        let c = nor(a, x);
+    }
+
+`c` is always false, as either `a` or its inverse is true. This is the
+`constant_gate` lint, a warning silenced with `#[allow(constant_gate)]`.
+Only the first gate of a constant chain is reported."#,
+	},
+	Explanation {
+		code: "E0009",
+		kind: "DoubleInversion",
+		text: r#"A gate inverts a wire that is already inverted.
+
+Erroneous example:
+
+    impl main {
+        let x = not(a);
+        let c = not(x);
+    }
+
+`c` is the same as `a` and takes two genes and their delay to get there.
+`nor(x, x)` counts as an inverter too. This is the `double_inversion` lint,
+a warning silenced with `#[allow(double_inversion)]`, as delaying a wire on
+purpose is sometimes what you want."#,
+	},
+	Explanation {
+		code: "E0010",
+		kind: "DeepChain",
+		text: r#"An output sits behind a long chain of gates.
+
+Every gate adds its delay to the time an output takes to settle, and chains
+deeper than 6 gates are slow to respond. A `pulse` counts as 2 gates. This
+is the `deep_chain` lint, a warning silenced with `#[allow(deep_chain)]`.
+Restructure the logic to be wider and shallower where you can."#,
+	},
+	Explanation {
+		code: "E0011",
+		kind: "UnreadSignal",
+		text: r#"A signal of an env is bound to an input that is never read.
+
+Erroneous example:
+
+    mod main { in(a, b); out(c); }
+    env main { in(LacI, TetR); out(YFP); }
+    impl main { let c = not(a); }
+
+`TetR` drives `b`, which the `impl` never reads, so the signal has no effect
+on the circuit. This is the `unused_signal` lint, a warning silenced with
+`#[allow(unused_signal)]` on the env."#,
+	},
+	Explanation {
+		code: "E0012",
+		kind: "Undefined",
+		text: r#"A definition the compiler needs is missing.
+
+Erroneous example:
+
+    mod main { in(a); out(c); }
+    impl main { let c = not(a); }
+
+The circuit is built from the `mod`, `env` and `impl` named `main`, and all
+three have to be in the source. Here the `env main` is missing. Define it,
+binding the inputs and outputs of `mod main` to signals and actuators of the
+library."#,
+	},
+	Explanation {
+		code: "E0013",
+		kind: "Undriven",
+		text: r#"A wire of the circuit has nothing driving it.
+
+Every wire a gate reads and every output of `mod main` has to be an input or
+the output of a gate. Sources that pass the checks shouldn't hit this error,
+so it points at a case the checks miss. Please report it with the source."#,
+	},
+	Explanation {
+		code: "E0014",
+		kind: "NotASignal",
+		text: r#"An input of the circuit is not a signal of the library.
+
+The inputs of `env main` are looked up in the signals of its library when
+the circuit is built. Sources that pass the checks shouldn't hit this error,
+so it points at a case the checks miss. Please report it with the source."#,
+	},
+	Explanation {
+		code: "E0015",
+		kind: "InvalidBody",
+		text: r#"The body of a request can't be decoded.
+
+Bodies sent base64 encoded have to decode to UTF-8 text. Send the source as
+plain text, or check the encoding of the client."#,
+	},
+	Explanation {
+		code: "E0016",
+		kind: "InvalidFile",
+		text: r#"A file can't be read or has the wrong format.
+
+This covers library files that are missing or aren't valid JSON, GenBank,
+SBOL and UCF files that can't be imported, and CSV measurements with values
+that aren't numbers. The message names the file and what is wrong with it."#,
+	},
+	Explanation {
+		code: "E0017",
+		kind: "InvalidSequence",
+		text: r#"The sequence of a part is not DNA.
+
+Erroneous example:
+
+    part pX promoter "ACGTNACGT";
+
+Sequences can only have the bases `A`, `C`, `G` and `T`, in either case, and
+can't be empty. The error gives the position of the first invalid base."#,
+	},
+	Explanation {
+		code: "E0018",
+		kind: "PartCollision",
+		text: r#"An imported part has the name of a known part with another sequence.
+
+`emc import` adds parts to the user parts library, and keeps parts that are
+already there with the same sequence. Rename the part in the imported file,
+or remove the old one from the library first."#,
+	},
+	Explanation {
+		code: "E0019",
+		kind: "UnknownLibrary",
+		text: r#"No library has the requested name.
+
+Libraries are the directories in `static/libraries/`, next to the default
+library in `static/`. `emc import-ucf` creates new ones. Check the name
+passed with `?library=` or `--library`."#,
+	},
+	Explanation {
+		code: "E0020",
+		kind: "InvalidLibrary",
+		text: r#"A library exists but failed validation.
+
+Libraries with errors are left out when they are loaded, so a circuit is
+never built from broken params or sequences. Run
+
+    emc validate-library static/libraries/<name>
+
+to see every issue, then fix the library files."#,
+	},
+	Explanation {
+		code: "E0021",
+		kind: "TestFailed",
+		text: r#"The simulated output of a test disagrees with the logic of the circuit.
+
+Erroneous example, with a gene too weak to switch its output off:
+
+    mod main { in(a); out(c); }
+    env main { in(LacI); out(YFP); }
+    impl main { let c = not(a); }
+    test t for main { @0 LacI = true; }
+
+`emc build` simulates every test and, at the last step before each
+breakpoint, compares the output with the value the `not`s and `nor`s give
+for the signals set so far. Levels above a threshold count as true. The
+threshold sits between the settled levels the test expects low and high
+when those are apart, and between the low and high steady states of the
+output otherwise. Outputs behind a `reg`, an `osc` or a `pulse` aren't
+checked. Breakpoints where the output is still moving are listed as not
+settled and don't fail the build.
+
+Hold the inputs longer so the circuit can settle, pass more `--iterations`
+to find better genes, or try another `--seed`."#,
+	},
+	Explanation {
+		code: "E0022",
+		kind: "InvalidOption",
+		text: r#"A compile option, a query parameter or an `emc` flag is unknown or out of
+range.
+
+Erroneous example, as the body of `POST /api/compile`:
+
+    {
+        "source": "mod main { ... }",
+        "options": { "iteration": 500 }
+    }
+
+`iteration` is not an option, `iterations` is. The options are:
+
+- `seed`, any unsigned integer, to get the same circuit back every time
+- `iterations`, from 1 to 100000, the gene assignments tried
+- `sim_time`, from 1 to 100000, the steps every test is simulated for
+- `library`, the name of a loaded library
+- `formats`, a list of `dna` and `sbol`, the outputs to build
+- `top_n`, from 1 to 10, the best assignments to list
+
+Bodies that aren't a JSON object are compiled as source as they are.
+`GET /api/catalog` takes a `kind` of `signals`, `actuators` or `genes`, and
+the flags of `emc`, like `--decay` or `--formats`, are checked the same way."#,
+	},
+	Explanation {
+		code: "E0023",
+		kind: "MissingLibrary",
+		text: r#"The directory of a library doesn't exist.
+
+The default library is read from `static/` in the working directory, so
+running `emc` or the server from elsewhere finds no library at all. Run
+them from the repository root, or start the server with `--static <dir>`."#,
+	},
+	Explanation {
+		code: "E0024",
+		kind: "InvalidMeasurements",
+		text: r#"Measurements read fine but can't pin down the Hill params of a gate.
+
+Erroneous example, as the csv of `emc fit`:
+
+    input,output
+    0.01,1.5
+    0.1,1.5
+    1,1.5
+    10,1.5
+    100,1.5
+
+A flat response has no threshold and no steepness to fit. `ymax`, `ymin`,
+`K` and `n` need at least 5 measurements, none of them with a negative
+input, over inputs that take the gate from on to off. Measure more inputs
+around the switch."#,
+	},
+	Explanation {
+		code: "E0025",
+		kind: "CompileFailed",
+		text: r#"Sources given to `emc` have errors.
+
+`emc build` goes on with the other sources when one fails, and `emc
+calibrate` stops at its source. Either way the errors of each source are
+printed above this one, each with its own code and the offending line.
+Fix those and run the command again."#,
+	},
+];
+
+// The explanation of a code like `E0004`, `e4` or `4`, or of a kind like
+// `NotEnoughGenes`.
+pub fn explain(query: &str) -> Result<&'static Explanation, Error> {
+	let number = query.trim_start_matches(['E', 'e']).parse::<usize>().ok();
+	EXPLANATIONS
+		.iter()
+		.find(|exp| number.map_or(exp.kind == query, |n| exp.code[1..].parse() == Ok(n)))
+		.ok_or_else(|| Error::Undefined {
+			kind: "error code".to_string(),
+			name: query.to_string(),
+		})
+}
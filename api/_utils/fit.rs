@@ -0,0 +1,349 @@
+use crate::_utils::{data::Params, error::Error};
+use serde::Serialize;
+
+const MAX_ITERATIONS: usize = 200;
+const TOLERANCE: f64 = 1e-10;
+
+// Two sided 97.5% quantiles of Student's t for 1 to 30 degrees of freedom,
+// past that the normal quantile is close enough.
+const T_975: [f64; 30] = [
+	12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+	2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+	2.052, 2.048, 2.045, 2.042,
+];
+const Z_975: f64 = 1.96;
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Measurement {
+	pub input: f64,
+	pub output: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Residual {
+	pub input: f64,
+	pub output: f64,
+	pub predicted: f64,
+	pub residual: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Estimate {
+	pub value: f64,
+	pub std_error: f64,
+	pub low: f64,
+	pub high: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HillFit {
+	pub params: Params,
+	pub ymax: Estimate,
+	pub ymin: Estimate,
+	pub k: Estimate,
+	pub n: Estimate,
+	pub residuals: Vec<Residual>,
+	pub rss: f64,
+	pub r_squared: f64,
+	pub iterations: usize,
+	pub converged: bool,
+}
+
+// Reads `input,output` rows, replicates are separate rows. A header row and
+// any columns after the second are ignored.
+pub fn read_measurements(text: &str) -> Result<Vec<Measurement>, Error> {
+	let mut measurements = Vec::new();
+	for (i, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let cols: Vec<&str> = line.split([',', ';', '\t']).collect();
+		let values: Vec<Option<f64>> = cols
+			.iter()
+			.take(2)
+			.map(|col| col.trim().parse::<f64>().ok())
+			.collect();
+		match values.as_slice() {
+			[Some(input), Some(output)] if input.is_finite() && output.is_finite() => measurements
+				.push(Measurement {
+					input: *input,
+					output: *output,
+				}),
+			_ if i == 0 => continue,
+			_ => {
+				return Err(Error::InvalidFile {
+					reason: format!("line {}: expected `input,output`, got `{}`", i + 1, line),
+				})
+			}
+		}
+	}
+	Ok(measurements)
+}
+
+fn params(p: &[f64; 4], decay: f64) -> Params {
+	Params {
+		ymax: p[0],
+		ymin: p[1],
+		k: p[2],
+		n: p[3],
+		decay,
+	}
+}
+
+fn hill(p: &[f64; 4], x: f64) -> f64 {
+	params(p, 0.0).transfer(x)
+}
+
+// Partial derivatives of the Hill equation by ymax, ymin, K and n.
+fn gradient(p: &[f64; 4], x: f64) -> [f64; 4] {
+	let ratio = x / p[2];
+	let r = ratio.powf(p[3]);
+	let d = 1.0 + r;
+	let span = p[0] - p[1];
+	let log = if ratio > 0.0 { ratio.ln() } else { 0.0 };
+	[
+		1.0 / d,
+		r / d,
+		span * r * p[3] / (p[2] * d * d),
+		-span * r * log / (d * d),
+	]
+}
+
+fn rss(p: &[f64; 4], data: &[Measurement]) -> f64 {
+	data.iter()
+		.map(|m| (m.output - hill(p, m.input)).powi(2))
+		.sum()
+}
+
+// Normal equations of the current point, `J^T J` and `J^T r`.
+fn normal(p: &[f64; 4], data: &[Measurement]) -> ([[f64; 4]; 4], [f64; 4]) {
+	let mut jtj = [[0.0; 4]; 4];
+	let mut jtr = [0.0; 4];
+	for m in data {
+		let g = gradient(p, m.input);
+		let r = m.output - hill(p, m.input);
+		for i in 0..4 {
+			jtr[i] += g[i] * r;
+			for j in 0..4 {
+				jtj[i][j] += g[i] * g[j];
+			}
+		}
+	}
+	(jtj, jtr)
+}
+
+// Gauss-Jordan elimination with partial pivoting, `None` if `a` is singular.
+fn invert(a: &[[f64; 4]; 4]) -> Option<[[f64; 4]; 4]> {
+	let mut a = *a;
+	let mut inv = [[0.0; 4]; 4];
+	for (i, row) in inv.iter_mut().enumerate() {
+		row[i] = 1.0;
+	}
+	for col in 0..4 {
+		let pivot = (col..4).max_by(|x, y| a[*x][col].abs().total_cmp(&a[*y][col].abs()))?;
+		if a[pivot][col].abs() < 1e-300 {
+			return None;
+		}
+		a.swap(col, pivot);
+		inv.swap(col, pivot);
+		let div = a[col][col];
+		for j in 0..4 {
+			a[col][j] /= div;
+			inv[col][j] /= div;
+		}
+		for row in 0..4 {
+			if row == col {
+				continue;
+			}
+			let factor = a[row][col];
+			for j in 0..4 {
+				a[row][j] -= factor * a[col][j];
+				inv[row][j] -= factor * inv[col][j];
+			}
+		}
+	}
+	Some(inv)
+}
+
+fn initial_guess(data: &[Measurement]) -> [f64; 4] {
+	let max = data.iter().map(|m| m.output).fold(f64::MIN, f64::max);
+	let min = data.iter().map(|m| m.output).fold(f64::MAX, f64::min);
+	let mid = (max + min) / 2.0;
+	let k = data
+		.iter()
+		.filter(|m| m.input > 0.0)
+		.min_by(|a, b| (a.output - mid).abs().total_cmp(&(b.output - mid).abs()))
+		.map(|m| m.input)
+		.unwrap_or(1.0);
+	[max, min, k, 2.0]
+}
+
+fn t_quantile(dof: usize) -> f64 {
+	T_975.get(dof - 1).cloned().unwrap_or(Z_975)
+}
+
+// Levenberg-Marquardt on the Hill equation of `Gene::transfer`. Steps that
+// would make `K` or `n` non positive are rejected like any worse step.
+pub fn fit_hill(data: &[Measurement], decay: f64) -> Result<HillFit, Error> {
+	if data.len() < 5 {
+		return Err(Error::InvalidMeasurements {
+			reason: format!(
+				"need at least 5 measurements to fit 4 params, got {}",
+				data.len()
+			),
+		});
+	}
+	if data.iter().any(|m| m.input < 0.0) {
+		return Err(Error::InvalidMeasurements {
+			reason: "inputs must not be negative".into(),
+		});
+	}
+
+	let mut p = initial_guess(data);
+	let mut cost = rss(&p, data);
+	let mut lambda = 1e-3;
+	let mut iterations = 0;
+	let mut converged = false;
+	while iterations < MAX_ITERATIONS && !converged {
+		iterations += 1;
+		let (jtj, jtr) = normal(&p, data);
+		let mut damped = jtj;
+		for (i, row) in damped.iter_mut().enumerate() {
+			row[i] += lambda * jtj[i][i].max(1e-12);
+		}
+		let inv = match invert(&damped) {
+			Some(inv) => inv,
+			None => {
+				lambda *= 10.0;
+				continue;
+			}
+		};
+		let mut next = p;
+		for i in 0..4 {
+			next[i] += (0..4).map(|j| inv[i][j] * jtr[j]).sum::<f64>();
+		}
+
+		let next_cost = rss(&next, data);
+		if next[2] > 0.0 && next[3] > 0.0 && next_cost.is_finite() && next_cost <= cost {
+			converged = (cost - next_cost) <= TOLERANCE * cost.max(TOLERANCE);
+			p = next;
+			cost = next_cost;
+			lambda = (lambda / 10.0).max(1e-12);
+		} else {
+			// Once no step short enough improves the fit we are at the minimum.
+			lambda *= 10.0;
+			converged = lambda > 1e12;
+		}
+	}
+
+	let dof = data.len() - 4;
+	let variance = cost / dof as f64;
+	let (jtj, _) = normal(&p, data);
+	let cov = invert(&jtj).ok_or_else(|| Error::InvalidMeasurements {
+		reason: "they don't determine all params, add more inputs".into(),
+	})?;
+	let t = t_quantile(dof);
+	let estimate = |i: usize| {
+		let std_error = (variance * cov[i][i]).max(0.0).sqrt();
+		Estimate {
+			value: p[i],
+			std_error,
+			low: p[i] - t * std_error,
+			high: p[i] + t * std_error,
+		}
+	};
+
+	let mean = data.iter().map(|m| m.output).sum::<f64>() / data.len() as f64;
+	let total: f64 = data.iter().map(|m| (m.output - mean).powi(2)).sum();
+	let residuals = data
+		.iter()
+		.map(|m| {
+			let predicted = hill(&p, m.input);
+			Residual {
+				input: m.input,
+				output: m.output,
+				predicted,
+				residual: m.output - predicted,
+			}
+		})
+		.collect();
+
+	Ok(HillFit {
+		params: params(&p, decay),
+		ymax: estimate(0),
+		ymin: estimate(1),
+		k: estimate(2),
+		n: estimate(3),
+		residuals,
+		rss: cost,
+		r_squared: if total > 0.0 { 1.0 - cost / total } else { 1.0 },
+		iterations,
+		converged,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TRUE: [f64; 4] = [3.9, 0.007, 0.03, 4.0];
+
+	// Triplicates at inputs spread evenly over the log scale, with a few
+	// percent of alternating noise.
+	fn curve(p: &[f64; 4]) -> Vec<Measurement> {
+		(0..16)
+			.flat_map(|i| {
+				let input = 0.001 * 10f64.powf(i as f64 / 4.0);
+				[0.97, 1.0, 1.03].iter().map(move |noise| Measurement {
+					input,
+					output: hill(p, input) * noise,
+				})
+			})
+			.collect()
+	}
+
+	#[test]
+	fn recovers_known_params() {
+		let fit = fit_hill(&curve(&TRUE), 0.1).unwrap();
+		assert!(fit.converged);
+		assert!(fit.r_squared > 0.99, "{}", fit.r_squared);
+		let estimates = [&fit.ymax, &fit.ymin, &fit.k, &fit.n];
+		for (estimate, value) in estimates.iter().zip(&TRUE) {
+			let error = (estimate.value - value).abs() / value;
+			assert!(error < 0.05, "{:?} for {}", estimate, value);
+			assert!(estimate.low <= *value && *value <= estimate.high);
+		}
+		assert_eq!(fit.params.decay, 0.1);
+	}
+
+	#[test]
+	fn rejects_degenerate_measurements() {
+		let few = &curve(&TRUE)[..4];
+		assert!(fit_hill(few, 0.1).is_err());
+
+		let flat: Vec<Measurement> = curve(&TRUE)
+			.iter()
+			.map(|m| Measurement {
+				input: m.input,
+				output: 1.5,
+			})
+			.collect();
+		match fit_hill(&flat, 0.1) {
+			Err(Error::InvalidMeasurements { reason }) => {
+				assert!(reason.contains("determine"), "{}", reason)
+			}
+			Err(e) => panic!("{}", e),
+			Ok(fit) => panic!("fit a flat curve: {:?}", fit),
+		}
+	}
+
+	#[test]
+	fn reads_measurements() {
+		let text = "input,output\n0.1, 2.5\n# replicate\n0.1;2.4\n1\t0.02\n";
+		let measurements = read_measurements(text).unwrap();
+		let rows: Vec<(f64, f64)> = measurements.iter().map(|m| (m.input, m.output)).collect();
+		assert_eq!(rows, [(0.1, 2.5), (0.1, 2.4), (1.0, 0.02)]);
+		assert!(read_measurements("0.1,2.5\n0.2,high\n").is_err());
+	}
+}
@@ -0,0 +1,374 @@
+use super::{Component, GeneticCircuit};
+use crate::_utils::{data::GeneData, error::Error, logic_circuit::Testbench};
+use serde::Serialize;
+
+const SWEEPS: usize = 6;
+const SECTION_STEPS: usize = 30;
+const DECAY_RANGE: (f64, f64) = (1e-3, 1.0);
+const SCALE_RANGE: (f64, f64) = (1e-3, 1e3);
+const GOLDEN: f64 = 0.618_033_988_749_895;
+
+// One measured channel, `(time, value)` points in the order of the file.
+#[derive(Serialize, Debug, Clone)]
+pub struct Trace {
+	pub channel: String,
+	pub points: Vec<(f64, f64)>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Metrics {
+	pub channel: String,
+	pub points: usize,
+	pub rmse: f64,
+	pub mae: f64,
+	pub r_squared: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Comparison {
+	pub channels: Vec<Metrics>,
+	pub overall: Metrics,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Calibration {
+	pub testbench: String,
+	pub time_scale: f64,
+	pub before: Comparison,
+	pub after: Comparison,
+	pub genes: Vec<GeneData>,
+}
+
+// Reads a `time,<channel>,...` table. Channels are named after an output, a
+// gate, a signal or a promoter of the circuit, empty cells are skipped.
+pub fn read_traces(text: &str) -> Result<Vec<Trace>, Error> {
+	let mut lines = text
+		.lines()
+		.enumerate()
+		.filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'));
+	let (_, header) = lines.next().ok_or_else(|| Error::InvalidFile {
+		reason: "empty time course".into(),
+	})?;
+	let mut traces: Vec<Trace> = header
+		.split([',', ';', '\t'])
+		.skip(1)
+		.map(|channel| Trace {
+			channel: channel.trim().to_string(),
+			points: Vec::new(),
+		})
+		.collect();
+
+	for (i, line) in lines {
+		let invalid = || Error::InvalidFile {
+			reason: format!("line {}: invalid value in `{}`", i + 1, line),
+		};
+		let mut cells = line.split([',', ';', '\t']).map(str::trim);
+		let time: f64 = cells.next().unwrap().parse().map_err(|_| invalid())?;
+		for (trace, cell) in traces.iter_mut().zip(cells) {
+			if cell.is_empty() {
+				continue;
+			}
+			let value: f64 = cell.parse().map_err(|_| invalid())?;
+			trace.points.push((time, value));
+		}
+	}
+	Ok(traces)
+}
+
+fn metrics(channel: &str, pairs: &[(f64, f64)]) -> Metrics {
+	// Folded from 0.0, as an empty float sum is -0.0.
+	let sum = |f: &dyn Fn(f64, f64) -> f64| pairs.iter().fold(0.0, |acc, (m, p)| acc + f(*m, *p));
+	let count = pairs.len().max(1) as f64;
+	let mean = sum(&|m, _| m) / count;
+	let sse = sum(&|m, p| (m - p).powi(2));
+	let total = sum(&|m, _| (m - mean).powi(2));
+	Metrics {
+		channel: channel.to_string(),
+		points: pairs.len(),
+		rmse: (sse / count).sqrt(),
+		mae: sum(&|m, p| (m - p).abs()) / count,
+		r_squared: if total > 0.0 { 1.0 - sse / total } else { 1.0 },
+	}
+}
+
+// Minimizes `f` over `[low, high]` in log space, assuming a single minimum.
+fn golden_section<F: FnMut(f64) -> f64>(mut f: F, low: f64, high: f64) -> f64 {
+	let (mut a, mut b) = (low.ln(), high.ln());
+	let mut c = b - GOLDEN * (b - a);
+	let mut d = a + GOLDEN * (b - a);
+	let (mut fc, mut fd) = (f(c.exp()), f(d.exp()));
+	for _ in 0..SECTION_STEPS {
+		if fc < fd {
+			b = d;
+			d = c;
+			fd = fc;
+			c = b - GOLDEN * (b - a);
+			fc = f(c.exp());
+		} else {
+			a = c;
+			c = d;
+			fc = fd;
+			d = a + GOLDEN * (b - a);
+			fd = f(d.exp());
+		}
+	}
+	((a + b) / 2.0).exp()
+}
+
+impl GeneticCircuit {
+	// History key of a measured channel.
+	fn channel_promoter(&self, channel: &str) -> Option<String> {
+		if let Some(out) = self.outputs.iter().find(|out| out.name == channel) {
+			return Some(out.input.to_string());
+		}
+		if let Some(sig) = self.inputs.iter().find(|sig| sig.name == channel) {
+			return Some(sig.promoter());
+		}
+		// Gates are described as `<wire> = <kind>(<inputs>)`.
+		let comp = self.components.iter().find(|comp| match comp {
+			Component::Gene(gene) => {
+				gene.name() == channel || gene.gate.split(" = ").next() == Some(channel)
+			}
+			Component::Signal(_) => false,
+		});
+		match comp {
+			Some(comp) => Some(comp.promoter()),
+			None if self.components.iter().any(|c| c.promoter() == channel) => {
+				Some(channel.to_string())
+			}
+			None => None,
+		}
+	}
+
+	// Simulated value at measured `time`, `history[i]` being the state after
+	// step `i`. Past the end of the testbench the last state holds.
+	fn predict(history: &[f64], time: f64, time_scale: f64) -> f64 {
+		let step = (time * time_scale).max(0.0);
+		let i = step.floor() as usize;
+		match (history.get(i), history.get(i + 1)) {
+			(Some(a), Some(b)) => a + (b - a) * (step - i as f64),
+			(Some(a), None) => *a,
+			_ => *history.last().unwrap_or(&0.0),
+		}
+	}
+
+	pub fn compare(
+		&mut self,
+		testbench: &Testbench,
+		traces: &[Trace],
+		time_scale: f64,
+	) -> Result<Comparison, Error> {
+		let mut promoters = Vec::new();
+		for trace in traces {
+			let promoter =
+				self.channel_promoter(&trace.channel)
+					.ok_or_else(|| Error::InvalidFile {
+						reason: format!("unknown channel {}", trace.channel),
+					})?;
+			promoters.push(promoter);
+		}
+
+		self.simulate(testbench);
+		let history = &self.simulations[&testbench.name].history;
+		let mut all = Vec::new();
+		let mut channels = Vec::new();
+		for (trace, promoter) in traces.iter().zip(&promoters) {
+			let pairs: Vec<(f64, f64)> = trace
+				.points
+				.iter()
+				.map(|(time, value)| (*value, Self::predict(&history[promoter], *time, time_scale)))
+				.collect();
+			channels.push(metrics(&trace.channel, &pairs));
+			all.extend(pairs);
+		}
+		Ok(Comparison {
+			channels,
+			overall: metrics("all", &all),
+		})
+	}
+
+	fn gene_indices(&self) -> Vec<usize> {
+		(0..self.components.len())
+			.filter(|i| matches!(self.components[*i], Component::Gene(_)))
+			.collect()
+	}
+
+	fn set_decay(&mut self, i: usize, decay: f64) {
+		if let Component::Gene(gene) = &mut self.components[i] {
+			gene.data.params.decay = decay;
+		}
+	}
+
+	fn sse(&mut self, testbench: &Testbench, traces: &[Trace], time_scale: f64) -> f64 {
+		match self.compare(testbench, traces, time_scale) {
+			Ok(cmp) => cmp.overall.rmse.powi(2) * cmp.overall.points as f64,
+			Err(_) => f64::MAX,
+		}
+	}
+
+	// Fits the decay of every gene and the number of simulation steps per unit
+	// of measured time by coordinate descent on the squared error.
+	pub fn calibrate(
+		&mut self,
+		testbench: &Testbench,
+		traces: &[Trace],
+		time_scale: f64,
+		fit_decay: bool,
+		fit_scale: bool,
+	) -> Result<Calibration, Error> {
+		let before = self.compare(testbench, traces, time_scale)?;
+		let mut time_scale = time_scale;
+		let mut best = self.sse(testbench, traces, time_scale);
+		// A section search can land on a worse local minimum, in which case
+		// the previous value is kept.
+		for _ in 0..SWEEPS {
+			if fit_scale {
+				let scale = golden_section(
+					|scale| self.sse(testbench, traces, scale),
+					SCALE_RANGE.0,
+					SCALE_RANGE.1,
+				);
+				let sse = self.sse(testbench, traces, scale);
+				if sse < best {
+					best = sse;
+					time_scale = scale;
+				}
+			}
+			if fit_decay {
+				for i in self.gene_indices() {
+					let previous = match &self.components[i] {
+						Component::Gene(gene) => gene.decay(),
+						Component::Signal(_) => continue,
+					};
+					let decay = golden_section(
+						|decay| {
+							self.set_decay(i, decay);
+							self.sse(testbench, traces, time_scale)
+						},
+						DECAY_RANGE.0,
+						DECAY_RANGE.1,
+					);
+					self.set_decay(i, decay);
+					let sse = self.sse(testbench, traces, time_scale);
+					if sse < best {
+						best = sse;
+					} else {
+						self.set_decay(i, previous);
+					}
+				}
+			}
+		}
+
+		let after = self.compare(testbench, traces, time_scale)?;
+		let genes = self
+			.components
+			.iter()
+			.filter_map(|comp| match comp {
+				Component::Gene(gene) if fit_decay => Some(gene.data.clone()),
+				_ => None,
+			})
+			.collect();
+		Ok(Calibration {
+			testbench: testbench.name.to_string(),
+			time_scale,
+			before,
+			after,
+			genes,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::_utils::pipeline::{compile, Options};
+	use std::collections::HashMap;
+
+	const DECAY: f64 = 0.05;
+	const TIME_SCALE: f64 = 4.0;
+
+	// A `not` gate with its decay set to `DECAY`, switched off halfway
+	// through the test.
+	fn circuit() -> (GeneticCircuit, Testbench) {
+		let source = "mod main { in(a); out(y); }
+env main { in(LacI); out(YFP); }
+impl main { let y = not(a); }";
+		let options = Options {
+			seed: Some(1),
+			iterations: 50,
+			..Options::default()
+		};
+		let mut gc = compile(source, &options).unwrap().gc;
+		for i in gc.gene_indices() {
+			gc.set_decay(i, DECAY);
+		}
+		let breakpoints = [(0, false), (200, true)]
+			.iter()
+			.map(|(at, on)| (*at, HashMap::from([("LacI".to_string(), *on)])))
+			.collect();
+		let testbench = Testbench {
+			name: "t".to_string(),
+			breakpoints,
+			duration: 400,
+			levels: HashMap::new(),
+		};
+		(gc, testbench)
+	}
+
+	// YFP measured every 2 units of time, `TIME_SCALE` steps each.
+	fn measure(gc: &mut GeneticCircuit, testbench: &Testbench) -> Vec<Trace> {
+		gc.simulate(testbench);
+		let promoter = gc.channel_promoter("YFP").unwrap();
+		let history = &gc.simulations[&testbench.name].history[&promoter];
+		let points = (0..50)
+			.map(|i| {
+				let time = i as f64 * 2.0;
+				(time, history[(time * TIME_SCALE) as usize])
+			})
+			.collect();
+		vec![Trace {
+			channel: "YFP".to_string(),
+			points,
+		}]
+	}
+
+	fn decays(gc: &GeneticCircuit) -> Vec<f64> {
+		gc.components
+			.iter()
+			.filter_map(|comp| match comp {
+				Component::Gene(gene) => Some(gene.decay()),
+				Component::Signal(_) => None,
+			})
+			.collect()
+	}
+
+	// A trace only pins down how fast a gene moves per unit of time, the
+	// product of the two, so each is fit with the other known.
+	#[test]
+	fn recovers_time_scale() {
+		let (mut gc, testbench) = circuit();
+		let traces = measure(&mut gc, &testbench);
+		let calibration = gc.calibrate(&testbench, &traces, 1.0, false, true).unwrap();
+		let error = (calibration.time_scale - TIME_SCALE).abs() / TIME_SCALE;
+		assert!(error < 0.01, "{}", calibration.time_scale);
+		assert!(calibration.after.overall.rmse < calibration.before.overall.rmse);
+		assert!(calibration.after.overall.r_squared > 0.999);
+	}
+
+	#[test]
+	fn recovers_decay() {
+		let (mut gc, testbench) = circuit();
+		let traces = measure(&mut gc, &testbench);
+		for i in gc.gene_indices() {
+			gc.set_decay(i, 0.3);
+		}
+		let calibration = gc
+			.calibrate(&testbench, &traces, TIME_SCALE, true, false)
+			.unwrap();
+		for decay in decays(&gc) {
+			assert!((decay - DECAY).abs() / DECAY < 0.01, "{}", decay);
+		}
+		assert_eq!(calibration.genes.len(), 1);
+		assert!(calibration.after.overall.r_squared > 0.999);
+	}
+}
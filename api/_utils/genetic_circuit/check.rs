@@ -0,0 +1,122 @@
+use super::GeneticCircuit;
+use crate::_utils::logic_circuit::{LogicCircuit, Testbench};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// Steps the output has to hold within `SETTLE_TOLERANCE` of its last level
+// for a breakpoint to count as settled.
+const SETTLE_WINDOW: usize = 10;
+const SETTLE_TOLERANCE: f64 = 0.01;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum FailureKind {
+	// The output settled on the other side of its threshold than the logic of
+	// the circuit says it should.
+	WrongValue,
+	// The output was still moving when the breakpoint was replaced, so its
+	// value says nothing about the logic. Hold the inputs longer.
+	NotSettled,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Failure {
+	pub kind: FailureKind,
+	pub testbench: String,
+	pub time: u32,
+	pub output: String,
+	pub expected: bool,
+	pub level: f64,
+	pub threshold: f64,
+}
+
+// Halfway between `low` and `high` on a log scale, as levels span orders of
+// magnitude.
+fn midpoint(low: f64, high: f64) -> f64 {
+	match low > 0.0 && high > 0.0 {
+		true => (low * high).sqrt(),
+		false => (low + high) / 2.0,
+	}
+}
+
+impl GeneticCircuit {
+	// Checks the simulation of `testbench` against the logic of `lc`, at the
+	// last step before every breakpoint is replaced by the next one. Outputs
+	// that depend on registers or motifs can't be predicted and are skipped.
+	pub fn check(&self, lc: &LogicCircuit, testbench: &Testbench) -> Vec<Failure> {
+		let sim = match self.simulations.get(&testbench.name) {
+			Some(sim) => sim,
+			None => return Vec::new(),
+		};
+		let output = match self.outputs.first() {
+			Some(output) => output,
+			None => return Vec::new(),
+		};
+		let (history, (low, high)) = match (
+			sim.history.get(&output.input),
+			sim.steady_states.get(&output.input),
+		) {
+			(Some(history), Some(steady)) => (history, *steady),
+			_ => return Vec::new(),
+		};
+
+		let mut times: Vec<u32> = testbench.breakpoints.keys().cloned().collect();
+		times.sort_unstable();
+		let mut signals: HashMap<String, bool> = HashMap::new();
+		// `(time, expected, level, settled)` of every checked breakpoint.
+		let mut checked = Vec::new();
+		for (i, time) in times.iter().enumerate() {
+			signals.extend(testbench.breakpoints[time].clone());
+			let end = times.get(i + 1).cloned().unwrap_or(testbench.duration) as usize;
+			if end <= *time as usize {
+				continue;
+			}
+			let (expected, level) = match (lc.evaluate(&signals), history.get(end - 1)) {
+				(Some(expected), Some(level)) => (expected, *level),
+				_ => continue,
+			};
+			let start = end.saturating_sub(SETTLE_WINDOW).max(*time as usize);
+			let settled = history[start..end]
+				.iter()
+				.all(|past| (past - level).abs() <= SETTLE_TOLERANCE * level.abs().max(1e-9));
+			checked.push((*time, expected, level, settled));
+		}
+
+		// The steady states are those of the inputs all off or all on. A `nor`
+		// with one input on settles in between, so where the settled levels
+		// of the test are apart the threshold goes between them instead.
+		let settled = checked.iter().filter(|(_, _, _, settled)| *settled);
+		let (lows, highs): (Vec<_>, Vec<_>) = settled.partition(|(_, expected, _, _)| !expected);
+		let max_low = lows
+			.iter()
+			.map(|(_, _, level, _)| *level)
+			.fold(f64::MIN, f64::max);
+		let min_high = highs
+			.iter()
+			.map(|(_, _, level, _)| *level)
+			.fold(f64::MAX, f64::min);
+		let threshold = match !lows.is_empty() && !highs.is_empty() && max_low < min_high {
+			true => midpoint(max_low, min_high),
+			false => midpoint(low, high),
+		};
+
+		checked
+			.into_iter()
+			.filter_map(|(time, expected, level, settled)| {
+				let kind = match settled {
+					false => FailureKind::NotSettled,
+					true if (level > threshold) != expected => FailureKind::WrongValue,
+					true => return None,
+				};
+				Some(Failure {
+					kind,
+					testbench: testbench.name.to_string(),
+					time,
+					output: output.name.to_string(),
+					expected,
+					level,
+					threshold,
+				})
+			})
+			.collect()
+	}
+}
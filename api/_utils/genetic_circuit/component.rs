@@ -1,5 +1,5 @@
 use super::{gene::Gene, signal::Signal};
-use core::panic;
+use crate::_utils::{data::Data, dna::Plasmid, error::Error};
 use serde::Serialize;
 use std::collections::HashMap;
 
@@ -11,10 +11,10 @@ pub enum Component {
 }
 
 impl Component {
-	pub fn signal(&self) -> Signal {
+	pub fn signal(&self) -> Result<Signal, Error> {
 		match self {
-			Component::Signal(sig) => sig.clone(),
-			_ => panic!("Component is not a Signal"),
+			Component::Signal(sig) => Ok(sig.clone()),
+			_ => Err(Error::NotASignal { name: self.name() }),
 		}
 	}
 
@@ -39,36 +39,36 @@ impl Component {
 		}
 	}
 
-	pub fn apply_rules(&mut self) {
+	pub fn apply_rules(&mut self, data: &Data) {
 		match self {
-			Component::Gene(gene) => gene.apply_rules(),
-			Component::Signal(sig) => {}
+			Component::Gene(gene) => gene.apply_rules(data),
+			Component::Signal(_) => {}
 		}
 	}
 
 	pub fn into_dna(
 		&self,
-		dna: &mut String,
-		plasmid: &mut String,
+		data: &Data,
+		plasmid: &mut Plasmid,
 		promoter_colors: &mut HashMap<String, String>,
 	) {
 		match self {
-			Component::Gene(gene) => gene.into_dna(dna, plasmid, promoter_colors),
-			Component::Signal(sig) => {}
+			Component::Gene(gene) => gene.into_dna(data, plasmid, promoter_colors),
+			Component::Signal(_) => {}
 		}
 	}
 
 	pub fn test_steady_state(&self, cached: &mut HashMap<String, (f64, f64, f64, f64)>) {
 		match self {
 			Component::Gene(gene) => gene.test_steady_state(cached),
-			Component::Signal(sig) => {}
+			Component::Signal(_) => {}
 		}
 	}
 
 	pub fn simulation_steady_state(&self, cached: &mut HashMap<String, (f64, f64)>) {
 		match self {
 			Component::Gene(gene) => gene.simulation_steady_state(cached),
-			Component::Signal(sig) => {}
+			Component::Signal(_) => {}
 		}
 	}
 
@@ -79,7 +79,7 @@ impl Component {
 	) {
 		match self {
 			Component::Gene(gene) => gene.model_and_save(states, history),
-			Component::Signal(sig) => {}
+			Component::Signal(_) => {}
 		}
 	}
 }
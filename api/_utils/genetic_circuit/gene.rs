@@ -1,6 +1,6 @@
 use crate::_utils::{data, dna};
-use data::{get_data, GeneData};
-use dna::Dna;
+use data::{Data, GeneData, Part};
+use dna::Plasmid;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -9,6 +9,7 @@ pub struct Gene {
 	pub data: GeneData,
 	pub color: String,
 	pub inputs: Vec<String>,
+	pub gate: String,
 }
 
 impl Gene {
@@ -32,8 +33,7 @@ impl Gene {
 		self.inputs.clone()
 	}
 
-	pub fn apply_rules(&mut self) {
-		let data = get_data();
+	pub fn apply_rules(&mut self, data: &Data) {
 		let rules = data.get_rules();
 		self.inputs().sort_by(|a, b| {
 			let a_index = rules.promoters.get(a).unwrap();
@@ -43,10 +43,7 @@ impl Gene {
 	}
 
 	pub fn transfer(&self, x: f64) -> f64 {
-		let data = &self.data;
-		data.params.ymin
-			+ (data.params.ymax - data.params.ymin)
-				/ (1.0 + (x / data.params.k).powf(data.params.n))
+		self.data.params.transfer(x)
 	}
 
 	pub fn model(&self, sum: f64, state: f64) -> f64 {
@@ -107,41 +104,32 @@ impl Gene {
 		cached.insert(self.promoter(), (off, on, curr_std.2, curr_std.3));
 	}
 
+	pub fn parts<'a>(&self, data: &'a Data) -> Vec<&'a Part> {
+		self.inputs
+			.iter()
+			.chain(self.data.parts.iter())
+			.map(|name| data.get_part(name))
+			.collect()
+	}
+
 	pub fn into_dna(
 		&self,
-		dna: &mut String,
-		plasmid: &mut String,
+		data: &Data,
+		plasmid: &mut Plasmid,
 		promoter_colors: &mut HashMap<String, String>,
 	) {
-		let data = get_data();
 		for inp in &self.inputs() {
-			let part = data.get_part(&inp);
-			let start = dna.len();
-			let end = start + part.seq.len();
-
-			dna.push_str(&part.seq);
-			plasmid.push_str(&Dna::make_plasmid_part(
-				&part.kind,
-				start,
-				end,
-				&part.name,
+			let part = data.get_part(inp);
+			plasmid.push(
+				part,
 				promoter_colors.get(inp).unwrap_or(&"white".to_string()),
-			));
+				Some(format!("input of gate {}", self.gate)),
+			);
 		}
 
 		for part_name in &self.data.parts {
 			let part = data.get_part(part_name);
-			let start = dna.len();
-			let end = start + part.seq.len();
-
-			dna.push_str(&part.seq);
-			plasmid.push_str(&Dna::make_plasmid_part(
-				&part.kind,
-				start,
-				end,
-				&part.name,
-				&self.color(),
-			));
+			plasmid.push(part, &self.color(), Some(format!("gate {}", self.gate)));
 		}
 
 		promoter_colors.insert(self.promoter(), self.color());
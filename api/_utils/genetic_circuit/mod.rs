@@ -1,17 +1,28 @@
 mod actuator;
+mod calibration;
+mod check;
 mod component;
 mod gene;
+mod motif;
 mod signal;
+mod stability;
+mod timing;
 
 pub use actuator::Actuator;
+pub use calibration::{read_traces, Calibration, Comparison, Metrics, Trace};
+pub use check::{Failure, FailureKind};
 pub use component::Component;
 pub use gene::Gene;
+pub use motif::{Motif, MotifKind};
 pub use signal::Signal;
+pub use stability::StableStates;
+pub use timing::{steps_to, TimingAnalysis};
 
-use crate::_utils::{data, dna, logic_circuit};
-use data::{get_data, PartKind};
-use dna::Dna;
+use crate::_utils::{data, dna, logic_circuit, sbol};
+use data::{Data, PartKind};
+use dna::{Dna, Plasmid};
 use logic_circuit::Testbench;
+use sbol::{InteractionKind, Participant, Sbol, SbolDocument};
 use serde::Serialize;
 use std::collections::HashMap;
 
@@ -21,18 +32,23 @@ pub struct SimulationData {
 	steady_states: HashMap<String, (f64, f64)>,
 }
 
+/// A circuit with a gene assigned to every gate, simulated over its
+/// testbenches. [`into_dna`](Self::into_dna) lays it out as [`Dna`].
 #[derive(Serialize, Debug)]
 pub struct GeneticCircuit {
 	pub inputs: Vec<Signal>,
 	pub outputs: Vec<Actuator>,
 	pub components: Vec<Component>,
+	pub motifs: Vec<Motif>,
 	pub score: Option<f64>,
-	pub simulation: Option<SimulationData>,
+	pub simulations: HashMap<String, SimulationData>,
+	pub timing: Option<TimingAnalysis>,
+	pub stability: Option<Vec<StableStates>>,
+	pub library: String,
 }
 
 impl GeneticCircuit {
-	pub fn apply_rules(&mut self) {
-		let data = get_data();
+	pub fn apply_rules(&mut self, data: &Data) {
 		let rules = data.get_rules();
 		self.components.sort_by(|a, b| {
 			let a_index = rules.gates.get(&a.group()).unwrap();
@@ -41,7 +57,17 @@ impl GeneticCircuit {
 		});
 
 		for comp in &mut self.components {
-			comp.apply_rules();
+			comp.apply_rules(data);
+		}
+	}
+
+	pub fn analyze_timing(&mut self) {
+		self.timing = Some(TimingAnalysis::new(self));
+	}
+
+	pub fn analyze_stability(&mut self) {
+		if self.has_feedback() {
+			self.stability = Some(self.stable_states());
 		}
 	}
 
@@ -49,110 +75,143 @@ impl GeneticCircuit {
 		(-x / 10.0).exp()
 	}
 
-	pub fn into_dna(&self) -> Dna {
-		let data = get_data();
-		let mut gates_plasmid = String::new();
+	pub fn into_plasmids(&self, data: &Data) -> (Plasmid, Plasmid) {
 		let mut promoter_colors = HashMap::new();
 
-		let pre_gates = data.get_part("gates_pre_backbone");
-		let mut gates_dna = pre_gates.seq.to_string();
-
-		gates_plasmid += &Dna::make_plasmid_part(
-			&pre_gates.kind,
-			0,
-			gates_dna.len(),
-			&pre_gates.name,
-			"white",
-		);
-
+		let mut gates = Plasmid::new("gates-plasmid", "Genetic gates of the compiled circuit.");
+		gates.push_all(data.get_backbone("gates_pre_backbone"));
 		for comp in &self.components {
-			comp.into_dna(&mut gates_dna, &mut gates_plasmid, &mut promoter_colors);
+			comp.into_dna(data, &mut gates, &mut promoter_colors);
 		}
-
-		let post_gates1 = data.get_part("gates_post_backbone1");
-		let post_gates2 = data.get_part("gates_post_backbone2");
-
-		let start1 = gates_dna.len();
-		let end1 = start1 + post_gates1.seq.len();
-
-		gates_dna += &post_gates1.seq;
-
-		let start2 = gates_dna.len();
-		let end2 = start2 + post_gates2.seq.len();
-
-		gates_dna += &post_gates2.seq;
-
-		gates_plasmid +=
-			&Dna::make_plasmid_part(&post_gates1.kind, start1, end1, &post_gates1.name, "white");
-		gates_plasmid +=
-			&Dna::make_plasmid_part(&post_gates2.kind, start2, end2, &post_gates2.name, "white");
-
-		let gates_title = Dna::make_plasmid_title("gates-plasmid", gates_dna.len());
-		let gates_plasmid_dna: String = Dna::make_plasmid_dna(&gates_dna);
-		let final_gates_plasmid = gates_title + &gates_plasmid + &gates_plasmid_dna;
+		gates.push_all(data.get_backbone("gates_post_backbone1"));
+		gates.push_all(data.get_backbone("gates_post_backbone2"));
 
 		// -----------------OUTPUT---------------------------
-		let mut output_plasmid = String::new();
-		let pre_output = data.get_part("output_pre_backbone");
-		let mut output_dna = pre_output.seq.to_owned();
-
-		output_plasmid += &Dna::make_plasmid_part(
-			&pre_output.kind,
-			0,
-			output_dna.len(),
-			&pre_output.name,
-			"white",
-		);
+		let mut output = Plasmid::new("output-plasmid", "Output actuator of the compiled circuit.");
+		output.push_all(data.get_backbone("output_pre_backbone"));
 
 		let out = &self.outputs[0];
-		let part = data.get_part(&out.input);
-		let start = output_dna.len();
-		let end = start + part.seq.len();
-
-		output_dna += &part.seq;
-
-		output_plasmid += &Dna::make_plasmid_part(
-			&part.kind,
-			start,
-			end,
-			&part.name,
+		let driver = self.components.iter().find_map(|comp| match comp {
+			Component::Gene(gene) if gene.promoter() == out.input => Some(gene.gate.to_string()),
+			_ => None,
+		});
+		output.push(
+			data.get_part(&out.input),
 			promoter_colors
 				.get(&out.input)
 				.unwrap_or(&"white".to_owned()),
+			driver.map(|gate| format!("output of gate {}", gate)),
 		);
 
-		let out_part = data.get_part(&out.name);
-		let start = output_dna.len();
-		let end = start + out_part.seq.len();
-
-		output_plasmid +=
-			&Dna::make_plasmid_part(&PartKind::Actuator, start, end, &out.name, "white");
+		output.push(
+			data.get_part(&out.name),
+			"white",
+			Some(format!("output {}", out.name)),
+		);
+		output.push_all(data.get_backbone("output_post_backbone"));
 
-		output_dna += &out_part.seq;
+		(gates, output)
+	}
 
-		let post_output = data.get_part("output_post_backbone");
-		let start = output_dna.len();
-		let end = start + post_output.seq.len();
+	pub fn into_dna(&self, data: &Data) -> Dna {
+		let (gates, output) = self.into_plasmids(data);
+		Dna::from_plasmids(&gates, &output)
+	}
 
-		output_plasmid +=
-			&Dna::make_plasmid_part(&post_output.kind, start, end, &post_output.name, "white");
-		output_dna += &post_output.seq;
+	pub fn into_sbol(&self, data: &Data) -> Sbol {
+		let (gates, output) = self.into_plasmids(data);
+		let mut doc = SbolDocument::new();
 
-		let output_title = Dna::make_plasmid_title("output-plasmid", output_plasmid.len());
+		// Units are laid out on the gates plasmid in the same order as
+		// `into_plasmids`, right after the pre backbone.
+		let mut units = Vec::new();
+		let mut gate_units = Vec::new();
+		let mut first = data.get_backbone("gates_pre_backbone").len();
+		for comp in &self.components {
+			if let Component::Gene(gene) = comp {
+				let parts = gene.parts(data);
+				let unit = doc.add_unit(&gene.name(), &parts);
+				gate_units.push((first, unit));
+				units.push((unit, gene.inputs(), Some(gene)));
+				first += parts.len();
+			}
+		}
 
-		let output_plasmid_dna = Dna::make_plasmid_dna(&output_dna);
-		let final_output_plasmid = output_title + &output_plasmid + &output_plasmid_dna;
+		let out = &self.outputs[0];
+		let out_parts = [data.get_part(&out.input), data.get_part(&out.name)];
+		let out_unit = doc.add_unit(&out.name, &out_parts);
+		units.push((out_unit, vec![out.input.to_string()], None));
+
+		doc.add_plasmid(&gates, &gate_units);
+		let out_first = data.get_backbone("output_pre_backbone").len();
+		doc.add_plasmid(&output, &[(out_first, out_unit)]);
+
+		for (source, _, gene) in &units {
+			let gene = match gene {
+				Some(gene) => gene,
+				None => continue,
+			};
+			let cds = gene
+				.data
+				.parts
+				.iter()
+				.position(|name| data.get_part(name).kind == PartKind::Cds);
+			let cds = match cds {
+				Some(i) => gene.inputs.len() + i,
+				None => continue,
+			};
+			let cds_name = &gene.parts(data)[cds].name;
+
+			for (target, inputs, _) in &units {
+				for (i, inp) in inputs.iter().enumerate() {
+					if *inp == gene.promoter() {
+						doc.add_interaction(
+							InteractionKind::Repression,
+							(cds_name, inp),
+							Participant::Feature(*source, cds),
+							Participant::Feature(*target, i),
+						);
+					}
+				}
+			}
+		}
 
-		Dna {
-			raw: gates_dna,
-			plasmid: final_gates_plasmid,
-			out_raw: output_dna,
-			out_plasmid: final_output_plasmid,
+		for sig in &self.inputs {
+			for (target, inputs, _) in &units {
+				for (i, inp) in inputs.iter().enumerate() {
+					if *inp == sig.promoter {
+						doc.add_interaction(
+							InteractionKind::Induction,
+							(&sig.name, inp),
+							Participant::Sensor(sig.name()),
+							Participant::Feature(*target, i),
+						);
+					}
+				}
+			}
 		}
+
+		doc.finish()
 	}
 
 	pub fn test(&mut self) -> f64 {
-		let mut cached = HashMap::new();
+		if !self.motifs.is_empty() {
+			return self.test_motifs();
+		}
+		if self.has_feedback() {
+			return self.test_stable_states();
+		}
+
+		let diff_score = self.test_logic(HashMap::new());
+		self.score = Some(diff_score);
+		diff_score
+	}
+
+	// Scores the output by its steady states, with `cached` holding the
+	// levels of promoters driven by something other than logic gates. Genes
+	// reading a promoter whose levels aren't known, like those of a motif,
+	// are left out, and an output that can't be reached scores zero.
+	pub fn test_logic(&self, mut cached: HashMap<String, (f64, f64, f64, f64)>) -> f64 {
 		for inp in &self.inputs {
 			cached.insert(
 				inp.promoter(),
@@ -161,45 +220,55 @@ impl GeneticCircuit {
 		}
 
 		for comp in &self.components {
-			comp.test_steady_state(&mut cached);
+			let known = match comp {
+				Component::Gene(gene) => {
+					!cached.contains_key(&gene.promoter())
+						&& gene.inputs.iter().all(|inp| cached.contains_key(inp))
+				}
+				Component::Signal(_) => true,
+			};
+			if known {
+				comp.test_steady_state(&mut cached);
+			}
 		}
 
-		let (_, _, diff, score) = cached[&self.outputs[0].input];
-		let diff_err = Self::inv_diff_error(diff);
-		let diff_score = diff_err * score;
-
-		self.score = Some(diff_score);
-		diff_score
+		match cached.get(&self.outputs[0].input) {
+			Some((_, _, diff, score)) => Self::inv_diff_error(*diff) * score,
+			None => 0.0,
+		}
 	}
 
-	pub fn simulate(&mut self, testbench: Testbench) {
-		let data = get_data();
+	pub fn simulate(&mut self, testbench: &Testbench) {
 		let mut states = HashMap::new();
 		let mut history: HashMap<String, Vec<f64>> = HashMap::new();
 		let mut steady_states: HashMap<String, (f64, f64)> = HashMap::new();
+		let feedback = self.has_feedback();
+		let levels = |inp: &Signal| {
+			let levels = testbench.levels.get(&inp.name).cloned();
+			levels.unwrap_or((inp.rpu_off, inp.rpu_on))
+		};
 		for inp in &self.inputs {
-			states.insert(inp.promoter(), inp.rpu_off);
+			let (off, _) = levels(inp);
+			states.insert(inp.promoter(), off);
 			history.insert(inp.promoter(), Vec::new());
-			steady_states.insert(inp.promoter(), (inp.rpu_off, inp.rpu_on));
+			steady_states.insert(inp.promoter(), levels(inp));
 		}
 		for comp in &self.components {
 			states.insert(comp.promoter(), 0.0);
 			history.insert(comp.promoter(), Vec::new());
-			comp.simulation_steady_state(&mut steady_states);
+			if !feedback {
+				comp.simulation_steady_state(&mut steady_states);
+			}
+		}
+		if feedback {
+			steady_states.extend(self.stable_steady_states());
 		}
-		for i in 0..1000 {
-			if testbench.breakpoints.contains_key(&i) {
-				let bp = testbench.breakpoints.get(&i).unwrap();
+		for i in 0..testbench.duration {
+			if let Some(bp) = testbench.breakpoints.get(&i) {
 				for (name, val) in bp {
-					let inp = data.get_signal(name);
-					states.insert(
-						inp.promoter.to_string(),
-						if *val == true {
-							inp.rpu_on
-						} else {
-							inp.rpu_off
-						},
-					);
+					let inp = self.inputs.iter().find(|inp| inp.name == *name).unwrap();
+					let (off, on) = levels(inp);
+					states.insert(inp.promoter.to_string(), if *val { on } else { off });
 				}
 			}
 
@@ -213,9 +282,12 @@ impl GeneticCircuit {
 				comp.model_and_save(&mut states, &mut history);
 			}
 		}
-		self.simulation = Some(SimulationData {
-			history,
-			steady_states,
-		})
+		self.simulations.insert(
+			testbench.name.to_string(),
+			SimulationData {
+				history,
+				steady_states,
+			},
+		);
 	}
 }
@@ -0,0 +1,226 @@
+use super::{Component, Gene, GeneticCircuit};
+use serde::Serialize;
+use std::collections::HashMap;
+
+const SETTLE_STEPS: usize = 1000;
+const RUN_STEPS: usize = 2000;
+const MIN_SWING: f64 = 0.01;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum MotifKind {
+	Oscillator,
+	Pulse,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Motif {
+	pub kind: MotifKind,
+	pub name: String,
+	pub input: Option<String>,
+	pub promoters: Vec<String>,
+	pub period: Option<f64>,
+	pub amplitude: Option<f64>,
+	pub width: Option<f64>,
+	// Lowest and highest level of the output once it's running.
+	pub levels: Option<(f64, f64)>,
+}
+
+fn run(genes: &[&Gene], states: &mut HashMap<String, f64>, steps: usize, watch: &str) -> Vec<f64> {
+	// Promoters driven from outside the motif, or by genes shared with the
+	// rest of the circuit, start off.
+	for gene in genes {
+		for promoter in gene.inputs.iter().chain(Some(&gene.data.promoter)) {
+			states.entry(promoter.to_string()).or_insert(0.0);
+		}
+	}
+	states.entry(watch.to_string()).or_insert(0.0);
+
+	// Runs thousands of steps for every candidate assignment, so the states
+	// are moved into a flat vector instead of being looked up by promoter.
+	let names: Vec<String> = states.keys().cloned().collect();
+	let index: HashMap<&str, usize> = names
+		.iter()
+		.enumerate()
+		.map(|(i, name)| (name.as_str(), i))
+		.collect();
+	let wiring: Vec<(usize, Vec<usize>)> = genes
+		.iter()
+		.map(|gene| {
+			let inputs = gene.inputs.iter().map(|inp| index[inp.as_str()]).collect();
+			(index[gene.data.promoter.as_str()], inputs)
+		})
+		.collect();
+	let mut values: Vec<f64> = names.iter().map(|name| states[name]).collect();
+	let watched = index[watch];
+
+	let mut trace = Vec::with_capacity(steps);
+	for _ in 0..steps {
+		for (gene, (out, inputs)) in genes.iter().zip(wiring.iter()) {
+			let sum: f64 = inputs.iter().map(|i| values[*i]).sum();
+			values[*out] += gene.model(sum, values[*out]);
+		}
+		trace.push(values[watched]);
+	}
+
+	for (name, value) in names.into_iter().zip(values) {
+		states.insert(name, value);
+	}
+	trace
+}
+
+impl Motif {
+	fn genes<'a>(&self, gc: &'a GeneticCircuit) -> Vec<&'a Gene> {
+		self.promoters
+			.iter()
+			.filter_map(|promoter| {
+				gc.components.iter().find_map(|comp| match comp {
+					Component::Gene(gene) if &gene.data.promoter == promoter => Some(gene),
+					_ => None,
+				})
+			})
+			.collect()
+	}
+
+	fn input_levels(&self, gc: &GeneticCircuit) -> (f64, f64) {
+		let input = self.input.as_ref().unwrap();
+		if let Some(sig) = gc.inputs.iter().find(|sig| &sig.promoter == input) {
+			return (sig.rpu_off, sig.rpu_on);
+		}
+		gc.components
+			.iter()
+			.find_map(|comp| match comp {
+				Component::Gene(gene) if &gene.data.promoter == input => {
+					let params = &gene.data.params;
+					Some((params.ymin / params.decay, params.ymax / params.decay))
+				}
+				_ => None,
+			})
+			.unwrap_or((0.0, 0.0))
+	}
+
+	// Lets the ring run from a lopsided start and measures the output after the
+	// first half, so the period is taken from the settled limit cycle.
+	fn analyze_oscillator(&mut self, gc: &GeneticCircuit) {
+		let genes = self.genes(gc);
+		let mut states = HashMap::new();
+		for (i, gene) in genes.iter().enumerate() {
+			let params = &gene.data.params;
+			let init = if i == 0 { params.ymax } else { params.ymin };
+			states.insert(gene.promoter(), init / params.decay);
+		}
+
+		let trace = run(&genes, &mut states, RUN_STEPS, &self.promoters[0]);
+		let tail = &trace[RUN_STEPS / 2..];
+		let max = tail.iter().cloned().fold(f64::MIN, f64::max);
+		let min = tail.iter().cloned().fold(f64::MAX, f64::min);
+		let amplitude = max - min;
+		self.amplitude = Some(amplitude);
+		self.levels = Some((min, max));
+		if amplitude < MIN_SWING * max.abs().max(MIN_SWING) {
+			return;
+		}
+
+		let peaks: Vec<usize> = (1..tail.len() - 1)
+			.filter(|&i| {
+				tail[i] > tail[i - 1] && tail[i] >= tail[i + 1] && tail[i] > min + amplitude / 2.0
+			})
+			.collect();
+		if peaks.len() > 1 {
+			let span = (peaks[peaks.len() - 1] - peaks[0]) as f64;
+			self.period = Some(span / (peaks.len() - 1) as f64);
+		}
+	}
+
+	// Settles the motif with its input off, switches the input on and
+	// measures how long the output stays above half of its peak.
+	fn analyze_pulse(&mut self, gc: &GeneticCircuit) {
+		let genes = self.genes(gc);
+		let input = self.input.clone().unwrap();
+		let (off, on) = self.input_levels(gc);
+
+		let mut states = HashMap::new();
+		states.insert(input.to_string(), off);
+		for gene in &genes {
+			states.insert(gene.promoter(), 0.0);
+		}
+		let baseline = *run(&genes, &mut states, SETTLE_STEPS, &self.promoters[0])
+			.last()
+			.unwrap();
+
+		states.insert(input, on);
+		let trace = run(&genes, &mut states, RUN_STEPS, &self.promoters[0]);
+		let peak = trace.iter().cloned().fold(baseline, f64::max);
+		let amplitude = peak - baseline;
+		self.amplitude = Some(amplitude);
+		self.levels = Some((baseline, peak));
+		if amplitude < MIN_SWING * peak.abs().max(MIN_SWING) {
+			return;
+		}
+
+		let half = baseline + amplitude / 2.0;
+		let rise = trace.iter().position(|x| *x >= half);
+		let fall = rise.and_then(|start| trace[start..].iter().position(|x| *x < half));
+		if let Some(fall) = fall {
+			self.width = Some(fall as f64);
+		}
+	}
+
+	pub fn analyze(&mut self, gc: &GeneticCircuit) {
+		match self.kind {
+			MotifKind::Oscillator => self.analyze_oscillator(gc),
+			MotifKind::Pulse => self.analyze_pulse(gc),
+		}
+	}
+
+	// The fold change between the lowest and highest output, the same scale
+	// as the on/off ratio logic is scored by. A ring that damps out or an
+	// input step that never produces a pulse scores zero.
+	pub fn score(&self) -> f64 {
+		let settled = match self.kind {
+			MotifKind::Oscillator => self.period.is_some(),
+			MotifKind::Pulse => self.width.is_some(),
+		};
+		match (settled, self.levels) {
+			(true, Some((low, high))) => high / low.max(MIN_SWING),
+			_ => 0.0,
+		}
+	}
+}
+
+impl GeneticCircuit {
+	pub fn analyze_motifs(&mut self) {
+		let mut motifs = self.motifs.clone();
+		for motif in &mut motifs {
+			motif.analyze(self);
+		}
+		self.motifs = motifs;
+	}
+
+	// Scores the motifs and the logic around them, the output of each motif
+	// driving the gates it feeds between its lowest and highest level. The
+	// circuit is only as good as its weakest part.
+	pub fn test_motifs(&mut self) -> f64 {
+		self.analyze_motifs();
+		let mut cached = HashMap::new();
+		let mut score = f64::MAX;
+		for motif in &self.motifs {
+			let (low, high) = motif.levels.unwrap_or((0.0, 0.0));
+			cached.insert(
+				motif.promoters[0].to_string(),
+				(low, high, 0.0, motif.score()),
+			);
+			score = score.min(motif.score());
+		}
+		// Genes inside a motif are scored with it, not as logic.
+		for motif in &self.motifs {
+			for promoter in &motif.promoters[1..] {
+				cached
+					.entry(promoter.to_string())
+					.or_insert((0.0, 0.0, 0.0, 0.0));
+			}
+		}
+		let score = score.min(self.test_logic(cached));
+		self.score = Some(score);
+		score
+	}
+}
@@ -0,0 +1,192 @@
+use super::{Component, Gene, GeneticCircuit};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+const MAX_ITERATIONS: usize = 500;
+const TOLERANCE: f64 = 1e-6;
+const MAX_CORNERS: usize = 6;
+// Fold change a gene needs between two states for them to count as distinct,
+// rather than the same state settled from different corners.
+const SEPARATION: f64 = 2.0;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StableStates {
+	pub inputs: HashMap<String, bool>,
+	pub states: Vec<HashMap<String, f64>>,
+}
+
+impl StableStates {
+	pub fn is_bistable(&self) -> bool {
+		self.states.len() > 1
+	}
+}
+
+fn ratio(a: f64, b: f64) -> f64 {
+	a.max(b) / a.min(b).max(TOLERANCE)
+}
+
+impl GeneticCircuit {
+	fn genes(&self) -> Vec<&Gene> {
+		self.components
+			.iter()
+			.filter_map(|comp| match comp {
+				Component::Gene(gene) => Some(gene),
+				_ => None,
+			})
+			.collect()
+	}
+
+	pub fn has_feedback(&self) -> bool {
+		let genes: HashMap<String, &Gene> = self
+			.genes()
+			.into_iter()
+			.map(|gene| (gene.promoter(), gene))
+			.collect();
+
+		fn visit(
+			promoter: &str,
+			genes: &HashMap<String, &Gene>,
+			done: &mut HashSet<String>,
+			stack: &mut HashSet<String>,
+		) -> bool {
+			if stack.contains(promoter) {
+				return true;
+			}
+			if done.contains(promoter) {
+				return false;
+			}
+			stack.insert(promoter.to_string());
+			let cyclic = genes
+				.get(promoter)
+				.is_some_and(|gene| gene.inputs.iter().any(|inp| visit(inp, genes, done, stack)));
+			stack.remove(promoter);
+			done.insert(promoter.to_string());
+			cyclic
+		}
+
+		let mut done = HashSet::new();
+		let mut stack = HashSet::new();
+		genes
+			.keys()
+			.any(|promoter| visit(promoter, &genes, &mut done, &mut stack))
+	}
+
+	// Iterates every gene towards its steady state until nothing moves, so
+	// starting points on either side of a feedback loop end up in different
+	// stable states. Returns false when the circuit keeps oscillating.
+	fn settle(genes: &[(&Gene, String)], states: &mut HashMap<String, f64>) -> bool {
+		for _ in 0..MAX_ITERATIONS {
+			let mut delta = 0.0f64;
+			for (gene, promoter) in genes {
+				let sum: f64 = gene.inputs.iter().map(|inp| states[inp]).sum();
+				let next = gene.transfer(sum) / gene.decay();
+				let prev = states.insert(promoter.to_string(), next).unwrap_or(0.0);
+				delta = delta.max((next - prev).abs() / next.max(TOLERANCE));
+			}
+			if delta < TOLERANCE {
+				return true;
+			}
+		}
+		false
+	}
+
+	pub fn stable_states(&self) -> Vec<StableStates> {
+		let genes: Vec<(&Gene, String)> = self
+			.genes()
+			.into_iter()
+			.map(|gene| (gene, gene.promoter()))
+			.collect();
+		let num_inputs = self.inputs.len();
+		let num_corners = genes.len().min(MAX_CORNERS);
+
+		let mut stable = Vec::new();
+		for combo in 0..(1usize << num_inputs) {
+			let mut base = HashMap::new();
+			let mut inputs = HashMap::new();
+			for (i, inp) in self.inputs.iter().enumerate() {
+				let on = (combo >> (num_inputs - 1 - i)) & 1 == 1;
+				base.insert(inp.promoter(), if on { inp.rpu_on } else { inp.rpu_off });
+				inputs.insert(inp.name(), on);
+			}
+
+			let mut states: Vec<HashMap<String, f64>> = Vec::new();
+			for corner in 0..(1usize << num_corners) {
+				let mut state = base.clone();
+				for (j, (gene, promoter)) in genes.iter().enumerate() {
+					let params = &gene.data.params;
+					let high = j < num_corners && (corner >> j) & 1 == 1;
+					let init = if high { params.ymax } else { params.ymin };
+					state.insert(promoter.to_string(), init / params.decay);
+				}
+				if !Self::settle(&genes, &mut state) {
+					continue;
+				}
+
+				let distinct = states.iter().all(|other| {
+					genes
+						.iter()
+						.any(|(_, promoter)| ratio(state[promoter], other[promoter]) > SEPARATION)
+				});
+				if distinct {
+					states.push(state);
+				}
+			}
+
+			stable.push(StableStates { inputs, states });
+		}
+		stable
+	}
+
+	pub fn test_stable_states(&mut self) -> f64 {
+		let out = self.outputs[0].input.to_string();
+		let stable = self.stable_states();
+
+		let separations: Vec<f64> = stable
+			.iter()
+			.filter(|st| st.is_bistable())
+			.map(|st| {
+				let levels: Vec<f64> = st.states.iter().map(|state| state[&out]).collect();
+				let max = levels.iter().cloned().fold(f64::MIN, f64::max);
+				let min = levels.iter().cloned().fold(f64::MAX, f64::min);
+				ratio(max, min)
+			})
+			.collect();
+
+		// A loop without any bistable input combination only ever scores below
+		// the worst bistable one, graded by how far apart its outputs still are.
+		let score = if separations.is_empty() {
+			let levels: Vec<f64> = stable
+				.iter()
+				.flat_map(|st| st.states.iter().map(|state| state[&out]))
+				.collect();
+			let max = levels.iter().cloned().fold(0.0, f64::max);
+			let min = levels.iter().cloned().fold(f64::MAX, f64::min);
+			if levels.is_empty() {
+				0.0
+			} else {
+				1.0 - 1.0 / ratio(max, min)
+			}
+		} else {
+			separations.into_iter().fold(f64::MAX, f64::min)
+		};
+
+		self.score = Some(score);
+		score
+	}
+
+	pub fn stable_steady_states(&self) -> HashMap<String, (f64, f64)> {
+		let mut steady_states: HashMap<String, (f64, f64)> = HashMap::new();
+		for st in self.stable_states() {
+			for state in &st.states {
+				for (promoter, value) in state {
+					let entry = steady_states
+						.entry(promoter.to_string())
+						.or_insert((*value, *value));
+					entry.0 = entry.0.min(*value);
+					entry.1 = entry.1.max(*value);
+				}
+			}
+		}
+		steady_states
+	}
+}
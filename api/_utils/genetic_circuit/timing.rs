@@ -0,0 +1,330 @@
+use super::{Component, Gene, GeneticCircuit};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+const MAX_STEPS: usize = 100_000;
+const EPSILON: f64 = 1e-9;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GateTiming {
+	pub name: String,
+	pub promoter: String,
+	pub rise: f64,
+	pub fall: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum HazardKind {
+	Static,
+	Dynamic,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Hazard {
+	pub kind: HazardKind,
+	pub signal: String,
+	pub gate: String,
+	pub delays: Vec<f64>,
+	pub width: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TimingAnalysis {
+	pub gates: Vec<GateTiming>,
+	pub critical_path: Vec<String>,
+	pub critical_delay: f64,
+	pub hazards: Vec<Hazard>,
+}
+
+// Earliest and latest a change of one input reaches a promoter through an
+// even and an odd number of inversions, and whether it may glitch there.
+#[derive(Clone, Copy, Default)]
+struct Arrival {
+	even: Option<(f64, f64)>,
+	odd: Option<(f64, f64)>,
+	glitch: bool,
+}
+
+// The window covering both `window` and `other` delayed by `delay`.
+fn widen(window: Option<(f64, f64)>, other: Option<(f64, f64)>, delay: f64) -> Option<(f64, f64)> {
+	let other = other.map(|(first, last)| (first + delay, last + delay));
+	match (window, other) {
+		(Some((a, b)), Some((c, d))) => Some((a.min(c), b.max(d))),
+		(window, other) => window.or(other),
+	}
+}
+
+pub fn steps_to(fraction: f64, decay: f64) -> f64 {
+	if decay >= 1.0 {
+		return 0.0;
+	}
+	(1.0 - fraction).ln() / (1.0 - decay).ln()
+}
+
+impl Gene {
+	pub fn decay(&self) -> f64 {
+		self.data.params.decay
+	}
+
+	// Steps from the driving input crossing its midpoint to this gene's output
+	// crossing its own, with the input relaxing towards `to` at rate `in_decay`.
+	pub fn propagation_delay(&self, from: f64, to: f64, in_decay: f64) -> f64 {
+		let decay = self.decay();
+		let start = self.transfer(from) / decay;
+		let end = self.transfer(to) / decay;
+		let mid = (start + end) / 2.0;
+		if (end - start).abs() < EPSILON {
+			return 0.0;
+		}
+
+		let mut state = start;
+		let mut crossed = MAX_STEPS as f64;
+		for t in 1..=MAX_STEPS {
+			let driven = 1.0 - (1.0 - in_decay.min(1.0)).powi(t as i32);
+			let input = from + (to - from) * driven;
+			let next = state + self.model(input, state);
+			if (next - mid) * (start - mid) <= 0.0 {
+				let frac = (mid - state) / (next - state);
+				crossed = (t - 1) as f64 + frac;
+				break;
+			}
+			state = next;
+		}
+
+		(crossed - steps_to(0.5, in_decay)).max(0.0)
+	}
+}
+
+impl TimingAnalysis {
+	fn decay_of(comp: Option<&Component>) -> f64 {
+		match comp {
+			Some(Component::Gene(gene)) => gene.decay(),
+			_ => 1.0,
+		}
+	}
+
+	fn levels(gc: &GeneticCircuit, order: &[&Gene]) -> HashMap<String, (f64, f64)> {
+		let mut levels = HashMap::new();
+		for inp in &gc.inputs {
+			levels.insert(inp.promoter(), (inp.rpu_off, inp.rpu_on));
+		}
+		for gene in order {
+			let (mut low, mut high) = (0.0, 0.0);
+			for inp in &gene.inputs {
+				let (l, h) = levels.get(inp).cloned().unwrap_or((0.0, 0.0));
+				low += l;
+				high += h;
+			}
+			let (off, on) = gene.steady_state(high, low);
+			levels.insert(gene.promoter(), (off, on));
+		}
+		levels
+	}
+
+	fn topological(gc: &GeneticCircuit) -> Vec<&Gene> {
+		let genes: HashMap<String, &Gene> = gc
+			.components
+			.iter()
+			.filter_map(|comp| match comp {
+				Component::Gene(gene) => Some((gene.promoter(), gene)),
+				_ => None,
+			})
+			.collect();
+
+		fn visit<'a>(
+			promoter: &str,
+			genes: &HashMap<String, &'a Gene>,
+			visited: &mut HashSet<String>,
+			order: &mut Vec<&'a Gene>,
+		) {
+			if visited.contains(promoter) {
+				return;
+			}
+			visited.insert(promoter.to_string());
+			if let Some(gene) = genes.get(promoter) {
+				for inp in &gene.inputs {
+					visit(inp, genes, visited, order);
+				}
+				order.push(gene);
+			}
+		}
+
+		let mut visited = HashSet::new();
+		let mut order = Vec::new();
+		for comp in &gc.components {
+			visit(&comp.promoter(), &genes, &mut visited, &mut order);
+		}
+		order
+	}
+
+	fn gate_timing(
+		gene: &Gene,
+		levels: &HashMap<String, (f64, f64)>,
+		drivers: &HashMap<String, &Component>,
+	) -> GateTiming {
+		let (mut rise, mut fall) = (0.0f64, 0.0f64);
+		for (i, inp) in gene.inputs.iter().enumerate() {
+			// Worst case for this input switching while the others sit low.
+			let rest: f64 = gene
+				.inputs
+				.iter()
+				.enumerate()
+				.filter(|(j, _)| *j != i)
+				.map(|(_, other)| levels.get(other).map_or(0.0, |l| l.0))
+				.sum();
+			let (low, high) = levels.get(inp).cloned().unwrap_or((0.0, 0.0));
+			let in_decay = Self::decay_of(drivers.get(inp).cloned());
+			fall = fall.max(gene.propagation_delay(rest + low, rest + high, in_decay));
+			rise = rise.max(gene.propagation_delay(rest + high, rest + low, in_decay));
+		}
+
+		GateTiming {
+			name: gene.name(),
+			promoter: gene.promoter(),
+			rise,
+			fall,
+		}
+	}
+
+	// Reconvergent fan-out of every input, found by carrying the window in
+	// which its change arrives along each parity instead of every path. A
+	// gate reached through inputs of opposite parity at different times has
+	// a static hazard, and one where a glitching input meets another change
+	// of the same input has a dynamic hazard.
+	fn hazards(
+		gc: &GeneticCircuit,
+		order: &[&Gene],
+		timings: &HashMap<String, GateTiming>,
+	) -> Vec<Hazard> {
+		let mut hazards = Vec::new();
+		for sig in &gc.inputs {
+			let mut arrivals: HashMap<String, Arrival> = HashMap::new();
+			let start = Arrival {
+				even: Some((0.0, 0.0)),
+				..Arrival::default()
+			};
+			arrivals.insert(sig.promoter(), start);
+
+			for gene in order {
+				let timing = &timings[&gene.promoter()];
+				let delay = timing.rise.max(timing.fall);
+				let incoming: Vec<Arrival> = gene
+					.inputs
+					.iter()
+					.filter_map(|inp| arrivals.get(inp).cloned())
+					.collect();
+				if incoming.is_empty() {
+					continue;
+				}
+
+				// Every gate inverts, so parities swap on the way through.
+				let mut reached = Arrival::default();
+				for arrival in &incoming {
+					reached.even = widen(reached.even, arrival.odd, delay);
+					reached.odd = widen(reached.odd, arrival.even, delay);
+					reached.glitch |= arrival.glitch;
+				}
+
+				let mut delays: Vec<f64> = [reached.even, reached.odd]
+					.iter()
+					.flatten()
+					.flat_map(|(first, last)| vec![*first, *last])
+					.collect();
+				delays.sort_by(f64::total_cmp);
+				delays.dedup_by(|a, b| (*a - *b).abs() < EPSILON);
+				let width = delays.last().unwrap_or(&0.0) - delays.first().unwrap_or(&0.0);
+
+				let kind = match incoming.len() > 1 && width > EPSILON {
+					false => None,
+					true if incoming.iter().any(|arrival| arrival.glitch) => {
+						Some(HazardKind::Dynamic)
+					}
+					true if reached.even.is_some() && reached.odd.is_some() => {
+						Some(HazardKind::Static)
+					}
+					true => None,
+				};
+				if let Some(kind) = kind {
+					reached.glitch = true;
+					hazards.push(Hazard {
+						kind,
+						signal: sig.name(),
+						gate: gene.name(),
+						delays,
+						width,
+					});
+				}
+
+				arrivals.insert(gene.promoter(), reached);
+			}
+		}
+		hazards
+	}
+
+	pub fn new(gc: &GeneticCircuit) -> Self {
+		let order = Self::topological(gc);
+		let levels = Self::levels(gc, &order);
+		let drivers: HashMap<String, &Component> = gc
+			.components
+			.iter()
+			.map(|comp| (comp.promoter(), comp))
+			.collect();
+
+		let mut timings = HashMap::new();
+		let mut gates = Vec::new();
+		for gene in &order {
+			let timing = Self::gate_timing(gene, &levels, &drivers);
+			timings.insert(gene.promoter(), timing.clone());
+			gates.push(timing);
+		}
+
+		let mut arrival: HashMap<String, (f64, Option<String>)> = HashMap::new();
+		for inp in &gc.inputs {
+			arrival.insert(inp.promoter(), (0.0, None));
+		}
+		for gene in &order {
+			let timing = &timings[&gene.promoter()];
+			let delay = timing.rise.max(timing.fall);
+			let mut latest = (0.0, None);
+			for inp in &gene.inputs {
+				if let Some((time, _)) = arrival.get(inp) {
+					if latest.1.is_none() || *time > latest.0 {
+						latest = (*time, Some(inp.to_string()));
+					}
+				}
+			}
+			arrival.insert(gene.promoter(), (latest.0 + delay, latest.1));
+		}
+
+		let names: HashMap<String, String> = gc
+			.inputs
+			.iter()
+			.map(|inp| (inp.promoter(), inp.name()))
+			.chain(order.iter().map(|gene| (gene.promoter(), gene.name())))
+			.collect();
+
+		let mut critical_path = Vec::new();
+		let mut critical_delay = 0.0;
+		if let Some(out) = gc.outputs.first() {
+			critical_delay = arrival.get(&out.input).map_or(0.0, |a| a.0);
+			critical_path.push(out.name.to_string());
+			let mut curr = Some(out.input.to_string());
+			while let Some(promoter) = curr {
+				if let Some(name) = names.get(&promoter) {
+					critical_path.push(name.to_string());
+				}
+				curr = arrival.get(&promoter).and_then(|a| a.1.clone());
+			}
+			critical_path.reverse();
+		}
+
+		let hazards = Self::hazards(gc, &order, &timings);
+
+		Self {
+			gates,
+			critical_path,
+			critical_delay,
+			hazards,
+		}
+	}
+}
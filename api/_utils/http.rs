@@ -0,0 +1,105 @@
+use crate::_utils::error::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+pub struct NowEvent {
+	#[serde(rename = "Action")]
+	pub action: String,
+	pub body: String,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, PartialEq, Debug)]
+pub enum Method {
+	POST,
+	GET,
+	OPTIONS,
+	PUT,
+	DELETE,
+	PATCH,
+}
+
+impl Method {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"POST" => Some(Self::POST),
+			"GET" => Some(Self::GET),
+			"OPTIONS" => Some(Self::OPTIONS),
+			"PUT" => Some(Self::PUT),
+			"DELETE" => Some(Self::DELETE),
+			"PATCH" => Some(Self::PATCH),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Deserialize)]
+pub struct Request {
+	pub host: String,
+	pub path: String,
+	pub method: Method,
+	pub headers: HashMap<String, String>,
+	#[serde(default = "String::new")]
+	pub body: String,
+	pub encoding: Option<String>,
+}
+
+impl Request {
+	pub fn decoded_body(&self) -> Result<String, Error> {
+		if self.encoding.as_deref() != Some("base64") {
+			return Ok(self.body.to_string());
+		}
+		let bytes = base64::decode(&self.body).map_err(|_| Error::InvalidBody {
+			encoding: "base64".to_string(),
+		})?;
+		String::from_utf8(bytes).map_err(|_| Error::InvalidBody {
+			encoding: "UTF-8".to_string(),
+		})
+	}
+
+	// Value of `name` in the query string, `+` and `%20` read as spaces.
+	pub fn query(&self, name: &str) -> Option<String> {
+		let (_, query) = self.path.split_once('?')?;
+		query
+			.split('&')
+			.filter_map(|pair| pair.split_once('='))
+			.find(|(key, _)| *key == name)
+			.map(|(_, value)| value.replace('+', " ").replace("%20", " "))
+	}
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+	pub status_code: u16,
+	pub headers: HashMap<String, String>,
+	pub body: String,
+	pub encoding: Option<String>,
+}
+
+impl Response {
+	pub fn new(status_code: u16, body: String) -> Self {
+		let mut headers = HashMap::new();
+		headers.insert("Access-Control-Allow-Origin".to_string(), "*".to_string());
+		Self {
+			status_code,
+			headers,
+			body,
+			encoding: None,
+		}
+	}
+
+	pub fn preflight(methods: &str) -> Self {
+		let mut res = Self::new(200, String::new());
+		res.headers.insert(
+			"Access-Control-Request-Method".to_string(),
+			methods.to_string(),
+		);
+		res.headers.insert(
+			"Access-Control-Request-Headers".to_string(),
+			"*".to_string(),
+		);
+		res
+	}
+}
@@ -1,7 +1,8 @@
+use crate::_utils::span::Span;
 use regex::Regex;
 use std::{
 	fmt::{Display, Formatter, Result},
-	iter::{Enumerate, Peekable},
+	iter::Peekable,
 	str::Chars,
 };
 
@@ -12,6 +13,7 @@ pub enum TokenKind {
 	Name,
 	Keyword,
 	Value,
+	Literal,
 	Unknown,
 }
 
@@ -25,34 +27,93 @@ impl Display for TokenKind {
 pub struct Token {
 	pub kind: TokenKind,
 	pub value: String,
-	pub pos: usize,
+	pub span: Span,
 }
 
+/// Splits a source into tokens, each with the span it was read from.
 pub struct LexerIter<'a> {
-	chars: Peekable<Enumerate<Chars<'a>>>,
+	source: &'a str,
+	file: Option<String>,
+	chars: Peekable<Chars<'a>>,
+	byte: usize,
+	line: usize,
+	col: usize,
 }
 
 impl<'a> LexerIter<'a> {
 	pub fn new(text: Chars<'a>) -> Self {
 		Self {
-			chars: text.enumerate().peekable(),
+			source: text.as_str(),
+			file: None,
+			chars: text.peekable(),
+			byte: 0,
+			line: 1,
+			col: 1,
+		}
+	}
+
+	// Same as `new`, with the file name in the span of every token.
+	pub fn with_file(text: Chars<'a>, file: &str) -> Self {
+		Self {
+			file: Some(file.to_string()),
+			..Self::new(text)
+		}
+	}
+
+	pub fn source(&self) -> &'a str {
+		self.source
+	}
+
+	pub fn file(&self) -> Option<String> {
+		self.file.clone()
+	}
+
+	fn bump(&mut self) -> Option<char> {
+		let c = self.chars.next()?;
+		self.byte += c.len_utf8();
+		if c == '\n' {
+			self.line += 1;
+			self.col = 1;
+		} else {
+			self.col += 1;
+		}
+		Some(c)
+	}
+
+	// Span from `start` to the current position.
+	fn span(&self, start: &Span) -> Span {
+		Span {
+			end: self.byte,
+			..start.clone()
 		}
 	}
 
 	fn scan_next(&mut self, pattern: &str) -> String {
 		let rg = Regex::new(pattern).unwrap();
 		let mut ret = String::new();
-		let (_, c) = self.chars.next().unwrap();
-		ret.push(c);
-		while let Some((_, ch)) = self.chars.peek() {
+		ret.push(self.bump().unwrap());
+		while let Some(ch) = self.chars.peek() {
 			if !rg.is_match(&ch.to_string()) {
 				return ret;
 			}
-			let (_, c) = self.chars.next().unwrap();
-			ret.push(c);
+			ret.push(self.bump().unwrap());
 		}
 		ret
 	}
+
+	// Reads a double quoted literal, returning `None` if the closing quote is
+	// missing.
+	fn scan_literal(&mut self) -> Option<String> {
+		self.bump();
+		let mut ret = String::new();
+		while let Some(c) = self.bump() {
+			if c == '"' {
+				return Some(ret);
+			}
+			ret.push(c);
+		}
+		None
+	}
 }
 
 impl<'a> Iterator for LexerIter<'a> {
@@ -61,55 +122,84 @@ impl<'a> Iterator for LexerIter<'a> {
 	fn next(&mut self) -> Option<Self::Item> {
 		let chars = Regex::new("[a-zA-Z]").unwrap();
 		let numbers = Regex::new("[0-9]").unwrap();
-		while let Some((pos, ch)) = self.chars.peek().cloned() {
+		while let Some(ch) = self.chars.peek().cloned() {
+			let start = Span {
+				file: self.file.clone(),
+				line: self.line,
+				col: self.col,
+				start: self.byte,
+				end: self.byte,
+			};
+			if ch == '"' {
+				let res = match self.scan_literal() {
+					Some(value) => Token {
+						kind: TokenKind::Literal,
+						value,
+						span: self.span(&start),
+					},
+					None => Token {
+						kind: TokenKind::Unknown,
+						value: "\"".to_string(),
+						span: Span {
+							end: start.start + 1,
+							..start
+						},
+					},
+				};
+				return Some(res);
+			}
 			let group = match ch {
 				'~' => self.scan_next("[|&^]"),
 				'-' => self.scan_next(">"),
-				c if chars.is_match(&c.to_string()) => self.scan_next("[a-zA-Z0-9]"),
-				c if numbers.is_match(&c.to_string()) => self.scan_next("[0-9]"),
+				c if chars.is_match(&c.to_string()) => self.scan_next("[a-zA-Z0-9_]"),
+				c if numbers.is_match(&c.to_string()) => self.scan_next("[0-9.]"),
 				c => {
-					self.chars.next();
+					self.bump();
 					c.to_string()
 				}
 			};
 			if ["\n", "\t", " "].contains(&group.as_str()) {
 				continue;
 			}
+			let span = self.span(&start);
 			let res = match group.as_str() {
+				// `part`, `gene`, `reg`, `auto`, `osc` and `pulse` came after the
+				// others and stay names, so sources that use them as wires still
+				// compile. The parser reads them as keywords where one can start.
 				"out" | "in" | "let" | "impl" | "test" | "for" | "mod" | "env" => Token {
 					kind: TokenKind::Keyword,
 					value: group.to_string(),
-					pos,
+					span: span.clone(),
 				},
-				"(" | ")" | "{" | "}" | "," | ";" | "=" | "@" => Token {
+				"(" | ")" | "{" | "}" | "," | ";" | "=" | "@" | "#" | "[" | "]" => Token {
 					kind: TokenKind::Sign,
 					value: group.to_string(),
-					pos,
+					span: span.clone(),
 				},
 				"not" | "nor" => Token {
 					kind: TokenKind::Operation,
 					value: group.to_string(),
-					pos,
+					span: span.clone(),
 				},
 				"true" | "false" => Token {
 					kind: TokenKind::Value,
 					value: group.to_string(),
-					pos,
+					span: span.clone(),
 				},
-				c if chars.is_match(&c.to_string()) => Token {
+				c if chars.is_match(c) => Token {
 					kind: TokenKind::Name,
 					value: group.to_string(),
-					pos,
+					span: span.clone(),
 				},
-				c if numbers.is_match(&c.to_string()) => Token {
+				c if numbers.is_match(c) => Token {
 					kind: TokenKind::Value,
 					value: group.to_string(),
-					pos,
+					span: span.clone(),
 				},
 				_ => Token {
 					kind: TokenKind::Unknown,
 					value: group.to_string(),
-					pos,
+					span: span.clone(),
 				},
 			};
 			return Some(res);
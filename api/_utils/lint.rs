@@ -0,0 +1,268 @@
+use crate::_utils::{error, lexer, parser};
+use error::Error;
+use lexer::Token;
+use parser::{Enviroment, Implementation, LogicOp, Module, Operation};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+// Outputs more gates deep than this take long to settle.
+pub const MAX_DEPTH: usize = 6;
+// Constant gates are found by trying every input, up to this many inputs.
+const MAX_CONSTANT_INPUTS: usize = 12;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Lint {
+	UnusedWire,
+	UnusedInput,
+	ConstantGate,
+	DoubleInversion,
+	DeepChain,
+	UnusedSignal,
+}
+
+impl Lint {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Lint::UnusedWire => "unused_wire",
+			Lint::UnusedInput => "unused_input",
+			Lint::ConstantGate => "constant_gate",
+			Lint::DoubleInversion => "double_inversion",
+			Lint::DeepChain => "deep_chain",
+			Lint::UnusedSignal => "unused_signal",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"unused_wire" => Some(Lint::UnusedWire),
+			"unused_input" => Some(Lint::UnusedInput),
+			"constant_gate" => Some(Lint::ConstantGate),
+			"double_inversion" => Some(Lint::DoubleInversion),
+			"deep_chain" => Some(Lint::DeepChain),
+			"unused_signal" => Some(Lint::UnusedSignal),
+			_ => None,
+		}
+	}
+}
+
+fn allows(allow: &[Token], lint: Lint) -> bool {
+	allow.iter().any(|token| token.value == lint.name())
+}
+
+fn logic_ops(imp: &Implementation) -> impl Iterator<Item = &LogicOp> {
+	imp.body.iter().filter_map(|op| match op {
+		Operation::Logic(lop) => Some(lop),
+		Operation::Register(_) => None,
+	})
+}
+
+// Wires read by a gate. Oscillators read nothing, their only arg is the
+// number of stages.
+fn read_wires(imp: &Implementation) -> HashSet<&str> {
+	logic_ops(imp)
+		.filter(|lop| lop.symbol.value != "osc")
+		.flat_map(|lop| lop.args.iter().map(|arg| arg.value.as_str()))
+		.collect()
+}
+
+fn is_inverter(lop: &LogicOp) -> bool {
+	match lop.symbol.value.as_str() {
+		"not" => true,
+		"nor" => lop.args.len() == 2 && lop.args[0].value == lop.args[1].value,
+		_ => false,
+	}
+}
+
+// Outputs of every gate for every input, `None` where a gate depends on a
+// register, an oscillator or a pulse.
+fn truth_table(module: &Module, ops: &[&LogicOp]) -> Option<Vec<Vec<Option<bool>>>> {
+	let num_inputs = module.ins.len();
+	if num_inputs > MAX_CONSTANT_INPUTS {
+		return None;
+	}
+	let mut table = Vec::new();
+	for state in 0..(1usize << num_inputs) {
+		let mut wires: HashMap<&str, Option<bool>> = module
+			.ins
+			.iter()
+			.enumerate()
+			.map(|(i, inp)| (inp.value.as_str(), Some((state >> i) & 1 == 1)))
+			.collect();
+		let mut row = Vec::new();
+		for lop in ops {
+			let arg = |i: usize| wires.get(lop.args[i].value.as_str()).cloned().flatten();
+			let value = match lop.symbol.value.as_str() {
+				"not" => arg(0).map(|a| !a),
+				"nor" => match (arg(0), arg(1)) {
+					(Some(true), _) | (_, Some(true)) => Some(false),
+					(Some(false), Some(false)) => Some(true),
+					_ => None,
+				},
+				_ => None,
+			};
+			wires.insert(lop.var.value.as_str(), value);
+			row.push(value);
+		}
+		table.push(row);
+	}
+	Some(table)
+}
+
+fn lint_constant_gates(module: &Module, ops: &[&LogicOp], lints: &mut Vec<(Lint, Error)>) {
+	let table = match truth_table(module, ops) {
+		Some(table) => table,
+		None => return,
+	};
+	// Only the first gate of a constant chain is reported.
+	let mut constant = HashSet::new();
+	for (i, lop) in ops.iter().enumerate() {
+		let values: HashSet<Option<bool>> = table.iter().map(|row| row[i]).collect();
+		let value = match values.into_iter().collect::<Vec<_>>()[..] {
+			[Some(value)] => value,
+			_ => continue,
+		};
+		let root = !lop.args.iter().any(|arg| constant.contains(&arg.value));
+		constant.insert(lop.var.value.to_string());
+		if root {
+			lints.push((
+				Lint::ConstantGate,
+				Error::ConstantOutput {
+					wire: lop.var.value.to_string(),
+					span: lop.var.span.clone(),
+					value,
+				},
+			));
+		}
+	}
+}
+
+// Number of gates on the longest path from an input to every wire, counting
+// the two gates of a pulse. Registers and oscillators start a path.
+fn depths<'a>(ops: &[&'a LogicOp]) -> HashMap<&'a str, usize> {
+	let mut depths: HashMap<&str, usize> = HashMap::new();
+	for lop in ops {
+		let deepest = |depths: &HashMap<&str, usize>| {
+			lop.args
+				.iter()
+				.filter_map(|arg| depths.get(arg.value.as_str()))
+				.max()
+				.cloned()
+				.unwrap_or(0)
+		};
+		let depth = match lop.symbol.value.as_str() {
+			"osc" => 1,
+			"pulse" => deepest(&depths) + 2,
+			_ => deepest(&depths) + 1,
+		};
+		depths.insert(lop.var.value.as_str(), depth);
+	}
+	depths
+}
+
+// Lints of an implementation and the module it implements.
+pub fn lint_implementation(module: &Module, imp: &Implementation) -> Vec<(Lint, Error)> {
+	let mut lints = Vec::new();
+	let ops: Vec<&LogicOp> = logic_ops(imp).collect();
+	let read = read_wires(imp);
+	let outs: HashSet<&str> = module.outs.iter().map(|out| out.value.as_str()).collect();
+
+	for lop in &ops {
+		let wire = lop.var.value.as_str();
+		if !read.contains(wire) && !outs.contains(wire) {
+			lints.push((
+				Lint::UnusedWire,
+				Error::NotUsed {
+					name: wire.to_string(),
+					span: lop.var.span.clone(),
+				},
+			));
+		}
+	}
+	for inp in &module.ins {
+		if !read.contains(inp.value.as_str()) {
+			lints.push((
+				Lint::UnusedInput,
+				Error::NotUsed {
+					name: inp.value.to_string(),
+					span: inp.span.clone(),
+				},
+			));
+		}
+	}
+
+	lint_constant_gates(module, &ops, &mut lints);
+
+	let inverters: HashMap<&str, &LogicOp> = ops
+		.iter()
+		.filter(|lop| is_inverter(lop))
+		.map(|lop| (lop.var.value.as_str(), *lop))
+		.collect();
+	for lop in ops.iter().filter(|lop| is_inverter(lop)) {
+		if let Some(inner) = inverters.get(lop.args[0].value.as_str()) {
+			lints.push((
+				Lint::DoubleInversion,
+				Error::DoubleInversion {
+					wire: lop.var.value.to_string(),
+					span: lop.var.span.clone(),
+					input: inner.args[0].value.to_string(),
+				},
+			));
+		}
+	}
+
+	let depths = depths(&ops);
+	for lop in ops
+		.iter()
+		.filter(|lop| outs.contains(lop.var.value.as_str()))
+	{
+		let depth = depths[lop.var.value.as_str()];
+		if depth > MAX_DEPTH {
+			lints.push((
+				Lint::DeepChain,
+				Error::DeepChain {
+					wire: lop.var.value.to_string(),
+					span: lop.var.span.clone(),
+					depth,
+				},
+			));
+		}
+	}
+
+	// Gate lints can be allowed on the gate, input lints on the module, all
+	// of them on the implementation.
+	lints.sort_by_key(|(_, error)| error.span().map(|span| span.start));
+	lints
+		.into_iter()
+		.filter(|(lint, error)| {
+			let gate = ops
+				.iter()
+				.find(|lop| Some(&lop.var.span) == error.span())
+				.is_some_and(|lop| allows(&lop.allow, *lint));
+			let module = *lint == Lint::UnusedInput && allows(&module.allow, *lint);
+			!gate && !module && !allows(&imp.allow, *lint)
+		})
+		.collect()
+}
+
+// Signals of `env` bound to an input of `module` that its implementation
+// never reads.
+pub fn lint_signals(env: &Enviroment, module: &Module, imp: &Implementation) -> Vec<(Lint, Error)> {
+	if allows(&env.allow, Lint::UnusedSignal) {
+		return Vec::new();
+	}
+	let read = read_wires(imp);
+	env.ins
+		.iter()
+		.zip(&module.ins)
+		.filter(|(_, inp)| !read.contains(inp.value.as_str()))
+		.map(|(sig, inp)| {
+			let error = Error::UnreadSignal {
+				signal: sig.value.to_string(),
+				span: sig.span.clone(),
+				input: inp.value.to_string(),
+			};
+			(Lint::UnusedSignal, error)
+		})
+		.collect()
+}
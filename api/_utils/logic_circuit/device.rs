@@ -1,5 +1,5 @@
 use super::*;
-use crate::_utils::genetic_circuit::Component;
+use crate::_utils::{data::Data, error::Error, genetic_circuit::Component};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
@@ -9,21 +9,28 @@ pub enum Device {
 }
 
 impl Device {
-	pub fn num_biological(&self) -> usize {
+	pub fn num_biological(&self, data: &Data) -> usize {
 		match self {
-			Self::Gate(gate) => gate.num_biological(),
-			Self::Input(input) => input.num_biological(),
+			Self::Gate(gate) => gate.num_biological(data),
+			Self::Input(input) => input.num_biological(data),
+		}
+	}
+
+	pub fn declare(&self, data: &Data, i: usize, cached: &mut HashMap<String, Component>) {
+		if let Self::Gate(gate) = self {
+			gate.declare(data, i, cached);
 		}
 	}
 
 	pub fn into_biological(
 		&self,
+		data: &Data,
 		i: usize,
 		cached: &mut HashMap<String, Component>,
-	) -> Vec<Component> {
+	) -> Result<Vec<Component>, Error> {
 		match self {
-			Self::Gate(gate) => gate.into_biological(i, cached),
-			Self::Input(input) => input.into_biological(cached),
+			Self::Gate(gate) => gate.into_biological(data, i, cached),
+			Self::Input(input) => input.into_biological(data, cached),
 		}
 	}
 }
@@ -1,8 +1,12 @@
-use crate::_utils::{data, genetic_circuit};
+use crate::_utils::{data, error, genetic_circuit};
 use colors_transform::{Color, Hsl};
-use data::get_data;
+use data::Data;
+use error::Error;
 use genetic_circuit::{Component, Gene};
-use std::collections::HashMap;
+use std::{
+	collections::HashMap,
+	fmt::{Display, Formatter, Result},
+};
 
 pub fn map(num: u32, in_min: u32, in_max: u32, out_min: u32, out_max: u32) -> u32 {
 	(num - in_min) * (out_max - out_min) / (in_max - in_min) + out_min
@@ -14,6 +18,15 @@ pub enum GateKind {
 	Nor,
 }
 
+impl Display for GateKind {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		match self {
+			GateKind::Not => write!(f, "not"),
+			GateKind::Nor => write!(f, "nor"),
+		}
+	}
+}
+
 #[derive(Debug, Clone)]
 pub struct Gate {
 	pub output: String,
@@ -22,22 +35,45 @@ pub struct Gate {
 }
 
 impl Gate {
-	pub fn num_biological(&self) -> usize {
-		let data = get_data();
+	pub fn num_biological(&self, data: &Data) -> usize {
 		data.genes_len()
 	}
 
+	pub fn describe(&self) -> String {
+		format!(
+			"{} = {}({})",
+			self.output,
+			self.kind,
+			self.inputs.join(", ")
+		)
+	}
+
+	pub fn declare(&self, data: &Data, i: usize, cached: &mut HashMap<String, Component>) {
+		let gene = Gene {
+			inputs: Vec::new(),
+			data: data.get_gene_at(i).clone(),
+			color: String::new(),
+			gate: self.describe(),
+		};
+		cached.insert(self.output.to_string(), Component::Gene(gene));
+	}
+
 	pub fn into_biological(
 		&self,
+		data: &Data,
 		i: usize,
 		cached: &mut HashMap<String, Component>,
-	) -> Vec<Component> {
-		let data = get_data();
+	) -> std::result::Result<Vec<Component>, Error> {
 		let gene_data = data.get_gene_at(i).clone();
 
 		let mut inputs = Vec::new();
 		for inp in &self.inputs {
-			let input = cached.get(inp).unwrap().promoter().to_string();
+			let input = cached
+				.get(inp)
+				.ok_or_else(|| Error::Undriven {
+					wire: inp.to_string(),
+				})?
+				.promoter();
 			inputs.push(input);
 		}
 
@@ -49,8 +85,9 @@ impl Gate {
 			inputs,
 			data: gene_data,
 			color: color_hex,
+			gate: self.describe(),
 		};
 		cached.insert(self.output.to_string(), Component::Gene(gene.clone()));
-		vec![Component::Gene(gene)]
+		Ok(vec![Component::Gene(gene)])
 	}
 }
@@ -1,6 +1,7 @@
-use crate::_utils::{data, genetic_circuit};
-use data::get_data;
-use genetic_circuit::{Component, Signal};
+use crate::_utils::{data, error, genetic_circuit};
+use data::Data;
+use error::Error;
+use genetic_circuit::Component;
 use std::collections::hash_map::HashMap;
 
 #[derive(Clone, Debug)]
@@ -10,15 +11,22 @@ pub struct Input {
 }
 
 impl Input {
-	pub fn num_biological(&self) -> usize {
-		let data = get_data();
+	pub fn num_biological(&self, data: &Data) -> usize {
 		data.signals_len()
 	}
 
-	pub fn into_biological(&self, cached: &mut HashMap<String, Component>) -> Vec<Component> {
-		let data = get_data();
-		let signal = data.get_signal(&self.value);
+	pub fn into_biological(
+		&self,
+		data: &Data,
+		cached: &mut HashMap<String, Component>,
+	) -> Result<Vec<Component>, Error> {
+		let signal = data
+			.signals
+			.get(&self.value)
+			.ok_or_else(|| Error::NotASignal {
+				name: self.value.to_string(),
+			})?;
 		cached.insert(self.name.to_string(), Component::Signal(signal.clone()));
-		vec![Component::Signal(signal.clone())]
+		Ok(vec![Component::Signal(signal.clone())])
 	}
 }
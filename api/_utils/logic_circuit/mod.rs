@@ -1,9 +1,10 @@
-use crate::_utils::{assigner, error, genetic_circuit};
+use crate::_utils::{assigner, data, error, genetic_circuit};
 use assigner::GeneNetwork;
+use data::Data;
 use error::Error;
-use genetic_circuit::{Component, GeneticCircuit, Signal};
+use genetic_circuit::{Component, GeneticCircuit, MotifKind, Signal};
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 mod device;
 mod gate;
@@ -17,53 +18,195 @@ pub use output::Output;
 
 #[derive(Serialize, Debug, Clone)]
 pub struct Testbench {
+	pub name: String,
 	pub breakpoints: HashMap<u32, HashMap<String, bool>>,
+	pub duration: u32,
+	// Off and on levels of the inputs, when the test is bound to an env with
+	// other signals than those the circuit is built from.
+	pub levels: HashMap<String, (f64, f64)>,
 }
 
+#[derive(Debug, Clone)]
+pub struct Motif {
+	pub kind: MotifKind,
+	pub output: String,
+	pub input: Option<String>,
+	pub wires: Vec<String>,
+}
+
+impl Motif {
+	pub fn into_biological(
+		&self,
+		cached: &HashMap<String, Component>,
+	) -> Result<genetic_circuit::Motif, Error> {
+		let promoter = |wire: &String| {
+			cached
+				.get(wire)
+				.map(Component::promoter)
+				.ok_or_else(|| Error::Undriven {
+					wire: wire.to_string(),
+				})
+		};
+		Ok(genetic_circuit::Motif {
+			kind: self.kind.clone(),
+			name: self.output.to_string(),
+			input: self.input.as_ref().map(promoter).transpose()?,
+			promoters: self.wires.iter().map(promoter).collect::<Result<_, _>>()?,
+			period: None,
+			amplitude: None,
+			width: None,
+			levels: None,
+		})
+	}
+}
+
+/// The gates of a circuit and the signals wired to them, before any gene is
+/// assigned. [`fit_into_biological`](Self::fit_into_biological) searches the
+/// library for the genes that make it a [`GeneticCircuit`].
 #[derive(Clone)]
 pub struct LogicCircuit {
 	pub inputs: Vec<Input>,
 	pub outputs: Vec<Output>,
 	pub devices: Vec<Device>,
-	pub testbench: Testbench,
+	pub testbenches: Vec<Testbench>,
+	pub motifs: Vec<Motif>,
+	pub library: String,
 }
 
 impl LogicCircuit {
-	pub fn into_biological(&self, selected_genes: &Vec<usize>) -> GeneticCircuit {
+	pub fn into_biological(
+		&self,
+		data: &Data,
+		selected_genes: &[usize],
+	) -> Result<GeneticCircuit, Error> {
 		let mut components = Vec::new();
 		let mut inputs = Vec::new();
 		let mut cached: HashMap<String, Component> = HashMap::new();
 
 		for inp in &self.inputs {
-			let sig = inp.into_biological(&mut cached);
-			let sigs: Vec<Signal> = sig.iter().map(|x| x.signal()).collect();
-			inputs.extend(sigs);
+			let sig = inp.into_biological(data, &mut cached)?;
+			let sigs = sig.iter().map(Component::signal);
+			inputs.extend(sigs.collect::<Result<Vec<Signal>, Error>>()?);
+		}
+
+		// Gates may read wires declared with `reg` before they are assigned, so
+		// every gate's promoter has to be known before any inputs are resolved.
+		for (device, selected) in self.devices.iter().zip(selected_genes.iter().rev()) {
+			device.declare(data, *selected, &mut cached);
 		}
 
-		for (i, selected) in selected_genes.iter().rev().enumerate() {
-			let device = self.devices.get(i).unwrap();
-			let batch = device.into_biological(*selected, &mut cached);
+		for (device, selected) in self.devices.iter().zip(selected_genes.iter().rev()) {
+			let batch = device.into_biological(data, *selected, &mut cached)?;
 			components.extend(batch);
 		}
 
 		let genetic_circuit = GeneticCircuit {
+			library: data.name.to_string(),
 			inputs,
 			outputs: self
 				.outputs
 				.iter()
 				.map(|x| x.into_biological(&cached))
-				.collect(),
+				.collect::<Result<_, _>>()?,
 			components,
+			motifs: self
+				.motifs
+				.iter()
+				.map(|motif| motif.into_biological(&cached))
+				.collect::<Result<_, _>>()?,
 			score: None,
-			simulation: None,
+			simulations: HashMap::new(),
+			timing: None,
+			stability: None,
 		};
-		genetic_circuit
+		Ok(genetic_circuit)
+	}
+
+	// The logic value of the first output with `signals` driving the inputs,
+	// or `None` where it depends on a register or a motif. Signals left out
+	// are off.
+	pub fn evaluate(&self, signals: &HashMap<String, bool>) -> Option<bool> {
+		let mut wires: HashMap<&str, Option<bool>> = HashMap::new();
+		for inp in &self.inputs {
+			let value = signals.get(&inp.value).cloned().unwrap_or(false);
+			wires.insert(&inp.name, Some(value));
+		}
+		let motif_wires: HashSet<&str> = self
+			.motifs
+			.iter()
+			.flat_map(|motif| motif.wires.iter().chain(Some(&motif.output)))
+			.map(String::as_str)
+			.collect();
+		for device in &self.devices {
+			let gate = match device {
+				Device::Gate(gate) => gate,
+				Device::Input(_) => continue,
+			};
+			let arg = |i: usize| {
+				let wire = gate.inputs.get(i)?;
+				wires.get(wire.as_str()).cloned().flatten()
+			};
+			let value = match gate.kind {
+				GateKind::Not => arg(0).map(|a| !a),
+				GateKind::Nor => match (arg(0), arg(1)) {
+					(Some(true), _) | (_, Some(true)) => Some(false),
+					(Some(false), Some(false)) => Some(true),
+					_ => None,
+				},
+			};
+			let value = value.filter(|_| !motif_wires.contains(gate.output.as_str()));
+			wires.insert(&gate.output, value);
+		}
+		let output = self.outputs.first()?;
+		wires.get(output.name.as_str()).cloned().flatten()
+	}
+
+	// The assignment that puts the gene named in `genes` on every gate, keyed
+	// by the gate as `Gene::gate` describes it.
+	pub fn select_genes(
+		&self,
+		data: &Data,
+		genes: &HashMap<String, String>,
+	) -> Result<Vec<usize>, Error> {
+		let mut selected = Vec::new();
+		for device in &self.devices {
+			let gate = match device {
+				Device::Gate(gate) => gate,
+				Device::Input(_) => {
+					selected.push(0);
+					continue;
+				}
+			};
+			let name = genes
+				.get(&gate.describe())
+				.ok_or_else(|| Error::Undefined {
+					kind: "gene for gate".to_string(),
+					name: gate.describe(),
+				})?;
+			let i = data
+				.genes
+				.iter()
+				.position(|gene| gene.name == *name)
+				.ok_or_else(|| Error::Undefined {
+					kind: "gene".to_string(),
+					name: name.to_string(),
+				})?;
+			selected.push(i);
+		}
+		// Devices take their genes from the back.
+		selected.reverse();
+		Ok(selected)
 	}
 
-	pub fn fit_into_biological(&self) -> Result<GeneticCircuit, Error> {
-		let mut assn = GeneNetwork::init(self.clone(), 6000)?;
+	pub fn fit_into_biological(
+		&self,
+		data: &Data,
+		iterations: usize,
+		seed: Option<u64>,
+	) -> Result<GeneticCircuit, Error> {
+		let mut assn = GeneNetwork::init(self.clone(), data, iterations, seed)?;
 		let selected_genes = assn.fit()?;
-		let mut gc = self.into_biological(&selected_genes);
+		let mut gc = self.into_biological(data, &selected_genes)?;
 		gc.test();
 		Ok(gc)
 	}
@@ -1,4 +1,5 @@
-use crate::_utils::genetic_circuit;
+use crate::_utils::{error, genetic_circuit};
+use error::Error;
 use genetic_circuit::{Actuator, Component};
 use std::collections::HashMap;
 
@@ -9,11 +10,16 @@ pub struct Output {
 }
 
 impl Output {
-	pub fn into_biological(&self, cached: &HashMap<String, Component>) -> Actuator {
-		let input = cached.get(&self.name).unwrap().promoter().to_string();
-		Actuator {
+	pub fn into_biological(&self, cached: &HashMap<String, Component>) -> Result<Actuator, Error> {
+		let input = cached
+			.get(&self.name)
+			.ok_or_else(|| Error::Undriven {
+				wire: self.name.to_string(),
+			})?
+			.promoter();
+		Ok(Actuator {
 			name: self.value.to_string(),
 			input,
-		}
+		})
 	}
 }
@@ -1,9 +1,22 @@
 pub mod assigner;
 pub mod builder;
+pub mod catalog;
 pub mod data;
+pub mod diagnostic;
 pub mod dna;
 pub mod error;
+pub mod explain;
+pub mod fit;
 pub mod genetic_circuit;
+pub mod http;
 pub mod lexer;
+pub mod lint;
 pub mod logic_circuit;
 pub mod parser;
+pub mod pipeline;
+pub mod routes;
+pub mod sbol;
+pub mod server;
+pub mod span;
+pub mod ucf;
+pub mod validate;
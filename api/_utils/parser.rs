@@ -1,7 +1,11 @@
-use crate::_utils::{error, lexer};
+use crate::_utils::{error, lexer, lint, span};
 use error::Error;
 use lexer::{LexerIter, Token, TokenKind};
-use std::iter::Peekable;
+use lint::Lint;
+use span::Span;
+use std::{collections::VecDeque, iter::Peekable};
+
+const DEF_KEYWORDS: [&str; 6] = ["mod", "env", "impl", "test", "part", "gene"];
 
 #[derive(Debug)]
 pub struct LogicOp {
@@ -9,11 +13,19 @@ pub struct LogicOp {
 	pub pos: usize,
 	pub symbol: Token,
 	pub args: Vec<Token>,
+	pub allow: Vec<Token>,
+}
+
+#[derive(Debug)]
+pub struct Register {
+	pub symbol: Token,
+	pub var: Token,
 }
 
 #[derive(Debug)]
 pub enum Operation {
 	Logic(LogicOp),
+	Register(Register),
 }
 
 #[derive(Debug)]
@@ -29,31 +41,72 @@ pub struct Breakpoint {
 	pub assignments: Vec<TestbenchAssignment>,
 }
 
+#[derive(Debug)]
+pub enum Ordering {
+	Binary,
+	Gray,
+}
+
+#[derive(Debug)]
+pub struct Exhaustive {
+	pub symbol: Token,
+	// Steps each combination is held for, left to the builder when unset.
+	pub hold: Option<u32>,
+	pub ordering: Ordering,
+}
+
 #[derive(Debug)]
 pub struct Module {
 	pub name: Token,
 	pub ins: Vec<Token>,
 	pub outs: Vec<Token>,
+	pub allow: Vec<Token>,
 }
 
 #[derive(Debug)]
 pub struct Enviroment {
 	pub name: Token,
+	pub library: Option<Token>,
 	pub ins: Vec<Token>,
 	pub outs: Vec<Token>,
+	pub allow: Vec<Token>,
 }
 
 #[derive(Debug)]
 pub struct Implementation {
 	pub name: Token,
 	pub body: Vec<Operation>,
+	pub allow: Vec<Token>,
 }
 
 #[derive(Debug)]
 pub struct Test {
 	pub module: Token,
 	pub name: Token,
+	pub env: Token,
 	pub body: Vec<Breakpoint>,
+	pub exhaustive: Option<Exhaustive>,
+}
+
+#[derive(Debug)]
+pub struct PartDef {
+	pub name: Token,
+	pub kind: Token,
+	pub seq: Token,
+}
+
+#[derive(Debug)]
+pub struct Param {
+	pub name: Token,
+	pub value: f64,
+}
+
+#[derive(Debug)]
+pub struct GeneDef {
+	pub name: Token,
+	pub promoter: Option<Token>,
+	pub parts: Option<Vec<Token>>,
+	pub params: Vec<Param>,
 }
 
 #[derive(Debug)]
@@ -62,49 +115,122 @@ pub enum Def {
 	Enviroment(Enviroment),
 	Implementation(Implementation),
 	Test(Test),
+	Part(PartDef),
+	Gene(GeneDef),
 }
 
+/// Reads the tokens of a [`LexerIter`] into definitions, one `mod`, `env`,
+/// `impl`, `test`, `part` or `gene` at a time.
+///
+/// Errors don't end parsing. A broken statement is skipped up to its `;` and
+/// a broken definition up to its `}`, so every error of a source is reported
+/// at once.
 pub struct ParserIter<'a> {
 	tokens: Peekable<LexerIter<'a>>,
+	eof: Span,
+	pending: VecDeque<Result<Def, Error>>,
+}
+
+fn is_sign(token: &Token, values: &[&str]) -> bool {
+	token.kind == TokenKind::Sign && values.contains(&token.value.as_str())
+}
+
+// `part` and `gene` are names everywhere but at the start of a definition.
+fn is_def_start(token: &Token) -> bool {
+	let kinds = [TokenKind::Keyword, TokenKind::Name];
+	kinds.contains(&token.kind) && DEF_KEYWORDS.contains(&token.value.as_str())
+}
+
+// A contextual keyword, lexed as a name.
+fn is_name(token: &Token, values: &[&str]) -> bool {
+	token.kind == TokenKind::Name && values.contains(&token.value.as_str())
 }
 
 impl<'a> ParserIter<'a> {
 	pub fn new(tokens: LexerIter<'a>) -> Self {
 		Self {
+			eof: Span::end_of(tokens.source(), tokens.file()),
 			tokens: tokens.peekable(),
+			pending: VecDeque::new(),
 		}
 	}
 
+	// Unexpected tokens are left in place to recover from.
 	fn get_token(&mut self, kind: TokenKind, value_pre: Option<&[&str]>) -> Result<Token, Error> {
-		let value: Option<Vec<String>> =
-			value_pre.map(|x| x.iter().map(|a| a.to_string()).collect());
-		let token = self.tokens.next().ok_or(Error::EndOfFile)?;
-		let res = match (token.kind == kind, value) {
-			(true, None) => Ok(token),
-			(true, Some(value)) => {
-				if value.contains(&token.value) {
-					Ok(token)
-				} else {
-					Err(Error::UnexpectedToken(
-						token.value.to_string(),
-						token.pos,
-						token.value.len(),
-					))
-				}
-			}
-			(false, _) => Err(Error::UnexpectedToken(
-				token.value.to_string(),
-				token.pos,
-				token.value.len(),
-			)),
+		let eof = &self.eof;
+		let token = self
+			.tokens
+			.peek()
+			.ok_or_else(|| Error::EndOfFile { span: eof.clone() })?;
+		let expected = match value_pre {
+			Some(values) => values.contains(&token.value.as_str()),
+			None => true,
 		};
-		res
+		if token.kind != kind || !expected {
+			return Err(Error::UnexpectedToken {
+				token: token.value.to_string(),
+				span: token.span.clone(),
+			});
+		}
+		Ok(self.tokens.next().unwrap())
+	}
+
+	// Skips past the `;` of a broken statement, stopping early where the
+	// next statement, the block or the next definition starts.
+	fn skip_statement(&mut self, error: Error) {
+		self.pending.push_back(Err(error));
+		while let Some(token) = self.tokens.peek() {
+			if is_sign(token, &["}", "@", "#"]) || is_def_start(token) {
+				return;
+			}
+			if (token.kind == TokenKind::Keyword && token.value == "let")
+				|| is_name(token, &["reg"])
+			{
+				return;
+			}
+			let token = self.tokens.next().unwrap();
+			if is_sign(&token, &[";"]) {
+				return;
+			}
+		}
+	}
+
+	// Skips past the `}` of a broken definition, or up to the next one.
+	fn skip_def(&mut self) {
+		while let Some(token) = self.tokens.peek() {
+			if is_def_start(token) {
+				return;
+			}
+			let token = self.tokens.next().unwrap();
+			if is_sign(&token, &["}"]) {
+				return;
+			}
+		}
+	}
+
+	// Lints silenced with `#[allow(<lint>, ...)]`, any number of them.
+	fn parse_allow(&mut self) -> Result<Vec<Token>, Error> {
+		let mut allow = Vec::new();
+		while let Some(token) = self.tokens.peek() {
+			if !is_sign(token, &["#"]) {
+				break;
+			}
+			let _ = self.get_token(TokenKind::Sign, Some(&["#"]))?;
+			let _ = self.get_token(TokenKind::Sign, Some(&["["]))?;
+			let _ = self.get_token(TokenKind::Name, Some(&["allow"]))?;
+			for lint in self.parse_args()? {
+				Error::not_found(Lint::from_name(&lint.value).is_none(), &lint)?;
+				allow.push(lint);
+			}
+			let _ = self.get_token(TokenKind::Sign, Some(&["]"]))?;
+		}
+		Ok(allow)
 	}
 
 	fn parse_args(&mut self) -> Result<Vec<Token>, Error> {
 		let _ = self.get_token(TokenKind::Sign, Some(&["("]))?;
 		let mut args = Vec::new();
-		while let Some(_) = self.tokens.peek() {
+		while self.tokens.peek().is_some() {
 			let token = self.get_token(TokenKind::Name, None)?;
 
 			args.push(token);
@@ -118,32 +244,58 @@ impl<'a> ParserIter<'a> {
 	}
 
 	fn parse_operation(&mut self) -> Result<Operation, Error> {
+		let allow = self.parse_allow()?;
 		let _ = self.get_token(TokenKind::Keyword, Some(&["let"]))?;
 		let token = self.get_token(TokenKind::Name, None)?;
 		let _ = self.get_token(TokenKind::Sign, Some(&["="]))?;
-		let token1 = self.get_token(TokenKind::Operation, Some(&["not", "nor"]))?;
-		let args = self.parse_args()?;
+		let token1 = match self.tokens.peek() {
+			Some(token) if is_name(token, &["osc", "pulse"]) => {
+				self.get_token(TokenKind::Name, None)?
+			}
+			_ => self.get_token(TokenKind::Operation, Some(&["not", "nor"]))?,
+		};
+		let args = if token1.value == "osc" {
+			let _ = self.get_token(TokenKind::Sign, Some(&["("]))?;
+			let stages = self.get_token(TokenKind::Value, None)?;
+			let _ = self.get_token(TokenKind::Sign, Some(&[")"]))?;
+			vec![stages]
+		} else {
+			self.parse_args()?
+		};
 		let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
 
 		let op = Operation::Logic(LogicOp {
 			var: token,
 			symbol: token1.clone(),
-			pos: token1.pos,
+			pos: token1.span.start,
 			args,
+			allow,
 		});
 
 		Ok(op)
 	}
 
+	fn parse_register(&mut self) -> Result<Operation, Error> {
+		let symbol = self.get_token(TokenKind::Name, Some(&["reg"]))?;
+		let var = self.get_token(TokenKind::Name, None)?;
+		let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
+
+		Ok(Operation::Register(Register { symbol, var }))
+	}
+
 	fn parse_operations(&mut self) -> Result<Vec<Operation>, Error> {
 		let mut ops = Vec::new();
 		while let Some(token) = self.tokens.peek() {
 			let exp = match (token.kind, token.value.as_str()) {
-				(TokenKind::Keyword, "let") => self.parse_operation()?,
+				(TokenKind::Keyword, "let") | (TokenKind::Sign, "#") => self.parse_operation(),
+				(TokenKind::Name, "reg") => self.parse_register(),
 				_ => break,
 			};
 
-			ops.push(exp);
+			match exp {
+				Ok(exp) => ops.push(exp),
+				Err(e) => self.skip_statement(e),
+			}
 		}
 
 		let _ = self.get_token(TokenKind::Sign, Some(&["}"]))?;
@@ -157,24 +309,27 @@ impl<'a> ParserIter<'a> {
 
 		let ops = self.parse_operations()?;
 
-		Ok(Def::Implementation(Implementation { name, body: ops }))
+		Ok(Def::Implementation(Implementation {
+			name,
+			body: ops,
+			allow: Vec::new(),
+		}))
 	}
 
 	fn parse_assignment(&mut self) -> Result<TestbenchAssignment, Error> {
 		let token = self.get_token(TokenKind::Name, None)?;
 		let _ = self.get_token(TokenKind::Sign, Some(&["="]))?;
 		let bool_token = self.get_token(TokenKind::Value, None)?;
-		let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
 
 		let bool_value = bool_token.value.parse::<bool>();
 		let bool_value = match bool_value {
 			Ok(value) => value,
-			Err(_) => Err(Error::UnexpectedToken(
-				bool_token.value.to_string(),
-				bool_token.pos,
-				bool_token.value.len(),
-			))?,
+			Err(_) => Err(Error::UnexpectedToken {
+				token: bool_token.value.to_string(),
+				span: bool_token.span.clone(),
+			})?,
 		};
+		let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
 
 		Ok(TestbenchAssignment {
 			iden: token,
@@ -185,28 +340,26 @@ impl<'a> ParserIter<'a> {
 	fn parse_breakpoint(&mut self) -> Result<Breakpoint, Error> {
 		let token = self.get_token(TokenKind::Sign, Some(&["@"]))?;
 		let time_token = self.get_token(TokenKind::Value, None)?;
+		let parsed_time = time_token.value.parse::<u32>();
+		let parsed_time = match parsed_time {
+			Ok(val) => val,
+			Err(_) => Err(Error::UnexpectedToken {
+				token: time_token.value.to_string(),
+				span: time_token.span.clone(),
+			})?,
+		};
 
 		let mut assignments = Vec::new();
 		while let Some(token) = self.tokens.peek() {
 			match token.kind {
-				TokenKind::Name => {
-					let ass = self.parse_assignment()?;
-					assignments.push(ass);
-				}
+				TokenKind::Name => match self.parse_assignment() {
+					Ok(ass) => assignments.push(ass),
+					Err(e) => self.skip_statement(e),
+				},
 				_ => break,
 			}
 		}
 
-		let parsed_time = time_token.value.parse::<u32>();
-		let parsed_time = match parsed_time {
-			Ok(val) => val,
-			Err(_) => Err(Error::UnexpectedToken(
-				time_token.value.to_string(),
-				time_token.pos,
-				time_token.value.len(),
-			))?,
-		};
-
 		Ok(Breakpoint {
 			symbol: token,
 			time: parsed_time,
@@ -229,11 +382,70 @@ impl<'a> ParserIter<'a> {
 		Ok(breakpoints)
 	}
 
+	fn parse_exhaustive(&mut self) -> Result<Exhaustive, Error> {
+		let symbol = self.get_token(TokenKind::Name, Some(&["auto"]))?;
+		let mut exhaustive = Exhaustive {
+			symbol,
+			hold: None,
+			ordering: Ordering::Binary,
+		};
+
+		if let Some(Token { value, .. }) = self.tokens.peek() {
+			if value != "(" {
+				return Ok(exhaustive);
+			}
+		}
+
+		let _ = self.get_token(TokenKind::Sign, Some(&["("]))?;
+		let hold_token = self.get_token(TokenKind::Value, None)?;
+		exhaustive.hold = match hold_token.value.parse::<u32>() {
+			Ok(val) if val > 0 => Some(val),
+			_ => Err(Error::UnexpectedToken {
+				token: hold_token.value.to_string(),
+				span: hold_token.span.clone(),
+			})?,
+		};
+
+		let token = self.get_token(TokenKind::Sign, Some(&[",", ")"]))?;
+		if token.value == "," {
+			let order_token = self.get_token(TokenKind::Name, Some(&["binary", "gray"]))?;
+			if order_token.value == "gray" {
+				exhaustive.ordering = Ordering::Gray;
+			}
+			let _ = self.get_token(TokenKind::Sign, Some(&[")"]))?;
+		}
+
+		Ok(exhaustive)
+	}
+
 	fn parse_test(&mut self) -> Result<Def, Error> {
 		let _ = self.get_token(TokenKind::Keyword, Some(&["test"]))?;
 		let name = self.get_token(TokenKind::Name, None)?;
 		let _ = self.get_token(TokenKind::Keyword, Some(&["for"]))?;
 		let module = self.get_token(TokenKind::Name, None)?;
+
+		let mut env = name.clone();
+		if let Some(Token { value, .. }) = self.tokens.peek() {
+			if value == "in" {
+				let _ = self.get_token(TokenKind::Keyword, Some(&["in"]))?;
+				env = self.get_token(TokenKind::Name, None)?;
+			}
+		}
+
+		if let Some(Token { value, .. }) = self.tokens.peek() {
+			if value == "auto" {
+				let exhaustive = self.parse_exhaustive()?;
+				let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
+				return Ok(Def::Test(Test {
+					module,
+					name,
+					env,
+					body: Vec::new(),
+					exhaustive: Some(exhaustive),
+				}));
+			}
+		}
+
 		let _ = self.get_token(TokenKind::Sign, Some(&["{"]))?;
 		let breakpoints = self.parse_breakpoints()?;
 		let _ = self.get_token(TokenKind::Sign, Some(&["}"]))?;
@@ -241,7 +453,9 @@ impl<'a> ParserIter<'a> {
 		Ok(Def::Test(Test {
 			module,
 			name,
+			env,
 			body: breakpoints,
+			exhaustive: None,
 		}))
 	}
 
@@ -258,12 +472,26 @@ impl<'a> ParserIter<'a> {
 
 		let _ = self.get_token(TokenKind::Sign, Some(&["}"]))?;
 
-		Ok(Def::Module(Module { name, ins, outs }))
+		Ok(Def::Module(Module {
+			name,
+			ins,
+			outs,
+			allow: Vec::new(),
+		}))
 	}
 
 	fn parse_env(&mut self) -> Result<Def, Error> {
 		let _ = self.get_token(TokenKind::Keyword, Some(&["env"]))?;
 		let name = self.get_token(TokenKind::Name, None)?;
+
+		let mut library = None;
+		if let Some(Token { value, .. }) = self.tokens.peek() {
+			if value == "for" {
+				let _ = self.get_token(TokenKind::Keyword, Some(&["for"]))?;
+				library = Some(self.get_token(TokenKind::Literal, None)?);
+			}
+		}
+
 		let _ = self.get_token(TokenKind::Sign, Some(&["{"]))?;
 		let _ = self.get_token(TokenKind::Keyword, Some(&["in"]))?;
 		let ins = self.parse_args()?;
@@ -274,27 +502,129 @@ impl<'a> ParserIter<'a> {
 
 		let _ = self.get_token(TokenKind::Sign, Some(&["}"]))?;
 
-		Ok(Def::Enviroment(Enviroment { name, ins, outs }))
+		Ok(Def::Enviroment(Enviroment {
+			name,
+			library,
+			ins,
+			outs,
+			allow: Vec::new(),
+		}))
+	}
+
+	fn parse_part(&mut self) -> Result<Def, Error> {
+		let _ = self.get_token(TokenKind::Name, Some(&["part"]))?;
+		let name = self.get_token(TokenKind::Name, None)?;
+		let kind = self.get_token(TokenKind::Name, None)?;
+		let seq = self.get_token(TokenKind::Literal, None)?;
+		let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
+
+		Ok(Def::Part(PartDef { name, kind, seq }))
+	}
+
+	fn parse_param(&mut self) -> Result<Param, Error> {
+		let name = self.get_token(TokenKind::Name, Some(&["ymax", "ymin", "K", "n", "decay"]))?;
+		let _ = self.get_token(TokenKind::Sign, Some(&["="]))?;
+		let value_token = self.get_token(TokenKind::Value, None)?;
+
+		let value = match value_token.value.parse::<f64>() {
+			Ok(value) => value,
+			Err(_) => Err(Error::UnexpectedToken {
+				token: value_token.value.to_string(),
+				span: value_token.span.clone(),
+			})?,
+		};
+		let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
+
+		Ok(Param { name, value })
+	}
+
+	fn parse_gene_promoter(&mut self, gene: &mut GeneDef) -> Result<(), Error> {
+		let _ = self.get_token(TokenKind::Name, Some(&["promoter"]))?;
+		gene.promoter = Some(self.get_token(TokenKind::Name, None)?);
+		let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
+		Ok(())
+	}
+
+	fn parse_gene_parts(&mut self, gene: &mut GeneDef) -> Result<(), Error> {
+		let _ = self.get_token(TokenKind::Name, Some(&["parts"]))?;
+		gene.parts = Some(self.parse_args()?);
+		let _ = self.get_token(TokenKind::Sign, Some(&[";"]))?;
+		Ok(())
+	}
+
+	fn parse_gene(&mut self) -> Result<Def, Error> {
+		let _ = self.get_token(TokenKind::Name, Some(&["gene"]))?;
+		let name = self.get_token(TokenKind::Name, None)?;
+		let _ = self.get_token(TokenKind::Sign, Some(&["{"]))?;
+
+		let mut gene = GeneDef {
+			name,
+			promoter: None,
+			parts: None,
+			params: Vec::new(),
+		};
+		while let Some(token) = self.tokens.peek() {
+			let res = match (token.kind, token.value.as_str()) {
+				(TokenKind::Name, "promoter") => self.parse_gene_promoter(&mut gene),
+				(TokenKind::Name, "parts") => self.parse_gene_parts(&mut gene),
+				// Skipping a broken param stops at these, so they end the body.
+				(TokenKind::Name, _) if is_def_start(token) || is_name(token, &["reg"]) => break,
+				(TokenKind::Name, _) => self.parse_param().map(|param| gene.params.push(param)),
+				_ => break,
+			};
+			if let Err(e) = res {
+				self.skip_statement(e);
+			}
+		}
+		let _ = self.get_token(TokenKind::Sign, Some(&["}"]))?;
+
+		Ok(Def::Gene(gene))
+	}
+
+	fn parse_def(&mut self) -> Result<Def, Error> {
+		let eof = &self.eof;
+		let token = self
+			.tokens
+			.peek()
+			.ok_or_else(|| Error::EndOfFile { span: eof.clone() })?;
+		match (token.kind, token.value.as_str()) {
+			(TokenKind::Keyword, "mod") => self.parse_mod(),
+			(TokenKind::Keyword, "env") => self.parse_env(),
+			(TokenKind::Keyword, "impl") => self.parse_impl(),
+			(TokenKind::Keyword, "test") => self.parse_test(),
+			(TokenKind::Name, "part") => self.parse_part(),
+			(TokenKind::Name, "gene") => self.parse_gene(),
+			_ => Err(Error::UnexpectedToken {
+				token: token.value.to_string(),
+				span: token.span.clone(),
+			}),
+		}
 	}
 }
 
 impl<'a> Iterator for ParserIter<'a> {
 	type Item = Result<Def, Error>;
 
+	// Errors of skipped statements come before their definition.
 	fn next(&mut self) -> Option<Result<Def, Error>> {
-		while let Some(token) = self.tokens.peek() {
-			return match (token.kind, token.value.as_str()) {
-				(TokenKind::Keyword, "mod") => Some(self.parse_mod()),
-				(TokenKind::Keyword, "env") => Some(self.parse_env()),
-				(TokenKind::Keyword, "impl") => Some(self.parse_impl()),
-				(TokenKind::Keyword, "test") => Some(self.parse_test()),
-				_ => Some(Err(Error::UnexpectedToken(
-					token.value.to_string(),
-					token.pos,
-					token.value.len(),
-				))),
-			};
+		if let Some(res) = self.pending.pop_front() {
+			return Some(res);
+		}
+		self.tokens.peek()?;
+		let res = self.parse_allow().and_then(|allow| {
+			let mut def = self.parse_def()?;
+			match &mut def {
+				Def::Module(module) => module.allow = allow,
+				Def::Enviroment(env) => env.allow = allow,
+				Def::Implementation(imp) => imp.allow = allow,
+				_ => {}
+			}
+			Ok(def)
+		});
+		if res.is_err() {
+			self.skip_def();
 		}
-		None
+		self.pending.push_back(res);
+		self.pending.pop_front()
 	}
 }
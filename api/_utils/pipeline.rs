@@ -0,0 +1,207 @@
+use crate::_utils::{
+	assigner, builder, data, diagnostic, dna, error, genetic_circuit, lexer, parser, sbol,
+};
+use assigner::{GeneNetwork, DEFAULT_ITERATIONS};
+use builder::LogicCircuitBuilder;
+use data::{get_libraries, Data, DEFAULT_LIBRARY};
+use diagnostic::Diagnostic;
+use dna::Dna;
+use error::Error;
+use genetic_circuit::{Component, Failure, GeneticCircuit};
+use lexer::LexerIter;
+use parser::ParserIter;
+use sbol::Sbol;
+use serde::{Deserialize, Serialize};
+
+const MAX_ITERATIONS: usize = 100_000;
+const MAX_SIM_TIME: u32 = 100_000;
+const MAX_TOP_N: usize = 10;
+
+/// An output of [`compile`] besides the genetic circuit itself.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+	/// The plasmids as raw sequences and GenBank files, [`Dna`].
+	Dna,
+	/// The circuit as an SBOL3 document, [`Sbol`].
+	Sbol,
+}
+
+/// Settings of a compile. [`Options::default`] compiles like the web API.
+///
+/// Options read from JSON, like the body of `/api/compile`, can leave any of
+/// them out, and unknown ones are rejected.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+	/// Seed of the gene assignment. The same seed and source give the same
+	/// circuit, without one every compile is seeded from the OS.
+	pub seed: Option<u64>,
+	/// Assignments tried before the best one is kept.
+	pub iterations: usize,
+	/// Library used when the main env doesn't name one with `for`.
+	pub library: String,
+	/// Steps every test is simulated for, instead of 1000 for tests with
+	/// breakpoints and every input combination for exhaustive ones.
+	pub sim_time: Option<u32>,
+	/// Outputs to build besides the genetic circuit.
+	pub formats: Vec<Format>,
+	/// Distinct gene assignments listed in [`CompileResult::assignments`],
+	/// the circuit being built from the best.
+	pub top_n: usize,
+	/// Name of the source file, shown in the spans of diagnostics.
+	#[serde(skip)]
+	pub file: Option<String>,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			seed: None,
+			iterations: DEFAULT_ITERATIONS,
+			library: DEFAULT_LIBRARY.to_string(),
+			sim_time: None,
+			formats: vec![Format::Dna, Format::Sbol],
+			top_n: 1,
+			file: None,
+		}
+	}
+}
+
+impl Options {
+	/// Checks that the numbers are in the ranges a compile can handle.
+	pub fn validate(&self) -> Result<(), Error> {
+		let out_of_range = |option: &str, max: usize| Error::InvalidOption {
+			option: option.to_string(),
+			reason: format!("expected a number from 1 to {}", max),
+		};
+		if !(1..=MAX_ITERATIONS).contains(&self.iterations) {
+			return Err(out_of_range("iterations", MAX_ITERATIONS));
+		}
+		if let Some(sim_time) = self.sim_time {
+			if !(1..=MAX_SIM_TIME).contains(&sim_time) {
+				return Err(out_of_range("sim_time", MAX_SIM_TIME as usize));
+			}
+		}
+		if !(1..=MAX_TOP_N).contains(&self.top_n) {
+			return Err(out_of_range("top_n", MAX_TOP_N));
+		}
+		Ok(())
+	}
+}
+
+/// A gene assignment tried while fitting, by the names of its genes.
+#[derive(Serialize, Debug)]
+pub struct Assignment {
+	pub score: f64,
+	pub genes: Vec<String>,
+}
+
+/// A compiled circuit, with the warnings found on the way.
+#[derive(Serialize, Debug)]
+pub struct CompileResult {
+	/// The circuit, with its simulations, timing and stability analyses.
+	pub gc: GeneticCircuit,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub dna: Option<Dna>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sbol: Option<Sbol>,
+	/// The best [`Options::top_n`] assignments, the first being the one
+	/// `gc` is built from.
+	pub assignments: Vec<Assignment>,
+	/// Breakpoints of tests where the simulated output disagrees with the
+	/// logic of the circuit.
+	pub failures: Vec<Failure>,
+	pub warnings: Vec<Diagnostic>,
+}
+
+/// Compiles `source` against the loaded libraries, `options.library` unless
+/// the main env names another.
+///
+/// Errors in the source come back with every diagnostic found, warnings
+/// included. Later stages stop at their first error. Diagnostics with a span
+/// are rendered with the offending line of `source`.
+pub fn compile(source: &str, options: &Options) -> Result<CompileResult, Vec<Diagnostic>> {
+	let library = get_libraries().require(&options.library)?;
+	compile_with(source, library, options)
+}
+
+/// Like [`compile`], with `library` in place of `options.library`, for
+/// libraries that aren't loaded from `static/`.
+pub fn compile_with(
+	source: &str,
+	library: &Data,
+	options: &Options,
+) -> Result<CompileResult, Vec<Diagnostic>> {
+	options.validate()?;
+	let lx = match &options.file {
+		Some(file) => LexerIter::with_file(source.chars(), file),
+		None => LexerIter::new(source.chars()),
+	};
+	let mut bld = LogicCircuitBuilder::new(ParserIter::new(lx), get_libraries(), library);
+	let rendered = |diagnostics: Vec<Diagnostic>| {
+		diagnostics
+			.into_iter()
+			.map(|diag| diag.with_rendered(source))
+			.collect()
+	};
+	let mut result = build(&mut bld, options).map_err(rendered)?;
+	result.warnings = rendered(result.warnings);
+	Ok(result)
+}
+
+fn build(
+	bld: &mut LogicCircuitBuilder,
+	options: &Options,
+) -> Result<CompileResult, Vec<Diagnostic>> {
+	bld.build_parse_tree()?;
+	let warnings = bld.take_diagnostics();
+	let data = bld.build_library()?;
+	let data = data.as_ref();
+	let mut lc = bld.build_logic_circut(data)?;
+	if let Some(sim_time) = options.sim_time {
+		for testbench in &mut lc.testbenches {
+			testbench.duration = sim_time;
+		}
+	}
+
+	let mut assn = GeneNetwork::init(lc.clone(), data, options.iterations, options.seed)?;
+	let top = assn.fit_top(options.top_n)?;
+	let mut assignments = Vec::new();
+	for (score, selected) in &top {
+		let genes = lc
+			.into_biological(data, selected)?
+			.components
+			.iter()
+			.filter_map(|comp| match comp {
+				Component::Gene(gene) => Some(gene.name()),
+				_ => None,
+			})
+			.collect();
+		assignments.push(Assignment {
+			score: *score,
+			genes,
+		});
+	}
+	let best = top.first().map(|(_, selected)| selected.as_slice());
+	let mut gc = lc.into_biological(data, best.unwrap_or_default())?;
+	gc.test();
+
+	let mut failures = Vec::new();
+	for testbench in &lc.testbenches {
+		gc.simulate(testbench);
+		failures.extend(gc.check(&lc, testbench));
+	}
+	gc.analyze_timing();
+	gc.analyze_stability();
+	gc.apply_rules(data);
+	let wants = |format| options.formats.contains(&format);
+	Ok(CompileResult {
+		dna: wants(Format::Dna).then(|| gc.into_dna(data)),
+		sbol: wants(Format::Sbol).then(|| gc.into_sbol(data)),
+		gc,
+		assignments,
+		failures,
+		warnings,
+	})
+}
@@ -0,0 +1,172 @@
+use crate::_utils::{catalog, data, diagnostic, error, explain, http, pipeline};
+use catalog::Filter;
+use data::{get_libraries, DEFAULT_LIBRARY};
+use diagnostic::Diagnostic;
+use error::Error;
+use explain::EXPLANATIONS;
+use http::{Method, Request, Response};
+use pipeline::Options;
+use serde_json::{json, to_string, Map, Value};
+
+// The handlers behind the Lambdas in `api/` and the self-hosted server, so
+// both answer a request the same way.
+
+// The source and options of `{ "source": ..., "options": { ... } }`. Options
+// left out keep their defaults, the library the one of `?library=`.
+fn read_envelope(
+	mut envelope: Map<String, Value>,
+	library: String,
+) -> Result<(String, Options), Error> {
+	let invalid = |option: &str, reason: String| Error::InvalidOption {
+		option: option.to_string(),
+		reason,
+	};
+	if let Some(key) = envelope
+		.keys()
+		.find(|key| *key != "source" && *key != "options")
+	{
+		let reason = "unknown field, expected `source` or `options`".to_string();
+		return Err(invalid(key, reason));
+	}
+	let source = match envelope.remove("source") {
+		Some(Value::String(source)) => source,
+		Some(_) => return Err(invalid("source", "expected a string".to_string())),
+		None => return Err(invalid("source", "missing".to_string())),
+	};
+	let fields = match envelope.remove("options") {
+		Some(Value::Object(fields)) => fields,
+		Some(_) => return Err(invalid("options", "expected an object".to_string())),
+		None => Map::new(),
+	};
+
+	let mut options: Options =
+		serde_json::from_value(Value::Object(fields.clone())).map_err(|e| {
+			// Serde doesn't say which option failed, so they're read one at a
+			// time to find it.
+			let option = fields.iter().find(|(key, value)| {
+				let mut one = Map::new();
+				one.insert(key.to_string(), (*value).clone());
+				serde_json::from_value::<Options>(Value::Object(one)).is_err()
+			});
+			let option = option.map_or("options", |(key, _)| key.as_str());
+			invalid(option, e.to_string())
+		})?;
+	if !fields.contains_key("library") {
+		options.library = library;
+	}
+	Ok((source, options))
+}
+
+// `POST /api/compile` with the source as the body, or a JSON envelope with
+// the source and its options.
+pub fn compile(req: &Request) -> Response {
+	if req.method == Method::OPTIONS {
+		return Response::preflight("POST, OPTIONS, GET");
+	}
+
+	// The library can be picked per request with `?library=<name>`, an env
+	// naming its own library with `for "<name>"` takes precedence.
+	let library = req
+		.query("library")
+		.unwrap_or_else(|| DEFAULT_LIBRARY.to_string());
+	// Sources never start with `{`, so a body that is a JSON object is an
+	// envelope. Bodies sent as JSON have to be one.
+	let is_json = req
+		.headers
+		.get("content-type")
+		.is_some_and(|kind| kind.starts_with("application/json"));
+	let res = req
+		.decoded_body()
+		.and_then(|body| match serde_json::from_str(&body) {
+			Ok(Value::Object(envelope)) => read_envelope(envelope, library),
+			_ if is_json => Err(Error::InvalidBody {
+				encoding: "JSON".to_string(),
+			}),
+			_ => Ok((
+				body,
+				Options {
+					library,
+					..Options::default()
+				},
+			)),
+		});
+	let (source, options) = match res {
+		Ok(read) => read,
+		Err(err) => {
+			let body: Vec<Diagnostic> = err.into();
+			return Response::new(400, to_string(&body).unwrap());
+		}
+	};
+
+	match pipeline::compile(&source, &options) {
+		Ok(result) => Response::new(200, to_string(&result).unwrap()),
+		Err(diagnostics) => Response::new(400, to_string(&diagnostics).unwrap()),
+	}
+}
+
+// `GET /api/catalog?library=<name>&kind=<signals|actuators|genes>&q=<text>`
+pub fn catalog(req: &Request) -> Response {
+	match req.method {
+		Method::OPTIONS => return Response::preflight("GET, OPTIONS"),
+		Method::GET => {}
+		_ => return Response::new(405, String::new()),
+	}
+
+	let library = req
+		.query("library")
+		.unwrap_or_else(|| DEFAULT_LIBRARY.to_string());
+	let kind = req.query("kind");
+	let query = req.query("q");
+	let catalog = Filter::new(kind.as_deref(), query.as_deref())
+		.and_then(|filter| Ok(get_libraries().require(&library)?.catalog(&filter)));
+
+	match catalog {
+		Ok(catalog) => Response::new(200, to_string(&catalog).unwrap()),
+		Err(err) => Response::new(400, to_string(&Diagnostic::error(err)).unwrap()),
+	}
+}
+
+// `GET /api/explain?code=<code>`, or every code without `code`.
+pub fn explain(req: &Request) -> Response {
+	match req.method {
+		Method::OPTIONS => return Response::preflight("GET, OPTIONS"),
+		Method::GET => {}
+		_ => return Response::new(405, String::new()),
+	}
+
+	let code = match req.query("code") {
+		Some(code) => code,
+		None => return Response::new(200, to_string(&EXPLANATIONS).unwrap()),
+	};
+	match explain::explain(&code) {
+		Ok(exp) => Response::new(200, to_string(exp).unwrap()),
+		Err(err) => Response::new(404, to_string(&Diagnostic::error(err)).unwrap()),
+	}
+}
+
+// `GET /health`, with the libraries that loaded, only on the self-hosted
+// server.
+pub fn health(req: &Request) -> Response {
+	match req.method {
+		Method::GET => {}
+		_ => return Response::new(405, String::new()),
+	}
+	let body = json!({
+		"status": "ok",
+		"version": env!("CARGO_PKG_VERSION"),
+		"libraries": get_libraries().names(),
+	});
+	Response::new(200, body.to_string())
+}
+
+// The handler for the path of `req`, routed like `vercel.json`.
+pub fn route(req: &Request) -> Response {
+	let path = req.path.split('?').next().unwrap_or("");
+	match path.trim_end_matches('/') {
+		"/api/compile" => compile(req),
+		"/api/catalog" => catalog(req),
+		"/api/explain" => explain(req),
+		"/health" => health(req),
+		_ => Response::new(404, String::new()),
+	}
+}
@@ -0,0 +1,601 @@
+use crate::_utils::{
+	data::{Part, PartKind},
+	dna::Plasmid,
+	error::Error,
+};
+use serde::Serialize;
+use serde_json::{from_str, json, to_string_pretty, Value};
+use std::collections::{HashMap, HashSet};
+
+const NAMESPACE: &str = "https://github.com/gogenhub/emergence";
+const SBOL: &str = "http://sbols.org/v3#";
+const SBO: &str = "https://identifiers.org/SBO:";
+const SO: &str = "https://identifiers.org/SO:";
+const IUPAC_DNA: &str = "https://identifiers.org/edam:format_1207";
+
+#[derive(Serialize, Debug)]
+pub struct Sbol {
+	pub document: String,
+}
+
+pub enum InteractionKind {
+	Repression,
+	Induction,
+}
+
+pub enum Participant {
+	Feature(usize, usize),
+	Sensor(String),
+}
+
+struct Unit {
+	uri: String,
+	instance: String,
+	features: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct SbolDocument {
+	graph: Vec<Value>,
+	ids: HashSet<String>,
+	parts: HashMap<String, String>,
+	sensors: HashMap<String, String>,
+	units: Vec<Unit>,
+	design: Vec<Value>,
+	references: HashMap<(usize, usize), String>,
+	interactions: Vec<Value>,
+}
+
+impl PartKind {
+	pub fn sequence_role(&self) -> &'static str {
+		match self {
+			PartKind::Promoter => "0000167",
+			PartKind::Cds => "0000316",
+			PartKind::Rbs => "0000139",
+			PartKind::Terminator => "0000141",
+			PartKind::Ribozyme => "0000374",
+			PartKind::Scar => "0001953",
+			PartKind::SgRNA => "0001998",
+			PartKind::Backbone | PartKind::Actuator => "0000804",
+		}
+	}
+
+	pub fn from_sequence_role(role: &str) -> Option<Self> {
+		let term = role.rsplit([':', '/']).next()?;
+		match term {
+			"0000167" => Some(PartKind::Promoter),
+			"0000316" => Some(PartKind::Cds),
+			"0000139" => Some(PartKind::Rbs),
+			"0000141" => Some(PartKind::Terminator),
+			"0000374" => Some(PartKind::Ribozyme),
+			"0001953" => Some(PartKind::Scar),
+			"0001998" => Some(PartKind::SgRNA),
+			_ => None,
+		}
+	}
+}
+
+impl InteractionKind {
+	fn terms(&self) -> (&'static str, &'static str, &'static str) {
+		match self {
+			InteractionKind::Repression => ("0000169", "0000020", "0000642"),
+			InteractionKind::Induction => ("0000170", "0000459", "0000643"),
+		}
+	}
+
+	fn verb(&self) -> &'static str {
+		match self {
+			InteractionKind::Repression => "represses",
+			InteractionKind::Induction => "induces",
+		}
+	}
+}
+
+fn display_id(name: &str) -> String {
+	let id: String = name
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.collect();
+	if id.starts_with(|c: char| c.is_ascii_digit()) {
+		format!("_{}", id)
+	} else {
+		id
+	}
+}
+
+fn reference(uri: &str) -> Value {
+	json!({ "@id": uri })
+}
+
+fn identified(kind: &str, parent: &str, id: &str) -> Value {
+	json!({
+		"@id": format!("{}/{}", parent, id),
+		"@type": format!("sbol:{}", kind),
+		"sbol:displayId": id,
+	})
+}
+
+fn top_level(kind: &str, id: &str, name: &str) -> Value {
+	let mut obj = identified(kind, NAMESPACE, id);
+	obj["sbol:name"] = json!(name);
+	obj["sbol:hasNamespace"] = reference(NAMESPACE);
+	obj
+}
+
+fn sub_component(parent: &str, index: usize, instance_of: &str) -> Value {
+	let mut sub = identified("SubComponent", parent, &format!("SubComponent{}", index));
+	sub["sbol:instanceOf"] = reference(instance_of);
+	sub
+}
+
+fn range(parent: &Value, sequence: &str, start: usize, end: usize) -> Value {
+	let mut range = identified("Range", parent["@id"].as_str().unwrap(), "Range1");
+	range["sbol:start"] = json!(start + 1);
+	range["sbol:end"] = json!(end);
+	range["sbol:hasSequence"] = reference(sequence);
+	range["sbol:orientation"] = reference(&format!("{}inline", SBOL));
+	range
+}
+
+// Chains the features with `precedes` constraints in the order they were listed.
+fn precedes(parent: &str, features: &[Value]) -> Vec<Value> {
+	features
+		.windows(2)
+		.enumerate()
+		.map(|(i, pair)| {
+			let mut constraint = identified("Constraint", parent, &format!("Constraint{}", i + 1));
+			constraint["sbol:restriction"] = reference(&format!("{}precedes", SBOL));
+			constraint["sbol:subject"] = pair[0]["@id"].clone();
+			constraint["sbol:object"] = pair[1]["@id"].clone();
+			constraint
+		})
+		.collect()
+}
+
+impl SbolDocument {
+	pub fn new() -> Self {
+		Self {
+			graph: Vec::new(),
+			ids: HashSet::new(),
+			parts: HashMap::new(),
+			sensors: HashMap::new(),
+			units: Vec::new(),
+			design: Vec::new(),
+			references: HashMap::new(),
+			interactions: Vec::new(),
+		}
+	}
+
+	// `id`, or `id` with a number appended when a name that maps to the same
+	// display id (`a-b` and `a_b`) came first. The id of its sequence is
+	// taken along with it.
+	fn unique_id(&mut self, id: &str) -> String {
+		let taken = |ids: &HashSet<String>, id: &str| {
+			id == "circuit" || ids.contains(id) || ids.contains(&format!("{}_seq", id))
+		};
+		let mut unique = id.to_string();
+		let mut n = 1;
+		while taken(&self.ids, &unique) {
+			n += 1;
+			unique = format!("{}_{}", id, n);
+		}
+		self.ids.insert(format!("{}_seq", unique));
+		self.ids.insert(unique.to_string());
+		unique
+	}
+
+	fn add_sequence(&mut self, id: &str, name: &str, seq: &str) -> String {
+		let mut sequence = top_level("Sequence", &format!("{}_seq", id), name);
+		sequence["sbol:elements"] = json!(seq.to_lowercase());
+		sequence["sbol:encoding"] = reference(IUPAC_DNA);
+		let uri = sequence["@id"].as_str().unwrap().to_string();
+		self.graph.push(sequence);
+		uri
+	}
+
+	fn add_dna(&mut self, id: &str, name: &str, role: &str, seq: &str) -> Value {
+		let id = self.unique_id(id);
+		let sequence = self.add_sequence(&id, name, seq);
+		let mut component = top_level("Component", &id, name);
+		component["sbol:type"] = json!([reference(&format!("{}0000251", SBO))]);
+		component["sbol:role"] = json!([reference(&format!("{}{}", SO, role))]);
+		component["sbol:hasSequence"] = json!([reference(&sequence)]);
+		component
+	}
+
+	pub fn add_part(&mut self, part: &Part) -> String {
+		if let Some(uri) = self.parts.get(&part.name) {
+			return uri.to_string();
+		}
+
+		let component = self.add_dna(
+			&display_id(&part.name),
+			&part.name,
+			part.kind.sequence_role(),
+			&part.seq,
+		);
+		let uri = component["@id"].as_str().unwrap().to_string();
+		self.graph.push(component);
+		self.parts.insert(part.name.to_string(), uri.to_string());
+		uri
+	}
+
+	// Places the given parts one after another on a sequence of their own, so
+	// the unit can be reused both in the design and on the plasmid.
+	fn add_layout(
+		&mut self,
+		id: &str,
+		name: &str,
+		role: &str,
+		parts: &[&Part],
+	) -> (String, Vec<String>) {
+		let seq: String = parts.iter().map(|part| part.seq.as_str()).collect();
+		let mut component = self.add_dna(id, name, role, &seq);
+		let uri = component["@id"].as_str().unwrap().to_string();
+		let sequence = component["sbol:hasSequence"][0]["@id"]
+			.as_str()
+			.unwrap()
+			.to_string();
+
+		let mut features = Vec::new();
+		let mut start = 0;
+		for (i, part) in parts.iter().enumerate() {
+			let instance = self.add_part(part);
+			let mut sub = sub_component(&uri, i + 1, &instance);
+			sub["sbol:hasLocation"] =
+				json!([range(&sub, &sequence, start, start + part.seq.len())]);
+			start += part.seq.len();
+			features.push(sub);
+		}
+
+		let ids = features
+			.iter()
+			.map(|sub| sub["@id"].as_str().unwrap().to_string())
+			.collect();
+		component["sbol:hasConstraint"] = json!(precedes(&uri, &features));
+		component["sbol:hasFeature"] = json!(features);
+		self.graph.push(component);
+		(uri, ids)
+	}
+
+	pub fn add_unit(&mut self, name: &str, parts: &[&Part]) -> usize {
+		let id = format!("{}_tu", display_id(name));
+		let (uri, features) = self.add_layout(&id, name, "0000804", parts);
+
+		let design = format!("{}/circuit", NAMESPACE);
+		let sub = sub_component(&design, self.design.len() + 1, &uri);
+		let instance = sub["@id"].as_str().unwrap().to_string();
+		self.design.push(sub);
+		self.units.push(Unit {
+			uri,
+			instance,
+			features,
+		});
+		self.units.len() - 1
+	}
+
+	fn add_sensor(&mut self, name: &str) -> String {
+		if let Some(instance) = self.sensors.get(name) {
+			return instance.to_string();
+		}
+
+		let id = self.unique_id(&format!("{}_sensor", display_id(name)));
+		let mut component = top_level("Component", &id, name);
+		component["sbol:type"] = json!([reference(&format!("{}0000252", SBO))]);
+		let uri = component["@id"].as_str().unwrap().to_string();
+		self.graph.push(component);
+
+		let design = format!("{}/circuit", NAMESPACE);
+		let sub = sub_component(&design, self.design.len() + 1, &uri);
+		let instance = sub["@id"].as_str().unwrap().to_string();
+		self.design.push(sub);
+		self.sensors.insert(name.to_string(), instance.to_string());
+		instance
+	}
+
+	fn participant(&mut self, participant: &Participant) -> String {
+		match participant {
+			Participant::Sensor(name) => self.add_sensor(name),
+			Participant::Feature(unit, position) => {
+				if let Some(uri) = self.references.get(&(*unit, *position)) {
+					return uri.to_string();
+				}
+
+				let design = format!("{}/circuit", NAMESPACE);
+				let id = format!("ComponentReference{}", self.references.len() + 1);
+				let mut cref = identified("ComponentReference", &design, &id);
+				cref["sbol:inChildOf"] = reference(&self.units[*unit].instance);
+				cref["sbol:refersTo"] = reference(&self.units[*unit].features[*position]);
+				let uri = cref["@id"].as_str().unwrap().to_string();
+				self.design.push(cref);
+				self.references.insert((*unit, *position), uri.to_string());
+				uri
+			}
+		}
+	}
+
+	pub fn add_interaction(
+		&mut self,
+		kind: InteractionKind,
+		name: (&str, &str),
+		source: Participant,
+		target: Participant,
+	) {
+		let (kind_term, source_role, target_role) = kind.terms();
+		let source = self.participant(&source);
+		let target = self.participant(&target);
+
+		let design = format!("{}/circuit", NAMESPACE);
+		let id = format!("Interaction{}", self.interactions.len() + 1);
+		let mut interaction = identified("Interaction", &design, &id);
+		let uri = interaction["@id"].as_str().unwrap().to_string();
+		let participations: Vec<Value> = [(source, source_role), (target, target_role)]
+			.iter()
+			.enumerate()
+			.map(|(i, (participant, role))| {
+				let mut part =
+					identified("Participation", &uri, &format!("Participation{}", i + 1));
+				part["sbol:role"] = json!([reference(&format!("{}{}", SBO, role))]);
+				part["sbol:participant"] = reference(participant);
+				part
+			})
+			.collect();
+
+		interaction["sbol:name"] = json!(format!("{} {} {}", name.0, kind.verb(), name.1));
+		interaction["sbol:type"] = json!([reference(&format!("{}{}", SBO, kind_term))]);
+		interaction["sbol:hasParticipation"] = json!(participations);
+		self.interactions.push(interaction);
+	}
+
+	// `units` maps the index of the first feature of every transcription unit
+	// on the plasmid to the unit, the rest of the features become plain parts.
+	pub fn add_plasmid(&mut self, plasmid: &Plasmid, units: &[(usize, usize)]) {
+		let id = display_id(&plasmid.name);
+		let mut component = self.add_dna(&id, &plasmid.name, "0000637", &plasmid.seq);
+		component["sbol:description"] = json!(plasmid.definition);
+		component["sbol:type"]
+			.as_array_mut()
+			.unwrap()
+			.push(reference(&format!("{}0000988", SO)));
+		let uri = component["@id"].as_str().unwrap().to_string();
+		let sequence = component["sbol:hasSequence"][0]["@id"]
+			.as_str()
+			.unwrap()
+			.to_string();
+
+		let mut features = Vec::new();
+		let mut i = 0;
+		while i < plasmid.features.len() {
+			let feature = &plasmid.features[i];
+			let (instance, end, next) = match units.iter().find(|(first, _)| *first == i) {
+				Some((_, unit)) => {
+					let unit = &self.units[*unit];
+					let last = i + unit.features.len() - 1;
+					(unit.uri.to_string(), plasmid.features[last].end, last + 1)
+				}
+				None => {
+					let part = Part {
+						kind: feature.kind.clone(),
+						name: feature.name.to_string(),
+						seq: plasmid.seq[feature.start..feature.end].to_string(),
+					};
+					(self.add_part(&part), feature.end, i + 1)
+				}
+			};
+
+			let mut sub = sub_component(&uri, features.len() + 1, &instance);
+			sub["sbol:hasLocation"] = json!([range(&sub, &sequence, feature.start, end)]);
+			features.push(sub);
+			i = next;
+		}
+
+		component["sbol:hasConstraint"] = json!(precedes(&uri, &features));
+		component["sbol:hasFeature"] = json!(features);
+		self.graph.push(component);
+	}
+
+	pub fn finish(mut self) -> Sbol {
+		let mut design = top_level("Component", "circuit", "circuit");
+		design["sbol:type"] = json!([reference(&format!("{}0000241", SBO))]);
+		design["sbol:hasFeature"] = json!(self.design);
+		design["sbol:hasInteraction"] = json!(self.interactions);
+		self.graph.push(design);
+
+		let document = json!({
+			"@context": { "sbol": SBOL },
+			"@graph": self.graph,
+		});
+		Sbol {
+			document: to_string_pretty(&document).unwrap(),
+		}
+	}
+}
+
+// Properties can be written with the `sbol:` prefix or as full IRIs, and their
+// values either as `{"@id": ...}` references or as plain strings.
+fn property<'a>(obj: &'a Value, name: &str) -> Vec<&'a Value> {
+	let value = obj
+		.get(format!("sbol:{}", name))
+		.or_else(|| obj.get(format!("{}{}", SBOL, name)));
+	match value {
+		Some(Value::Array(values)) => values.iter().collect(),
+		Some(value) => vec![value],
+		None => Vec::new(),
+	}
+}
+
+fn iri(value: &Value) -> Option<&str> {
+	match value {
+		Value::String(iri) => Some(iri),
+		Value::Object(obj) => obj.get("@id").or_else(|| obj.get("@value"))?.as_str(),
+		_ => None,
+	}
+}
+
+fn is_type(obj: &Value, kind: &str) -> bool {
+	let types = match obj.get("@type") {
+		Some(Value::Array(types)) => types.iter().collect(),
+		Some(value) => vec![value],
+		None => Vec::new(),
+	};
+	types
+		.iter()
+		.filter_map(|t| t.as_str())
+		.any(|t| t == format!("sbol:{}", kind) || t == format!("{}{}", SBOL, kind))
+}
+
+fn collect<'a>(value: &'a Value, objects: &mut HashMap<&'a str, &'a Value>) {
+	match value {
+		Value::Object(obj) => {
+			if let (Some(id), Some(_)) = (obj.get("@id").and_then(Value::as_str), obj.get("@type"))
+			{
+				objects.insert(id, value);
+			}
+			obj.values().for_each(|v| collect(v, objects));
+		}
+		Value::Array(values) => values.iter().for_each(|v| collect(v, objects)),
+		_ => {}
+	}
+}
+
+impl Part {
+	// Reads an SBOL3 JSON-LD document and turns every DNA component with a
+	// part role and a sequence into a part. Composite designs are skipped.
+	pub fn from_sbol(text: &str) -> Result<Vec<Part>, Error> {
+		let document: Value = from_str(text).map_err(|e| Error::InvalidFile {
+			reason: format!("not JSON-LD: {}", e),
+		})?;
+		let mut objects = HashMap::new();
+		collect(&document, &mut objects);
+
+		let mut ids: Vec<&&str> = objects.keys().collect();
+		ids.sort();
+		let mut parts = Vec::new();
+		for id in ids {
+			let obj = objects[*id];
+			if !is_type(obj, "Component") {
+				continue;
+			}
+			let is_dna = property(obj, "type")
+				.into_iter()
+				.filter_map(iri)
+				.any(|t| t.ends_with("0000251"));
+			let kind = property(obj, "role")
+				.into_iter()
+				.filter_map(iri)
+				.find_map(PartKind::from_sequence_role);
+			let kind = match kind {
+				Some(kind) if is_dna => kind,
+				_ => continue,
+			};
+
+			let sequence = property(obj, "hasSequence")
+				.into_iter()
+				.filter_map(iri)
+				.find_map(|seq| objects.get(seq))
+				.and_then(|seq| property(seq, "elements").into_iter().find_map(iri))
+				.ok_or_else(|| Error::InvalidFile {
+					reason: format!("{} has no sequence", id),
+				})?;
+			let name = property(obj, "name")
+				.into_iter()
+				.chain(property(obj, "displayId"))
+				.find_map(iri)
+				.unwrap_or(id);
+
+			parts.push(Part {
+				kind,
+				name: name.to_string(),
+				seq: sequence.to_string(),
+			});
+		}
+		Ok(parts)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn part(kind: PartKind, name: &str, seq: &str) -> Part {
+		Part {
+			kind,
+			name: name.to_string(),
+			seq: seq.to_string(),
+		}
+	}
+
+	#[test]
+	fn export_round_trip() {
+		let parts = [
+			part(PartKind::Promoter, "p-A", "TTGACAAT"),
+			part(PartKind::Promoter, "p_A", "TTGATTAT"),
+			part(PartKind::Cds, "LacI", "ATGAAACCA"),
+		];
+		let mut doc = SbolDocument::new();
+		let unit = doc.add_unit("p-A", &[&parts[0], &parts[2]]);
+		doc.add_unit("p_A", &[&parts[1], &parts[2]]);
+		doc.add_interaction(
+			InteractionKind::Induction,
+			("IPTG", "p-A"),
+			Participant::Sensor("IPTG".to_string()),
+			Participant::Feature(unit, 0),
+		);
+		let sbol = doc.finish();
+
+		let document: Value = from_str(&sbol.document).unwrap();
+		let graph = document["@graph"].as_array().unwrap();
+		let mut ids: Vec<&str> = graph
+			.iter()
+			.map(|obj| obj["sbol:displayId"].as_str().unwrap())
+			.collect();
+		let total = ids.len();
+		ids.sort();
+		ids.dedup();
+		assert_eq!(ids.len(), total, "{:?}", ids);
+		assert!(ids.contains(&"p_A") && ids.contains(&"p_A_2"));
+
+		let mut read = Part::from_sbol(&sbol.document).unwrap();
+		read.sort_by(|a, b| a.name.cmp(&b.name));
+		let names: Vec<&str> = read.iter().map(|part| part.name.as_str()).collect();
+		assert_eq!(names, ["LacI", "p-A", "p_A"]);
+		for read in &read {
+			let part = parts.iter().find(|part| part.name == read.name).unwrap();
+			assert_eq!(read.kind, part.kind);
+			assert_eq!(read.seq.to_uppercase(), part.seq);
+		}
+	}
+
+	// Full IRIs and plain string values are read the same as prefixed
+	// references; components that aren't DNA parts are left out.
+	#[test]
+	fn imports_parts() {
+		let text = r#"{
+	"@graph": [
+		{
+			"@id": "https://example.org/pTet",
+			"@type": "http://sbols.org/v3#Component",
+			"http://sbols.org/v3#displayId": "pTet",
+			"http://sbols.org/v3#type": "https://identifiers.org/SBO:0000251",
+			"http://sbols.org/v3#role": "https://identifiers.org/SO:0000167",
+			"http://sbols.org/v3#hasSequence": "https://example.org/pTet_seq"
+		},
+		{
+			"@id": "https://example.org/pTet_seq",
+			"@type": "http://sbols.org/v3#Sequence",
+			"http://sbols.org/v3#elements": "tccctatcagtgatagaga"
+		},
+		{
+			"@id": "https://example.org/aTc",
+			"@type": "sbol:Component",
+			"sbol:type": [{ "@id": "https://identifiers.org/SBO:0000247" }]
+		}
+	]
+}"#;
+		let parts = Part::from_sbol(text).unwrap();
+		assert_eq!(parts.len(), 1);
+		assert_eq!(parts[0].kind, PartKind::Promoter);
+		assert_eq!(parts[0].name, "pTet");
+		assert_eq!(parts[0].seq, "tccctatcagtgatagaga");
+	}
+}
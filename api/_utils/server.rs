@@ -0,0 +1,142 @@
+use crate::_utils::{diagnostic, error, http, routes};
+use diagnostic::Diagnostic;
+use error::Error;
+use http::{Method, Request, Response};
+use std::{
+	collections::HashMap,
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{TcpListener, TcpStream},
+	panic, thread,
+	time::{Duration, Instant},
+};
+
+const MAX_BODY: usize = 1 << 20;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// Serves the routes of `vercel.json` over plain HTTP/1.1, one request per
+// connection and `threads` requests at a time. Bodies are read as raw text,
+// or as base64 with `Content-Transfer-Encoding: base64`, the same as
+// `Request::encoding` on Vercel.
+pub fn serve(listener: TcpListener, threads: usize) -> io::Result<()> {
+	let mut workers = Vec::new();
+	for _ in 0..threads.max(1) {
+		let listener = listener.try_clone()?;
+		workers.push(thread::spawn(move || {
+			for stream in listener.incoming().flatten() {
+				handle(stream);
+			}
+		}));
+	}
+	for worker in workers {
+		let _ = worker.join();
+	}
+	Ok(())
+}
+
+fn handle(mut stream: TcpStream) {
+	let _ = stream.set_read_timeout(Some(TIMEOUT));
+	let start = Instant::now();
+	let (line, res) = match read_request(&stream) {
+		Ok(req) => {
+			let line = format!("{:?} {}", req.method, req.path);
+			// A panic in a handler only fails its own request.
+			let res = panic::catch_unwind(|| routes::route(&req))
+				.unwrap_or_else(|_| Response::new(500, String::new()));
+			(line, res)
+		}
+		Err(res) => ("-".to_string(), res),
+	};
+	eprintln!(
+		"{} {} {}ms",
+		line,
+		res.status_code,
+		start.elapsed().as_millis()
+	);
+	let _ = write_response(&mut stream, &res);
+}
+
+// The request on `stream`, or the response to send back if it's malformed.
+fn read_request(stream: &TcpStream) -> Result<Request, Response> {
+	let bad_request = || Response::new(400, String::new());
+	let mut reader = BufReader::new(stream);
+	let mut line = String::new();
+	reader.read_line(&mut line).map_err(|_| bad_request())?;
+	let mut parts = line.split_whitespace();
+	let method = parts.next().ok_or_else(bad_request)?;
+	let method = Method::from_name(method).ok_or_else(|| Response::new(405, String::new()))?;
+	let path = parts.next().ok_or_else(bad_request)?.to_string();
+
+	let mut headers = HashMap::new();
+	loop {
+		let mut line = String::new();
+		reader.read_line(&mut line).map_err(|_| bad_request())?;
+		let line = line.trim_end();
+		if line.is_empty() {
+			break;
+		}
+		let (name, value) = line.split_once(':').ok_or_else(bad_request)?;
+		headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+	}
+
+	let length = match headers.get("content-length") {
+		Some(length) => length.parse().map_err(|_| bad_request())?,
+		None => 0,
+	};
+	if length > MAX_BODY {
+		return Err(Response::new(413, String::new()));
+	}
+	let mut body = vec![0; length];
+	reader.read_exact(&mut body).map_err(|_| bad_request())?;
+	let body = String::from_utf8(body).map_err(|_| {
+		let body: Vec<Diagnostic> = Error::InvalidBody {
+			encoding: "UTF-8".to_string(),
+		}
+		.into();
+		Response::new(400, serde_json::to_string(&body).unwrap())
+	})?;
+
+	let encoding = headers
+		.get("content-transfer-encoding")
+		.map(|encoding| encoding.to_lowercase());
+	Ok(Request {
+		host: headers.get("host").cloned().unwrap_or_default(),
+		path,
+		method,
+		headers,
+		body,
+		encoding,
+	})
+}
+
+fn reason(status_code: u16) -> &'static str {
+	match status_code {
+		200 => "OK",
+		400 => "Bad Request",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		413 => "Payload Too Large",
+		500 => "Internal Server Error",
+		_ => "",
+	}
+}
+
+fn write_response(stream: &mut TcpStream, res: &Response) -> io::Result<()> {
+	let mut head = format!(
+		"HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+		res.status_code,
+		reason(res.status_code),
+		res.body.len()
+	);
+	if !res.body.is_empty() {
+		head += "Content-Type: application/json\r\n";
+	}
+	let mut headers: Vec<_> = res.headers.iter().collect();
+	headers.sort();
+	for (name, value) in headers {
+		head += &format!("{}: {}\r\n", name, value);
+	}
+	head += "\r\n";
+	stream.write_all(head.as_bytes())?;
+	stream.write_all(res.body.as_bytes())?;
+	stream.flush()
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+use std::fmt::{Display, Formatter, Result};
+
+// Location of a token in its source. `line` and `col` start at 1, `col`
+// counting chars, `start..end` is the byte range.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Span {
+	pub file: Option<String>,
+	pub line: usize,
+	pub col: usize,
+	pub start: usize,
+	pub end: usize,
+}
+
+impl Span {
+	// Empty span after the last token of `source`, where a missing token was
+	// expected.
+	pub fn end_of(source: &str, file: Option<String>) -> Self {
+		let text = source.trim_end();
+		let last = text.rfind('\n').map(|i| i + 1).unwrap_or(0);
+		Self {
+			file,
+			line: text.matches('\n').count() + 1,
+			col: text[last..].chars().count() + 1,
+			start: text.len(),
+			end: text.len(),
+		}
+	}
+}
+
+impl Display for Span {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		match &self.file {
+			Some(file) => write!(f, "{}:{}:{}", file, self.line, self.col),
+			None => write!(f, "{}:{}", self.line, self.col),
+		}
+	}
+}
+
+// Renders `message` at `level` (error, warning) with the source line of
+// `span` underlined, in the layout of rustc:
+//
+//     error: unexpected token `)`
+//      --> main.em:3:16
+//       |
+//     3 |     let a = not);
+//       |                ^ unexpected token
+pub fn render(source: &str, span: &Span, level: &str, message: &str, label: &str) -> String {
+	let line = source
+		.lines()
+		.nth(span.line.saturating_sub(1))
+		.unwrap_or("");
+	let number = span.line.to_string();
+	let gutter = " ".repeat(number.len());
+
+	// Tabs are kept so the carets line up with the text above them.
+	let indent: String = line
+		.chars()
+		.take(span.col.saturating_sub(1))
+		.map(|c| if c == '\t' { '\t' } else { ' ' })
+		.collect();
+	let rest = line
+		.chars()
+		.count()
+		.saturating_sub(span.col.saturating_sub(1));
+	let width = source
+		.get(span.start..span.end)
+		.map(|text| text.lines().next().unwrap_or("").chars().count())
+		.unwrap_or(0)
+		.min(rest)
+		.max(1);
+
+	let mut out = format!(
+		"{}: {}\n{}--> {}\n{} |\n",
+		level, message, gutter, span, gutter
+	);
+	out += &format!("{} | {}\n", number, line);
+	out += &format!("{} | {}{}", gutter, indent, "^".repeat(width));
+	if !label.is_empty() {
+		out += &format!(" {}", label);
+	}
+	out
+}
@@ -0,0 +1,514 @@
+use crate::_utils::{data, error::Error, genetic_circuit::Signal};
+use data::{Data, GeneData, Params, Part, PartKind, Rules};
+use serde_json::{from_str, Value};
+use std::collections::{HashMap, HashSet};
+
+// UCF response functions carry no degradation rate, so imported gates get this
+// one unless the model lists a `decay` parameter.
+pub const DEFAULT_DECAY: f64 = 0.1;
+
+// Collections of a UCF document, indexed by collection and name. Cello v1
+// keeps everything in one file, Cello 2 splits it into a UCF, an input and an
+// output file, so all of them are read into the same index.
+struct Ucf {
+	collections: HashMap<String, Vec<Value>>,
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+	value.get(key)?.as_str().map(String::from)
+}
+
+fn parameter(params: &[Value], name: &str) -> Option<f64> {
+	params
+		.iter()
+		.find(|p| text(p, "name").as_deref() == Some(name))
+		.and_then(|p| p.get("value"))
+		.and_then(Value::as_f64)
+}
+
+// Orders the names so every `a BEFORE b` rule holds, keeping the order of
+// first appearance where the rules leave a choice. Fails when the rules go
+// round in a circle, as no order can hold them all.
+fn order(names: &[String], before: &[(String, String)]) -> Result<HashMap<String, u32>, Error> {
+	let mut remaining: Vec<String> = names.to_vec();
+	let mut ordered = Vec::new();
+	while !remaining.is_empty() {
+		let free = remaining.iter().position(|name| {
+			!before
+				.iter()
+				.any(|(a, b)| b == name && a != name && remaining.contains(a))
+		});
+		match free {
+			Some(free) => ordered.push(remaining.remove(free)),
+			None => {
+				return Err(Error::InvalidFile {
+					reason: format!("cyclic BEFORE rules involving {}", remaining.join(", ")),
+				})
+			}
+		}
+	}
+	Ok(ordered
+		.into_iter()
+		.enumerate()
+		.map(|(i, name)| (name, i as u32))
+		.collect())
+}
+
+impl Ucf {
+	fn parse(files: &[String]) -> Result<Self, Error> {
+		let mut collections: HashMap<String, Vec<Value>> = HashMap::new();
+		for file in files {
+			let values: Vec<Value> = from_str(file).map_err(|e| Error::InvalidFile {
+				reason: format!("not a UCF file: {}", e),
+			})?;
+			for value in values {
+				let collection = text(&value, "collection").ok_or_else(|| Error::InvalidFile {
+					reason: "entry without a collection".into(),
+				})?;
+				collections.entry(collection).or_default().push(value);
+			}
+		}
+		Ok(Self { collections })
+	}
+
+	fn all(&self, collection: &str) -> &[Value] {
+		self.collections
+			.get(collection)
+			.map(Vec::as_slice)
+			.unwrap_or(&[])
+	}
+
+	fn find(&self, collection: &str, key: &str, name: &str) -> Option<&Value> {
+		self.all(collection)
+			.iter()
+			.find(|v| text(v, key).as_deref() == Some(name))
+	}
+
+	// Cello 2 keeps parameters in a separate model, Cello v1 in the response
+	// function of the gate.
+	fn parameters(&self, entry: &Value, name: &str) -> Vec<Value> {
+		let model = text(entry, "model").and_then(|model| self.find("models", "name", &model));
+		let function = self.find("response_functions", "gate_name", name);
+		model
+			.or(function)
+			.or(Some(entry))
+			.and_then(|v| v.get("parameters"))
+			.and_then(Value::as_array)
+			.cloned()
+			.unwrap_or_default()
+	}
+
+	// Flattens the devices of a Cello 2 structure into its parts, dropping the
+	// `#in` placeholders for input promoters.
+	fn structure_parts(&self, structure: &Value, device: &str, parts: &mut Vec<String>) {
+		let devices = structure
+			.get("devices")
+			.and_then(Value::as_array)
+			.cloned()
+			.unwrap_or_default();
+		let components = devices
+			.iter()
+			.find(|d| text(d, "name").as_deref() == Some(device))
+			.and_then(|d| d.get("components"))
+			.and_then(Value::as_array)
+			.cloned()
+			.unwrap_or_default();
+		for comp in components.iter().filter_map(Value::as_str) {
+			if comp.starts_with('#') {
+				continue;
+			}
+			if devices
+				.iter()
+				.any(|d| text(d, "name").as_deref() == Some(comp))
+			{
+				self.structure_parts(structure, comp, parts);
+			} else {
+				parts.push(comp.to_string());
+			}
+		}
+	}
+
+	// Returns the parts and output promoter of a gate, sensor or output device.
+	fn layout(&self, entry: &Value, name: &str) -> (Vec<String>, Option<String>) {
+		if let Some(gate_parts) = self.find("gate_parts", "gate_name", name) {
+			let parts = gate_parts["expression_cassettes"][0]["cassette_parts"]
+				.as_array()
+				.map(|parts| {
+					parts
+						.iter()
+						.filter_map(|p| p.as_str().map(String::from))
+						.collect()
+				})
+				.unwrap_or_default();
+			return (parts, text(gate_parts, "promoter"));
+		}
+
+		let structure = text(entry, "structure").and_then(|s| self.find("structures", "name", &s));
+		match structure {
+			Some(structure) => {
+				let mut parts = Vec::new();
+				self.structure_parts(structure, name, &mut parts);
+				let promoter = structure["outputs"][0].as_str().map(String::from);
+				(parts, promoter)
+			}
+			None => (Vec::new(), text(entry, "promoter")),
+		}
+	}
+
+	fn rules(&self) -> Vec<String> {
+		fn strings(value: &Value, out: &mut Vec<String>) {
+			match value {
+				Value::String(s) => out.push(s.to_string()),
+				Value::Array(values) => values.iter().for_each(|v| strings(v, out)),
+				Value::Object(obj) => obj.values().for_each(|v| strings(v, out)),
+				_ => {}
+			}
+		}
+
+		let mut rules = Vec::new();
+		for collection in &["eugene_rules", "circuit_rules", "device_rules"] {
+			for value in self.all(collection) {
+				strings(value, &mut rules);
+			}
+		}
+		rules
+	}
+}
+
+impl Data {
+	pub fn from_ucf(files: &[String], decay: f64) -> Result<Self, Error> {
+		let ucf = Ucf::parse(files)?;
+		let mut data = Data::new();
+
+		for part in ucf.all("parts") {
+			let name = text(part, "name").unwrap_or_default();
+			let kind = match text(part, "type").as_deref().and_then(PartKind::from_name) {
+				Some(kind) => kind,
+				None => continue,
+			};
+			let seq = text(part, "dnasequence").unwrap_or_default().to_uppercase();
+			let part = Part { kind, name, seq };
+			part.validate()?;
+			data.parts.insert(part.name.to_string(), part);
+		}
+
+		let mut groups = Vec::new();
+		for gate in ucf.all("gates") {
+			let name = text(gate, "gate_name")
+				.or_else(|| text(gate, "name"))
+				.ok_or_else(|| Error::InvalidFile {
+					reason: "gate without a name".into(),
+				})?;
+			let params = ucf.parameters(gate, &name);
+			let value = |key: &str| {
+				parameter(&params, key).ok_or_else(|| Error::InvalidFile {
+					reason: format!("gate {} has no {}", name, key),
+				})
+			};
+			let (parts, promoter) = ucf.layout(gate, &name);
+			let promoter = promoter.ok_or_else(|| Error::InvalidFile {
+				reason: format!("gate {} has no promoter", name),
+			})?;
+
+			let gene = GeneData {
+				name: name.to_string(),
+				parts,
+				promoter,
+				params: Params {
+					ymax: value("ymax")?,
+					ymin: value("ymin")?,
+					k: value("K")?,
+					n: value("n")?,
+					decay: parameter(&params, "decay").unwrap_or(decay),
+				},
+			};
+			if !groups.contains(&gene.group()) {
+				groups.push(gene.group());
+			}
+			data.genes.push(gene);
+		}
+
+		for sensor in ucf.all("input_sensors") {
+			let name = text(sensor, "name").unwrap_or_default();
+			let params = ucf.parameters(sensor, &name);
+			let (_, promoter) = ucf.layout(sensor, &name);
+			let promoter = promoter.ok_or_else(|| Error::InvalidFile {
+				reason: format!("sensor {} has no promoter", name),
+			})?;
+			let name = name.trim_end_matches("_sensor").to_string();
+			data.signals.insert(
+				name.to_string(),
+				Signal {
+					name,
+					promoter,
+					rpu_off: parameter(&params, "ymin").unwrap_or(0.0),
+					rpu_on: parameter(&params, "ymax").unwrap_or(0.0),
+				},
+			);
+		}
+
+		for device in ucf.all("output_devices") {
+			let name = text(device, "name").unwrap_or_default();
+			let (parts, _) = ucf.layout(device, &name);
+			let mut seq = String::new();
+			for part in &parts {
+				let part = data.parts.get(part).ok_or_else(|| Error::InvalidFile {
+					reason: format!("output {} uses unknown part {}", name, part),
+				})?;
+				seq += &part.seq;
+			}
+			let name = name.trim_end_matches("_reporter").to_string();
+			data.parts.insert(
+				name.to_string(),
+				Part {
+					kind: PartKind::Actuator,
+					name,
+					seq,
+				},
+			);
+		}
+
+		for gene in &data.genes {
+			for part in &gene.parts {
+				if !data.parts.contains_key(part) {
+					return Err(Error::InvalidFile {
+						reason: format!("gate {} uses unknown part {}", gene.name, part),
+					});
+				}
+			}
+		}
+
+		// Rules name either gates (`A1_AmtR`), their groups or promoters.
+		let mut before = Vec::new();
+		for rule in ucf.rules() {
+			let words: Vec<&str> = rule.split_whitespace().collect();
+			if let [a, "BEFORE", b] = words.as_slice() {
+				before.push((a.to_string(), b.to_string()));
+			}
+		}
+		let group_of: HashMap<String, String> = data
+			.genes
+			.iter()
+			.map(|gene| (gene.name.to_string(), gene.group()))
+			.collect();
+		let gate_before: Vec<(String, String)> = before
+			.iter()
+			.map(|(a, b)| {
+				let group = |x: &String| group_of.get(x).unwrap_or(x).to_string();
+				(group(a), group(b))
+			})
+			.collect();
+
+		let mut promoters: Vec<String> = Vec::new();
+		let mut seen = HashSet::new();
+		let sensors = data.signals.values().map(|sig| sig.promoter.to_string());
+		for promoter in sensors.chain(data.genes.iter().map(|gene| gene.promoter.to_string())) {
+			if seen.insert(promoter.to_string()) {
+				promoters.push(promoter);
+			}
+		}
+		promoters.sort();
+		let mut gate_groups: Vec<String> = data.signals.keys().cloned().collect();
+		gate_groups.sort();
+		for group in groups {
+			if !gate_groups.contains(&group) {
+				gate_groups.push(group);
+			}
+		}
+
+		data.rules = Rules {
+			gates: order(&gate_groups, &gate_before)?,
+			promoters: order(&promoters, &before)?,
+		};
+		Ok(data)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// One gate, one sensor and the parts they use, the way Cello v1 keeps them
+	// in a single file.
+	const V1: &str = r#"[
+	{ "collection": "header", "version": "Eco1C1G1T1" },
+	{ "collection": "parts", "type": "ribozyme", "name": "RiboJ10", "dnasequence": "agcgctcaacgggtgtgctt" },
+	{ "collection": "parts", "type": "rbs", "name": "S2", "dnasequence": "ctcctaggagtacc" },
+	{ "collection": "parts", "type": "cds", "name": "SrpR", "dnasequence": "atggcacgtaaaaccgcggc" },
+	{ "collection": "parts", "type": "terminator", "name": "ECK120029600", "dnasequence": "ttcagccaaaaaacttaaga" },
+	{ "collection": "parts", "type": "promoter", "name": "pSrpR", "dnasequence": "tctatgatgggtttttggtg" },
+	{ "collection": "parts", "type": "promoter", "name": "pTac", "dnasequence": "aacgatcgttggctgtgttg" },
+	{ "collection": "parts", "type": "plasmid", "name": "backbone", "dnasequence": "gggg" },
+	{ "collection": "gates", "gate_name": "S4_SrpR", "gate_type": "NOR", "group_name": "SrpR" },
+	{
+		"collection": "response_functions",
+		"gate_name": "S4_SrpR",
+		"parameters": [
+			{ "name": "ymax", "value": 2.1 },
+			{ "name": "ymin", "value": 0.007 },
+			{ "name": "K", "value": 0.1 },
+			{ "name": "n", "value": 2.8 }
+		]
+	},
+	{
+		"collection": "gate_parts",
+		"gate_name": "S4_SrpR",
+		"expression_cassettes": [
+			{ "cassette_parts": ["RiboJ10", "S2", "SrpR", "ECK120029600"] }
+		],
+		"promoter": "pSrpR"
+	},
+	{
+		"collection": "input_sensors",
+		"name": "LacI_sensor",
+		"promoter": "pTac",
+		"parameters": [
+			{ "name": "ymax", "value": 2.8 },
+			{ "name": "ymin", "value": 0.0034 }
+		]
+	},
+	{ "collection": "eugene_rules", "eugene_part_rules": ["pSrpR BEFORE pTac"] }
+]"#;
+
+	// The same gate as Cello 2 splits it, with the parameters in a model and
+	// the parts in a structure, and the sensor and output in files of their
+	// own.
+	const V2_UCF: &str = r##"[
+	{ "collection": "parts", "type": "ribozyme", "name": "RiboJ10", "dnasequence": "agcgctcaacgggtgtgctt" },
+	{ "collection": "parts", "type": "rbs", "name": "S2", "dnasequence": "ctcctaggagtacc" },
+	{ "collection": "parts", "type": "cds", "name": "SrpR", "dnasequence": "atggcacgtaaaaccgcggc" },
+	{ "collection": "parts", "type": "terminator", "name": "ECK120029600", "dnasequence": "ttcagccaaaaaacttaaga" },
+	{ "collection": "parts", "type": "promoter", "name": "pSrpR", "dnasequence": "tctatgatgggtttttggtg" },
+	{ "collection": "gates", "name": "S4_SrpR", "model": "S4_SrpR_model", "structure": "S4_SrpR_structure" },
+	{
+		"collection": "models",
+		"name": "S4_SrpR_model",
+		"parameters": [
+			{ "name": "ymax", "value": 2.1 },
+			{ "name": "ymin", "value": 0.007 },
+			{ "name": "K", "value": 0.1 },
+			{ "name": "n", "value": 2.8 },
+			{ "name": "decay", "value": 0.3 }
+		]
+	},
+	{
+		"collection": "structures",
+		"name": "S4_SrpR_structure",
+		"outputs": ["pSrpR"],
+		"devices": [
+			{ "name": "S4_SrpR", "components": ["#in1", "#in2", "S4_SrpR_cassette"] },
+			{ "name": "S4_SrpR_cassette", "components": ["RiboJ10", "S2", "SrpR", "ECK120029600"] }
+		]
+	}
+]"##;
+
+	const V2_INPUT: &str = r#"[
+	{ "collection": "parts", "type": "promoter", "name": "pTac", "dnasequence": "aacgatcgttggctgtgttg" },
+	{ "collection": "input_sensors", "name": "LacI_sensor", "model": "LacI_sensor_model", "structure": "LacI_sensor_structure" },
+	{
+		"collection": "models",
+		"name": "LacI_sensor_model",
+		"parameters": [
+			{ "name": "ymax", "value": 2.8 },
+			{ "name": "ymin", "value": 0.0034 }
+		]
+	},
+	{ "collection": "structures", "name": "LacI_sensor_structure", "outputs": ["pTac"] }
+]"#;
+
+	const V2_OUTPUT: &str = r##"[
+	{ "collection": "parts", "type": "cds", "name": "YFP", "dnasequence": "atggtgagcaagggcgag" },
+	{ "collection": "output_devices", "name": "YFP_reporter", "structure": "YFP_reporter_structure" },
+	{
+		"collection": "structures",
+		"name": "YFP_reporter_structure",
+		"devices": [
+			{ "name": "YFP_reporter", "components": ["#in1", "S2", "YFP", "ECK120029600"] }
+		]
+	}
+]"##;
+
+	fn assert_gate(data: &Data, decay: f64) {
+		assert_eq!(data.genes.len(), 1);
+		let gene = &data.genes[0];
+		assert_eq!(gene.name, "S4_SrpR");
+		assert_eq!(gene.promoter, "pSrpR");
+		assert_eq!(gene.parts, ["RiboJ10", "S2", "SrpR", "ECK120029600"]);
+		let params = &gene.params;
+		assert_eq!(
+			(params.ymax, params.ymin, params.k, params.n, params.decay),
+			(2.1, 0.007, 0.1, 2.8, decay)
+		);
+
+		let sensor = &data.signals["LacI"];
+		assert_eq!(sensor.promoter, "pTac");
+		assert_eq!((sensor.rpu_off, sensor.rpu_on), (0.0034, 2.8));
+		assert_eq!(data.parts["SrpR"].kind, PartKind::Cds);
+		assert_eq!(data.parts["SrpR"].seq, "ATGGCACGTAAAACCGCGGC");
+	}
+
+	#[test]
+	fn imports_v1() {
+		let data = Data::from_ucf(&[V1.to_string()], DEFAULT_DECAY).unwrap();
+		assert_gate(&data, DEFAULT_DECAY);
+		// Parts of a kind Emergence has no use for are left out.
+		assert!(!data.parts.contains_key("backbone"));
+		assert!(data.rules.promoters["pSrpR"] < data.rules.promoters["pTac"]);
+	}
+
+	#[test]
+	fn imports_v2() {
+		let files = [V2_UCF, V2_INPUT, V2_OUTPUT].map(String::from);
+		let data = Data::from_ucf(&files, DEFAULT_DECAY).unwrap();
+		assert_gate(&data, 0.3);
+		let yfp = &data.parts["YFP"];
+		assert_eq!(yfp.kind, PartKind::Actuator);
+		assert_eq!(
+			yfp.seq,
+			data.parts["S2"].seq.to_string()
+				+ "ATGGTGAGCAAGGGCGAG"
+				+ &data.parts["ECK120029600"].seq
+		);
+	}
+
+	#[test]
+	fn rejects_outputs_with_unknown_parts() {
+		let output = V2_OUTPUT.replace(
+			r#"{ "collection": "parts", "type": "cds", "name": "YFP", "dnasequence": "atggtgagcaagggcgag" },"#,
+			"",
+		);
+		let files = [V2_UCF, V2_INPUT, &output].map(String::from);
+		match Data::from_ucf(&files, DEFAULT_DECAY) {
+			Err(Error::InvalidFile { reason }) => {
+				assert_eq!(reason, "output YFP_reporter uses unknown part YFP")
+			}
+			_ => panic!("imported an output without its parts"),
+		}
+	}
+
+	#[test]
+	fn rejects_cyclic_rules() {
+		let file = V1.replace(
+			r#"["pSrpR BEFORE pTac"]"#,
+			r#"["pSrpR BEFORE pTac", "pTac BEFORE pSrpR"]"#,
+		);
+		match Data::from_ucf(&[file], DEFAULT_DECAY) {
+			Err(Error::InvalidFile { reason }) => {
+				assert_eq!(reason, "cyclic BEFORE rules involving pSrpR, pTac")
+			}
+			_ => panic!("imported rules no order can hold"),
+		}
+	}
+
+	#[test]
+	fn rejects_gates_without_parameters() {
+		let file = V1.replace(
+			r#"{ "name": "n", "value": 2.8 }"#,
+			r#"{ "name": "x", "value": 0 }"#,
+		);
+		match Data::from_ucf(&[file], DEFAULT_DECAY) {
+			Err(Error::InvalidFile { reason }) => assert_eq!(reason, "gate S4_SrpR has no n"),
+			_ => panic!("imported a gate without n"),
+		}
+	}
+}
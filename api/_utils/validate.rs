@@ -0,0 +1,258 @@
+use crate::_utils::{data, error::Error};
+use data::{Data, Params, PartKind};
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+	Error,
+	Warning,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum IssueKind {
+	InvalidFile,
+	MissingPart,
+	WrongPartKind,
+	MissingRule,
+	UnknownReference,
+	InvalidSequence,
+	InvalidParams,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Issue {
+	pub severity: Severity,
+	pub kind: IssueKind,
+	pub item: String,
+	pub message: String,
+}
+
+// Errors are references the compiler would follow and panic on, warnings are
+// entries that are never looked up.
+#[derive(Serialize, Debug)]
+pub struct Report {
+	pub library: String,
+	pub issues: Vec<Issue>,
+}
+
+impl Report {
+	pub fn new(library: &str) -> Self {
+		Self {
+			library: library.to_string(),
+			issues: Vec::new(),
+		}
+	}
+
+	pub fn error(&mut self, kind: IssueKind, item: &str, message: String) {
+		self.issues.push(Issue {
+			severity: Severity::Error,
+			kind,
+			item: item.to_string(),
+			message,
+		});
+	}
+
+	pub fn warning(&mut self, kind: IssueKind, item: &str, message: String) {
+		self.issues.push(Issue {
+			severity: Severity::Warning,
+			kind,
+			item: item.to_string(),
+			message,
+		});
+	}
+
+	pub fn errors(&self) -> usize {
+		self.issues
+			.iter()
+			.filter(|issue| issue.severity == Severity::Error)
+			.count()
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.errors() == 0
+	}
+}
+
+fn check_params(params: &Params) -> Option<String> {
+	let values = [params.ymax, params.ymin, params.k, params.n, params.decay];
+	if values.iter().any(|value| !value.is_finite()) {
+		return Some("params must be finite".to_string());
+	}
+	if params.ymin < 0.0 || params.ymax <= params.ymin {
+		return Some(format!(
+			"expected 0 <= ymin < ymax, got ymin {} and ymax {}",
+			params.ymin, params.ymax
+		));
+	}
+	if params.k <= 0.0 || params.n <= 0.0 {
+		return Some(format!(
+			"expected positive K and n, got {} and {}",
+			params.k, params.n
+		));
+	}
+	// The fraction of a level lost every step, anything above 1 overshoots
+	// past zero.
+	if params.decay <= 0.0 || params.decay > 1.0 {
+		return Some(format!("expected 0 < decay <= 1, got {}", params.decay));
+	}
+	None
+}
+
+impl Data {
+	fn check_promoter(&self, report: &mut Report, item: &str, promoter: &str) {
+		match self.parts.get(promoter) {
+			Some(part) if part.kind != PartKind::Promoter => report.error(
+				IssueKind::WrongPartKind,
+				item,
+				format!("{} is a {:?}, not a promoter", promoter, part.kind),
+			),
+			Some(_) => {}
+			None => report.error(
+				IssueKind::MissingPart,
+				item,
+				format!("promoter {} is not in the parts", promoter),
+			),
+		}
+		if !self.rules.promoters.contains_key(promoter) {
+			report.error(
+				IssueKind::MissingRule,
+				item,
+				format!("promoter {} has no promoter rule", promoter),
+			);
+		}
+	}
+
+	// Checks that every name the compiler looks up resolves, that sequences
+	// are DNA and that gate and signal params are physical.
+	pub fn validate(&self) -> Report {
+		let mut report = Report::new(&self.name);
+
+		let mut names: Vec<&String> = self.parts.keys().collect();
+		names.sort();
+		for name in names {
+			if let Err(Error::InvalidSequence { position: pos, .. }) = self.parts[name].validate() {
+				let message = if self.parts[name].seq.is_empty() {
+					"sequence is empty".to_string()
+				} else {
+					format!("sequence has an invalid base at {}", pos)
+				};
+				report.error(IssueKind::InvalidSequence, name, message);
+			}
+		}
+
+		let mut groups = HashSet::new();
+		let mut promoters = HashSet::new();
+		for gene in &self.genes {
+			groups.insert(gene.group());
+			promoters.insert(gene.promoter.to_string());
+			for part in &gene.parts {
+				if !self.parts.contains_key(part) {
+					report.error(
+						IssueKind::MissingPart,
+						&gene.name,
+						format!("part {} is not in the parts", part),
+					);
+				}
+			}
+			self.check_promoter(&mut report, &gene.name, &gene.promoter);
+			if !self.rules.gates.contains_key(&gene.group()) {
+				report.error(
+					IssueKind::MissingRule,
+					&gene.name,
+					format!("group {} has no gate rule", gene.group()),
+				);
+			}
+			if let Some(message) = check_params(&gene.params) {
+				report.error(IssueKind::InvalidParams, &gene.name, message);
+			}
+		}
+
+		let mut signals: Vec<_> = self.signals.values().collect();
+		signals.sort_by(|a, b| a.name.cmp(&b.name));
+		for sig in signals {
+			groups.insert(sig.group());
+			promoters.insert(sig.promoter());
+			self.check_promoter(&mut report, &sig.name, &sig.promoter);
+			if !self.rules.gates.contains_key(&sig.group()) {
+				report.error(
+					IssueKind::MissingRule,
+					&sig.name,
+					format!("signal {} has no gate rule", sig.name),
+				);
+			}
+			if !sig.rpu_off.is_finite() || sig.rpu_off <= 0.0 || sig.rpu_on <= sig.rpu_off {
+				report.error(
+					IssueKind::InvalidParams,
+					&sig.name,
+					format!(
+						"expected 0 < rpu_off < rpu_on, got {} and {}",
+						sig.rpu_off, sig.rpu_on
+					),
+				);
+			}
+		}
+
+		let mut roadblock: Vec<_> = self.roadblock.iter().collect();
+		roadblock.sort();
+		for group in roadblock {
+			if !groups.contains(group) {
+				report.warning(
+					IssueKind::UnknownReference,
+					group,
+					format!("roadblock names unknown group {}", group),
+				);
+			}
+		}
+
+		let mut rules: Vec<_> = self.rules.gates.keys().collect();
+		rules.sort();
+		for group in rules {
+			if !groups.contains(group) {
+				report.warning(
+					IssueKind::UnknownReference,
+					group,
+					format!("gate rule names unknown group {}", group),
+				);
+			}
+		}
+		let mut rules: Vec<_> = self.rules.promoters.keys().collect();
+		rules.sort();
+		for promoter in rules {
+			if !promoters.contains(promoter) {
+				report.warning(
+					IssueKind::UnknownReference,
+					promoter,
+					format!("promoter rule names unknown promoter {}", promoter),
+				);
+			}
+		}
+
+		report
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn params(decay: f64) -> Params {
+		Params {
+			ymax: 2.1,
+			ymin: 0.007,
+			k: 0.1,
+			n: 2.8,
+			decay,
+		}
+	}
+
+	#[test]
+	fn decay_is_a_fraction() {
+		assert_eq!(check_params(&params(0.1)), None);
+		assert_eq!(check_params(&params(1.0)), None);
+		for decay in &[0.0, -0.1, 1.5] {
+			let message = check_params(&params(*decay)).unwrap();
+			assert!(message.contains("decay"), "{}", message);
+		}
+	}
+}
@@ -0,0 +1,17 @@
+extern crate serde;
+extern crate serde_json;
+
+use emergence::_utils::{http, routes};
+use http::{NowEvent, Request, Response};
+use lambda_runtime::{error::HandlerError, start, Context};
+use std::error::Error as StdError;
+
+fn handler(e: NowEvent, _: Context) -> Result<Response, HandlerError> {
+	let req: Request = serde_json::from_str(&e.body)?;
+	Ok(routes::explain(&req))
+}
+
+fn main() -> Result<(), Box<dyn StdError>> {
+	start(handler, None);
+	Ok(())
+}
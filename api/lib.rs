@@ -0,0 +1,42 @@
+//! The Emergence compiler, from a source to a genetic circuit and its DNA.
+//!
+//! [`compile`] runs every stage with [`Options`]:
+//!
+//! ```
+//! use emergence::{compile, Options};
+//!
+//! let source = "mod main { in(a, b); out(c); }
+//! env main { in(LacI, TetR); out(YFP); }
+//! impl main { let c = nor(a, b); }";
+//! let options = Options {
+//!     seed: Some(1),
+//!     iterations: 100,
+//!     ..Options::default()
+//! };
+//! let result = compile(source, &options).unwrap();
+//! assert_eq!(result.gc.outputs[0].name, "YFP");
+//! ```
+//!
+//! The stages can also be run one at a time: [`LexerIter`] and [`ParserIter`]
+//! read the source, [`LogicCircuitBuilder`] checks it and builds the
+//! [`LogicCircuit`], which is fit into a [`GeneticCircuit`] and turned into
+//! [`Dna`]. Libraries are loaded from `static/` in the working directory, or
+//! from the directory given to [`set_static_dir`].
+#![allow(clippy::wrong_self_convention, clippy::result_large_err)]
+
+// Shared with the binaries and the lambdas in `api/`, not part of the API.
+#[doc(hidden)]
+pub mod _utils;
+
+pub use _utils::{
+	builder::LogicCircuitBuilder,
+	data::set_static_dir,
+	diagnostic::Diagnostic,
+	dna::Dna,
+	error::Error,
+	genetic_circuit::GeneticCircuit,
+	lexer::LexerIter,
+	logic_circuit::LogicCircuit,
+	parser::ParserIter,
+	pipeline::{compile, compile_with, Assignment, CompileResult, Format, Options},
+};
@@ -0,0 +1,774 @@
+use emergence::{
+	_utils::{
+		assigner::DEFAULT_ITERATIONS,
+		catalog::Filter,
+		data::{
+			get_libraries, to_json, user_parts_path, write_json, Data, GeneData, Part,
+			DEFAULT_LIBRARY,
+		},
+		explain::{explain, EXPLANATIONS},
+		fit::{fit_hill, read_measurements, Estimate},
+		genetic_circuit::{read_traces, Comparison, FailureKind},
+		ucf::DEFAULT_DECAY,
+		validate::Severity,
+	},
+	compile_with, CompileResult, Diagnostic, Error, Format, GeneticCircuit, LexerIter,
+	LogicCircuit, LogicCircuitBuilder, Options, ParserIter,
+};
+use serde_json::{from_str, to_string_pretty, Value};
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	env, fs,
+	path::Path,
+	process,
+};
+
+const USAGE: &str = "usage: emc <command> [options]
+
+commands:
+    build <source>... [--out <dir>] [--seed <n>] [--iterations <n>]
+                      [--library <name|dir>] [--formats <format,...>]
+        compile sources into <dir> (default: build), with the formats genbank,
+        circuit, simulation, sbol and diagnostics (default: all). Exits with 1
+        if a source has errors and 3 if a simulated test disagrees with the
+        logic of its circuit
+    import <file>... [--library <path>]
+        add the parts from GenBank (.gb, .gbk) or SBOL3 JSON-LD (.json, .jsonld)
+        files to the user parts library (default: static/user_parts.json)
+    import-ucf <file>... --out <dir> [--decay <rate>]
+        convert a Cello UCF library (with its input and output files) into a
+        library directory laid out like static/
+    validate-library <dir>... [--json]
+        check that the names in a library directory resolve, its sequences are
+        DNA and its params are in physical ranges
+    fit <csv> [--decay <rate>] [--json]
+              [--name <gene> --promoter <part> --parts <part,...>]
+        fit the Hill params of a gate to `input,output` RPU measurements, with
+        a gene entry for genes.json when --name is given
+    calibrate <source> <csv> [--test <name>] [--library <name>] [--json]
+              [--circuit <circuit.json> | --seed <n> [--iterations <n>]]
+              [--time-scale <steps>] [--fit <decay,scale>]
+        compare the simulation of a test with a measured `time,<channel>,...`
+        time course, optionally fitting gene decays and the simulation steps
+        per unit of measured time. Genes are those of the circuit.json of
+        `emc build`, or assigned as `emc build` does with the same --seed
+    catalog [--library <name>] [--kind <signals|actuators|genes>]
+            [--query <text>] [--json]
+        list the signals, actuators and gene families of a library with their
+        params and parts, optionally only those whose name contains <text>
+    explain [<code>] [--json]
+        describe an error code like E0004 at length, or list every code";
+
+const FORMATS: [&str; 5] = ["genbank", "circuit", "simulation", "sbol", "diagnostics"];
+
+type Command = fn(&Args) -> Result<(), Error>;
+
+struct Args {
+	positional: Vec<String>,
+	options: HashMap<String, String>,
+	switches: HashSet<String>,
+}
+
+impl Args {
+	// `flags` take a value, `switches` don't.
+	fn parse(args: &[String], flags: &[&str], switches: &[&str]) -> Option<Self> {
+		let mut positional = Vec::new();
+		let mut options = HashMap::new();
+		let mut set = HashSet::new();
+		let mut iter = args.iter();
+		while let Some(arg) = iter.next() {
+			match arg.strip_prefix("--") {
+				Some(name) if flags.contains(&name) => {
+					options.insert(name.to_string(), iter.next()?.to_string());
+				}
+				Some(name) if switches.contains(&name) => {
+					set.insert(name.to_string());
+				}
+				Some(_) => return None,
+				None => positional.push(arg.to_string()),
+			}
+		}
+		Some(Self {
+			positional,
+			options,
+			switches: set,
+		})
+	}
+
+	fn option(&self, name: &str) -> Option<&str> {
+		self.options.get(name).map(String::as_str)
+	}
+
+	fn switch(&self, name: &str) -> bool {
+		self.switches.contains(name)
+	}
+}
+
+fn read(path: &str) -> Result<String, Error> {
+	fs::read_to_string(path).map_err(|e| Error::InvalidFile {
+		reason: format!("{}: {}", path, e),
+	})
+}
+
+// The parts in the file at `path`, with warnings for what was skipped.
+fn read_parts(path: &str) -> Result<(Vec<Part>, Vec<String>), Error> {
+	let text = read(path)?;
+	let ext = Path::new(path)
+		.extension()
+		.and_then(|ext| ext.to_str())
+		.unwrap_or("");
+	match ext {
+		"gb" | "gbk" | "genbank" => Part::from_genbank(&text),
+		"json" | "jsonld" => Part::from_sbol(&text).map(|parts| (parts, Vec::new())),
+		_ if text.starts_with("LOCUS") => Part::from_genbank(&text),
+		_ if text.trim_start().starts_with('{') => {
+			Part::from_sbol(&text).map(|parts| (parts, Vec::new()))
+		}
+		_ => Err(Error::InvalidFile {
+			reason: format!("{}: unknown format", path),
+		}),
+	}
+}
+
+fn import(args: &Args) -> Result<(), Error> {
+	let path = args
+		.option("library")
+		.map(String::from)
+		.unwrap_or_else(user_parts_path);
+	let mut library: HashMap<String, Part> = if Path::new(&path).exists() {
+		from_str(&read(&path)?).map_err(|e| Error::InvalidFile {
+			reason: format!("{}: {}", path, e),
+		})?
+	} else {
+		HashMap::new()
+	};
+
+	let data = get_libraries().require(DEFAULT_LIBRARY)?;
+	for file in &args.positional {
+		let (parts, warnings) = read_parts(file)?;
+		for warning in warnings {
+			eprintln!("{}: warning: {}", file, warning);
+		}
+		let total = parts.len();
+		let added = data.import_parts(&mut library, parts)?;
+		println!(
+			"{}: {} parts, {} added, {} already known",
+			file,
+			total,
+			added.len(),
+			total - added.len()
+		);
+		for name in added {
+			println!("    + {} ({:?})", name, library[&name].kind);
+		}
+	}
+
+	let sorted: BTreeMap<_, _> = library.into_iter().collect();
+	write_json(&path, &sorted)
+}
+
+fn import_ucf(args: &Args) -> Result<(), Error> {
+	let out = args.option("out").unwrap_or_else(|| usage());
+	let decay = parse_decay(args)?;
+	let files = args
+		.positional
+		.iter()
+		.map(|file| read(file))
+		.collect::<Result<Vec<String>, Error>>()?;
+
+	let data = Data::from_ucf(&files, decay)?;
+	data.save(out)?;
+	println!(
+		"{}: {} gates, {} parts, {} signals",
+		out,
+		data.genes_len(),
+		data.parts.len(),
+		data.signals_len()
+	);
+	Ok(())
+}
+
+fn parse_decay(args: &Args) -> Result<f64, Error> {
+	match args.option("decay") {
+		Some(decay) => match decay.parse::<f64>() {
+			Ok(value) if value > 0.0 && value <= 1.0 => Ok(value),
+			_ => Err(Error::InvalidOption {
+				option: "decay".to_string(),
+				reason: format!("expected 0 < decay <= 1, got {}", decay),
+			}),
+		},
+		None => Ok(DEFAULT_DECAY),
+	}
+}
+
+fn fit(args: &Args) -> Result<(), Error> {
+	// A gene needs all of `--name`, `--promoter` and `--parts`.
+	let named = args.option("name").map(|name| {
+		let promoter = args.option("promoter").unwrap_or_else(|| usage());
+		let parts = args.option("parts").unwrap_or_else(|| usage());
+		(name, promoter, parts)
+	});
+	let file = &args.positional[0];
+	let measurements = read_measurements(&read(file)?)?;
+	let fit = fit_hill(&measurements, parse_decay(args)?)?;
+
+	let gene = named.map(|(name, promoter, parts)| GeneData {
+		name: name.to_string(),
+		parts: parts
+			.split(',')
+			.map(|part| part.trim().to_string())
+			.collect(),
+		promoter: promoter.to_string(),
+		params: fit.params.clone(),
+	});
+
+	if args.switch("json") {
+		let mut out = serde_json::to_value(&fit).unwrap();
+		if let Some(gene) = &gene {
+			out["gene"] = serde_json::to_value(gene).unwrap();
+		}
+		println!("{}", to_string_pretty(&out).unwrap());
+		return Ok(());
+	}
+
+	println!(
+		"{}: {} measurements, {} after {} iterations",
+		file,
+		measurements.len(),
+		if fit.converged {
+			"converged"
+		} else {
+			"stopped"
+		},
+		fit.iterations
+	);
+	let estimates: [(&str, &Estimate); 4] = [
+		("ymax", &fit.ymax),
+		("ymin", &fit.ymin),
+		("K", &fit.k),
+		("n", &fit.n),
+	];
+	for (name, est) in &estimates {
+		println!(
+			"    {:<5} {:>12.6} ± {:<10.6} 95% CI [{:.6}, {:.6}]",
+			name, est.value, est.std_error, est.low, est.high
+		);
+	}
+	println!("    rss {:.6}, r² {:.6}", fit.rss, fit.r_squared);
+	println!(
+		"\n    {:>12} {:>12} {:>12} {:>12}",
+		"input", "output", "predicted", "residual"
+	);
+	for res in &fit.residuals {
+		println!(
+			"    {:>12.6} {:>12.6} {:>12.6} {:>12.6}",
+			res.input, res.output, res.predicted, res.residual
+		);
+	}
+	if let Some(gene) = gene {
+		println!("\n{}", to_json(&gene));
+	}
+	Ok(())
+}
+
+// The gene of every gate in a `circuit.json` written by `emc build`.
+fn read_assignment(path: &str) -> Result<HashMap<String, String>, Error> {
+	let circuit: Value = from_str(&read(path)?).map_err(|e| Error::InvalidFile {
+		reason: format!("{}: {}", path, e),
+	})?;
+	let components = circuit["components"]
+		.as_array()
+		.ok_or_else(|| Error::InvalidFile {
+			reason: format!("{}: not a circuit.json", path),
+		})?;
+	Ok(components
+		.iter()
+		.filter(|comp| comp["type"] == "Gene")
+		.filter_map(|comp| {
+			let gate = comp["gate"].as_str()?;
+			let gene = comp["data"]["name"].as_str()?;
+			Some((gate.to_string(), gene.to_string()))
+		})
+		.collect())
+}
+
+// Genes come from `--circuit`, so a calibration runs on the circuit that was
+// built, or from an assignment seeded like `emc build`.
+fn build_circuit(
+	path: &str,
+	library: &str,
+	args: &Args,
+) -> Result<(LogicCircuit, GeneticCircuit), Error> {
+	let source = read(path)?;
+	let libraries = get_libraries();
+	let library = libraries.require(library)?;
+	let mut bld = LogicCircuitBuilder::new(
+		ParserIter::new(LexerIter::with_file(source.chars(), path)),
+		libraries,
+		library,
+	);
+	if let Err(diagnostics) = bld.build_parse_tree() {
+		for diag in &diagnostics {
+			eprintln!("{}\n", diag.render(&source));
+		}
+		return Err(Error::CompileFailed {
+			failed: 1,
+			sources: 1,
+		});
+	}
+	for diag in bld.take_diagnostics() {
+		eprintln!("{}\n", diag.render(&source));
+	}
+	let data = bld.build_library()?;
+	let lc = bld.build_logic_circut(&data)?;
+	let gc = match args.option("circuit") {
+		Some(circuit) => {
+			let selected = lc.select_genes(&data, &read_assignment(circuit)?)?;
+			let mut gc = lc.into_biological(&data, &selected)?;
+			gc.test();
+			gc
+		}
+		None => {
+			let seed = parse_number(args, "seed")?;
+			let iterations = parse_number(args, "iterations")?.unwrap_or(DEFAULT_ITERATIONS);
+			if iterations == 0 {
+				return Err(Error::InvalidOption {
+					option: "iterations".to_string(),
+					reason: "has to be at least 1".to_string(),
+				});
+			}
+			lc.fit_into_biological(&data, iterations, seed)?
+		}
+	};
+	Ok((lc, gc))
+}
+
+fn parse_number<T: std::str::FromStr>(args: &Args, name: &str) -> Result<Option<T>, Error> {
+	match args.option(name) {
+		Some(value) => value.parse().map(Some).map_err(|_| Error::InvalidOption {
+			option: name.to_string(),
+			reason: format!("expected a number, got {}", value),
+		}),
+		None => Ok(None),
+	}
+}
+
+// Keys sorted, so the same seed writes the same files.
+fn to_sorted_json<T: serde::Serialize>(value: &T) -> String {
+	to_string_pretty(&serde_json::to_value(value).unwrap()).unwrap()
+}
+
+fn write_file(path: &Path, text: &str) -> Result<(), Error> {
+	fs::write(path, text).map_err(|e| Error::InvalidFile {
+		reason: format!("{}: {}", path.display(), e),
+	})
+}
+
+fn build(args: &Args) -> Result<(), Error> {
+	let out = Path::new(args.option("out").unwrap_or("build"));
+	let seed = parse_number(args, "seed")?;
+	let iterations = parse_number(args, "iterations")?.unwrap_or(DEFAULT_ITERATIONS);
+	if iterations == 0 {
+		return Err(Error::InvalidOption {
+			option: "iterations".to_string(),
+			reason: "has to be at least 1".to_string(),
+		});
+	}
+	let formats: Vec<&str> = match args.option("formats") {
+		Some(formats) => formats.split(',').map(str::trim).collect(),
+		None => FORMATS.to_vec(),
+	};
+	if let Some(format) = formats.iter().find(|format| !FORMATS.contains(format)) {
+		return Err(Error::InvalidOption {
+			option: "formats".to_string(),
+			reason: format!(
+				"unknown format {}, expected one of {}",
+				format,
+				FORMATS.join(", ")
+			),
+		});
+	}
+
+	// A library is either the name of a loaded one or a directory laid out
+	// like `static/`.
+	let library = args.option("library").unwrap_or(DEFAULT_LIBRARY);
+	let opened;
+	let library = match Path::new(library).is_dir() {
+		true => {
+			let name = Path::new(library)
+				.file_name()
+				.map(|name| name.to_string_lossy().to_string())
+				.unwrap_or_else(|| library.to_string());
+			let (data, report) = Data::open(&name, library);
+			if !report.is_valid() {
+				return Err(Error::InvalidLibrary {
+					library: name,
+					errors: report.errors(),
+				});
+			}
+			opened = data;
+			&opened
+		}
+		false => get_libraries().require(library)?,
+	};
+	fs::create_dir_all(out).map_err(|e| Error::InvalidFile {
+		reason: format!("{}: {}", out.display(), e),
+	})?;
+	let mut options = Options {
+		seed,
+		iterations,
+		formats: Vec::new(),
+		..Options::default()
+	};
+	if formats.contains(&"genbank") {
+		options.formats.push(Format::Dna);
+	}
+	if formats.contains(&"sbol") {
+		options.formats.push(Format::Sbol);
+	}
+
+	let mut failed = 0;
+	let mut tests = 0;
+	let mut failures = 0;
+	for path in &args.positional {
+		let source = read(path)?;
+		let stem = Path::new(path)
+			.file_stem()
+			.map(|stem| stem.to_string_lossy().to_string())
+			.unwrap_or_else(|| path.to_string());
+		let file = |ext: &str| out.join(format!("{}.{}", stem, ext));
+
+		options.file = Some(path.to_string());
+		let res = compile_with(&source, library, &options);
+		let diagnostics = match &res {
+			Ok(result) => &result.warnings,
+			Err(diagnostics) => diagnostics,
+		};
+		for diag in diagnostics {
+			eprintln!("{}\n", diag.render(&source));
+		}
+		if formats.contains(&"diagnostics") {
+			let diagnostics: Vec<&Diagnostic> = diagnostics.iter().collect();
+			write_file(&file("diagnostics.json"), &to_sorted_json(&diagnostics))?;
+		}
+		let CompileResult {
+			gc,
+			dna,
+			sbol,
+			failures: checks,
+			..
+		} = match res {
+			Ok(result) => result,
+			Err(_) => {
+				println!("{}: failed", path);
+				failed += 1;
+				continue;
+			}
+		};
+
+		if let Some(dna) = dna {
+			write_file(&file("gates.gb"), &dna.plasmid)?;
+			write_file(&file("output.gb"), &dna.out_plasmid)?;
+		}
+		if formats.contains(&"circuit") {
+			write_file(&file("circuit.json"), &to_sorted_json(&gc))?;
+		}
+		if formats.contains(&"simulation") {
+			let simulation = serde_json::json!({
+				"simulations": gc.simulations,
+				"failures": checks,
+			});
+			write_file(&file("simulation.json"), &to_sorted_json(&simulation))?;
+		}
+		if let Some(sbol) = sbol {
+			write_file(&file("sbol.json"), &sbol.document)?;
+		}
+
+		let wrong = checks
+			.iter()
+			.filter(|check| check.kind == FailureKind::WrongValue)
+			.count();
+		println!(
+			"{}: score {:.4}, {} tests, {} failed checks, {} not settled",
+			path,
+			gc.score.unwrap_or(0.0),
+			gc.simulations.len(),
+			wrong,
+			checks.len() - wrong
+		);
+		for check in &checks {
+			let kind = match check.kind {
+				FailureKind::WrongValue => "",
+				FailureKind::NotSettled => "not settled, ",
+			};
+			println!(
+				"    test {} @{}: {}{} expected {}, {:.6} against threshold {:.6}",
+				check.testbench,
+				check.time,
+				kind,
+				check.output,
+				if check.expected { "high" } else { "low" },
+				check.level,
+				check.threshold
+			);
+		}
+		tests += gc.simulations.len();
+		// Windows too short to settle in say nothing about the logic.
+		failures += wrong;
+	}
+
+	if failed > 0 {
+		return Err(Error::CompileFailed {
+			failed,
+			sources: args.positional.len(),
+		});
+	}
+	match failures {
+		0 => Ok(()),
+		_ => Err(Error::TestFailed { tests, failures }),
+	}
+}
+
+fn print_comparison(title: &str, cmp: &Comparison) {
+	println!(
+		"    {:<10} {:>8} {:>12} {:>12} {:>10}",
+		title, "points", "rmse", "mae", "r²"
+	);
+	for m in cmp.channels.iter().chain(Some(&cmp.overall)) {
+		println!(
+			"    {:<10} {:>8} {:>12.6} {:>12.6} {:>10.4}",
+			m.channel, m.points, m.rmse, m.mae, m.r_squared
+		);
+	}
+}
+
+fn calibrate(args: &Args) -> Result<(), Error> {
+	let source = &args.positional[0];
+	let csv = &args.positional[1];
+	let traces = read_traces(&read(csv)?)?;
+	let time_scale = parse_number(args, "time-scale")?.unwrap_or(1.0);
+	let fit: Vec<&str> = args.option("fit").unwrap_or("").split(',').collect();
+
+	let library = args.option("library").unwrap_or(DEFAULT_LIBRARY);
+	let (lc, mut gc) = build_circuit(source, library, args)?;
+	// Sources without tests get the exhaustive one, so there is always a first.
+	let testbench = match args.option("test") {
+		Some(name) => lc
+			.testbenches
+			.iter()
+			.find(|tb| tb.name == name)
+			.ok_or_else(|| Error::InvalidOption {
+				option: "test".to_string(),
+				reason: format!("{} has no test {}", source, name),
+			})?,
+		None => &lc.testbenches[0],
+	};
+
+	let calibration = gc.calibrate(
+		testbench,
+		&traces,
+		time_scale,
+		fit.contains(&"decay"),
+		fit.contains(&"scale"),
+	)?;
+	if args.switch("json") {
+		println!("{}", to_string_pretty(&calibration).unwrap());
+		return Ok(());
+	}
+
+	println!(
+		"{}: test {}, {} steps per time unit",
+		csv, calibration.testbench, calibration.time_scale
+	);
+	print_comparison("measured", &calibration.before);
+	if fit.iter().any(|f| !f.is_empty()) {
+		println!();
+		print_comparison("fitted", &calibration.after);
+	}
+	for gene in &calibration.genes {
+		println!("    {:<10} decay {:.6}", gene.name, gene.params.decay);
+	}
+	Ok(())
+}
+
+fn catalog(args: &Args) -> Result<(), Error> {
+	let filter = Filter::new(args.option("kind"), args.option("query"))?;
+	let library = args.option("library").unwrap_or(DEFAULT_LIBRARY);
+	let catalog = get_libraries().require(library)?.catalog(&filter);
+	if args.switch("json") {
+		println!("{}", to_string_pretty(&catalog).unwrap());
+		return Ok(());
+	}
+
+	println!("library {}", catalog.library);
+	if !catalog.signals.is_empty() {
+		println!("\nsignals");
+		for sig in &catalog.signals {
+			println!(
+				"    {:<12} {:<12} rpu off {:<10.4} on {:.4}",
+				sig.name, sig.promoter, sig.rpu_off, sig.rpu_on
+			);
+		}
+	}
+	if !catalog.actuators.is_empty() {
+		println!("\nactuators");
+		for act in &catalog.actuators {
+			println!("    {:<12} {} bp", act.name, act.length);
+		}
+	}
+	if !catalog.families.is_empty() {
+		println!("\ngene families");
+	}
+	for family in &catalog.families {
+		println!("    {}", family.group);
+		for var in &family.variants {
+			let p = &var.params;
+			println!(
+				"        {:<12} {:<12} ymax {:.4} ymin {:.4} K {:.4} n {:.4} decay {:.4}",
+				var.name, var.promoter, p.ymax, p.ymin, p.k, p.n, p.decay
+			);
+			let parts: Vec<String> = var
+				.parts
+				.iter()
+				.map(|part| match &part.kind {
+					Some(kind) => format!("{} ({:?})", part.name, kind),
+					None => format!("{} (missing)", part.name),
+				})
+				.collect();
+			println!("            {}", parts.join(", "));
+		}
+	}
+	Ok(())
+}
+
+fn validate_library(args: &Args) -> Result<(), Error> {
+	let reports: Vec<_> = args
+		.positional
+		.iter()
+		.map(|dir| {
+			let name = Path::new(dir)
+				.file_name()
+				.map(|name| name.to_string_lossy().to_string())
+				.unwrap_or_else(|| dir.to_string());
+			Data::open(&name, dir).1
+		})
+		.collect();
+
+	if args.switch("json") {
+		println!("{}", to_string_pretty(&reports).unwrap());
+	} else {
+		for (dir, report) in args.positional.iter().zip(&reports) {
+			let errors = report.errors();
+			println!(
+				"{}: {} errors, {} warnings",
+				dir,
+				errors,
+				report.issues.len() - errors
+			);
+			for issue in &report.issues {
+				let severity = match issue.severity {
+					Severity::Error => "error",
+					Severity::Warning => "warning",
+				};
+				println!("    {} {}: {}", severity, issue.item, issue.message);
+			}
+		}
+	}
+
+	match reports.iter().find(|report| !report.is_valid()) {
+		Some(report) => Err(Error::InvalidLibrary {
+			library: report.library.to_string(),
+			errors: report.errors(),
+		}),
+		None => Ok(()),
+	}
+}
+
+fn explain_code(args: &Args) -> Result<(), Error> {
+	let code = match args.positional.first() {
+		Some(code) => code,
+		None if args.switch("json") => {
+			println!("{}", to_string_pretty(&EXPLANATIONS).unwrap());
+			return Ok(());
+		}
+		None => {
+			for exp in &EXPLANATIONS {
+				println!("{} {}", exp.code, exp.kind);
+			}
+			return Ok(());
+		}
+	};
+	let exp = explain(code)?;
+	if args.switch("json") {
+		println!("{}", to_string_pretty(exp).unwrap());
+	} else {
+		println!("{} {}\n\n{}", exp.code, exp.kind, exp.text);
+	}
+	Ok(())
+}
+
+// Arguments missing or not understood, as opposed to a value out of range.
+fn usage() -> ! {
+	eprintln!("{}", USAGE);
+	process::exit(2);
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+	// The last field is the least number of positional arguments.
+	let (command, flags, switches, required): (Command, &[&str], &[&str], usize) =
+		match args.first().map(String::as_str) {
+			Some("build") => (
+				build,
+				&["out", "seed", "iterations", "library", "formats"],
+				&[],
+				1,
+			),
+			Some("import") => (import, &["library"], &[], 1),
+			Some("import-ucf") => (import_ucf, &["out", "decay"], &[], 1),
+			Some("validate-library") => (validate_library, &[], &["json"], 1),
+			Some("fit") => (fit, &["decay", "name", "promoter", "parts"], &["json"], 1),
+			Some("calibrate") => (
+				calibrate,
+				&[
+					"test",
+					"library",
+					"time-scale",
+					"fit",
+					"circuit",
+					"seed",
+					"iterations",
+				],
+				&["json"],
+				2,
+			),
+			Some("catalog") => (catalog, &["library", "kind", "query"], &["json"], 0),
+			Some("explain") => (explain_code, &[], &["json"], 0),
+			_ => usage(),
+		};
+
+	let parsed = match Args::parse(&args[1..], flags, switches) {
+		Some(parsed) if parsed.positional.len() >= required => parsed,
+		_ => usage(),
+	};
+
+	if let Err(e) = command(&parsed) {
+		// Errors in a source file carry its name, so it can be read back to
+		// show the offending line.
+		let rendered = e
+			.span()
+			.and_then(|span| span.file.as_ref())
+			.and_then(|file| fs::read_to_string(file).ok())
+			.and_then(|source| e.render(&source));
+		match rendered {
+			Some(rendered) => eprintln!("{}", rendered),
+			None => eprintln!("error[{}]: {}", e.code(), e),
+		}
+		// Failed tests still produce a circuit, so scripts can tell them
+		// apart from sources that don't compile.
+		process::exit(match e {
+			Error::TestFailed { .. } => 3,
+			_ => 1,
+		});
+	}
+}
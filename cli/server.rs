@@ -0,0 +1,63 @@
+use emergence::{
+	_utils::{data::get_libraries, server::serve},
+	set_static_dir,
+};
+use std::{env, net::TcpListener, process};
+
+const USAGE: &str = "usage: server [--bind <addr>] [--static <dir>] [--threads <n>]
+
+    serve /api/compile, /api/catalog and /api/explain like the Vercel
+    deployment, and /health, over plain HTTP
+
+    --bind <addr>     address to listen on (default: 127.0.0.1:3000)
+    --static <dir>    directory with the default library and libraries/
+                      (default: static in the working directory)
+    --threads <n>     requests handled at a time (default: 4)";
+
+fn main() {
+	let mut bind = "127.0.0.1:3000".to_string();
+	let mut threads = 4;
+	let mut args = env::args().skip(1);
+	while let Some(arg) = args.next() {
+		let value = args.next();
+		let number = value.as_ref().and_then(|n| n.parse::<usize>().ok());
+		match (arg.as_str(), value) {
+			("--bind", Some(addr)) => bind = addr,
+			("--static", Some(dir)) => {
+				set_static_dir(&dir);
+			}
+			("--threads", Some(_)) if number.is_some_and(|n| n > 0) => {
+				threads = number.unwrap();
+			}
+			_ => {
+				eprintln!("{}", USAGE);
+				process::exit(2);
+			}
+		}
+	}
+
+	// Libraries are loaded up front, so a broken one shows before the first
+	// request does.
+	let names = get_libraries().names();
+	if names.is_empty() {
+		eprintln!("error: no valid library found");
+		process::exit(1);
+	}
+	let listener = match TcpListener::bind(&bind) {
+		Ok(listener) => listener,
+		Err(e) => {
+			eprintln!("error: {}: {}", bind, e);
+			process::exit(1);
+		}
+	};
+	eprintln!(
+		"listening on {} with {} threads, libraries {}",
+		bind,
+		threads,
+		names.join(", ")
+	);
+	if let Err(e) = serve(listener, threads) {
+		eprintln!("error: {}", e);
+		process::exit(1);
+	}
+}
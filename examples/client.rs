@@ -0,0 +1,62 @@
+// Posts a source to a running `server` and prints the response, to try it
+// without a browser:
+//
+//     cargo run --bin server &
+//     cargo run --example client -- circuit.em [--addr <addr>] [--base64]
+use std::{
+	env, fs,
+	io::{Read, Write},
+	net::TcpStream,
+	process,
+};
+
+fn main() {
+	let mut file = None;
+	let mut addr = "127.0.0.1:3000".to_string();
+	let mut encode = false;
+	let mut args = env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--addr" => addr = args.next().unwrap_or(addr),
+			"--base64" => encode = true,
+			_ => file = Some(arg),
+		}
+	}
+	let file = file.unwrap_or_else(|| {
+		eprintln!("usage: client <source> [--addr <addr>] [--base64]");
+		process::exit(2);
+	});
+
+	let source = fs::read_to_string(&file).unwrap_or_else(|e| {
+		eprintln!("error: {}: {}", file, e);
+		process::exit(1);
+	});
+	let (body, encoding) = match encode {
+		true => (
+			base64::encode(&source),
+			"Content-Transfer-Encoding: base64\r\n",
+		),
+		false => (source, ""),
+	};
+
+	let mut stream = TcpStream::connect(&addr).unwrap_or_else(|e| {
+		eprintln!("error: {}: {}", addr, e);
+		process::exit(1);
+	});
+	let request = format!(
+		"POST /api/compile HTTP/1.1\r\nHost: {}\r\n{}Content-Length: {}\r\n\r\n{}",
+		addr,
+		encoding,
+		body.len(),
+		body
+	);
+	let mut response = String::new();
+	stream
+		.write_all(request.as_bytes())
+		.and_then(|_| stream.read_to_string(&mut response))
+		.unwrap_or_else(|e| {
+			eprintln!("error: {}: {}", addr, e);
+			process::exit(1);
+		});
+	println!("{}", response);
+}
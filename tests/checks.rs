@@ -0,0 +1,45 @@
+use emergence::{CompileResult, Options};
+use serde_json::to_value;
+
+fn compile(source: &str, seed: u64) -> CompileResult {
+	let options = Options {
+		seed: Some(seed),
+		iterations: 200,
+		..Options::default()
+	};
+	emergence::compile(source, &options).unwrap()
+}
+
+fn kinds(result: &CompileResult) -> Vec<String> {
+	let failures = to_value(&result.failures).unwrap();
+	let failures = failures.as_array().unwrap();
+	failures
+		.iter()
+		.map(|failure| failure["kind"].as_str().unwrap().to_string())
+		.collect()
+}
+
+// An OR passes its exhaustive test whatever genes it gets, even where a
+// single input on leaves the output short of its high steady state.
+#[test]
+fn or_passes_with_every_seed() {
+	let source = "mod main { in(a, b); out(c); }
+env main { in(LacI, TetR); out(YFP); }
+impl main { let x = nor(a, b); let c = not(x); }";
+	for seed in 1..=8 {
+		let result = compile(source, seed);
+		assert_eq!(kinds(&result), Vec::<String>::new(), "seed {}", seed);
+	}
+}
+
+// Two steps aren't enough for the output to fall, which says nothing about
+// the logic of the circuit.
+#[test]
+fn short_breakpoints_are_not_settled() {
+	let source = "mod main { in(a, b); out(c); }
+env main { in(LacI, TetR); out(YFP); }
+impl main { let x = nor(a, b); let c = not(x); }
+test t for main in main { @0 LacI = true; @400 LacI = false; @402 TetR = true; }";
+	let result = compile(source, 1);
+	assert_eq!(kinds(&result), ["NotSettled"]);
+}
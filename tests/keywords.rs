@@ -0,0 +1,24 @@
+use emergence::Options;
+
+// Words added to the language after its first release are only keywords
+// where a definition, statement or gate can start, so sources that named
+// wires and tests after them still compile.
+#[test]
+fn new_keywords_are_still_names() {
+	let source = "mod main { in(part, gene, auto); out(pulse); }
+env main { in(LacI, TetR, AraC); out(YFP); }
+impl main {
+	let reg = nor(part, gene);
+	let osc = not(reg);
+	let pulse = nor(osc, auto);
+}
+test auto for main in main auto;";
+	let options = Options {
+		seed: Some(1),
+		iterations: 200,
+		..Options::default()
+	};
+	if let Err(diagnostics) = emergence::compile(source, &options) {
+		panic!("{:?}", diagnostics);
+	}
+}
@@ -0,0 +1,38 @@
+use emergence::Options;
+use serde_json::to_value;
+
+// An oscillator feeding an ordinary gate is scored together with the gate,
+// both as the fold change between their low and high levels. The circuit
+// never scores above its oscillator, and scores below it when the gate is
+// the weaker part.
+#[test]
+fn motifs_are_scored_with_the_logic_around_them() {
+	let source = "mod main { in(a); out(y); }
+env main { in(LacI); out(YFP); }
+impl main { let o = osc(3); let y = nor(o, a); }";
+	let mut limited_by_gate = false;
+	for seed in 1..=4 {
+		let options = Options {
+			seed: Some(seed),
+			iterations: 1000,
+			..Options::default()
+		};
+		let result = emergence::compile(source, &options).unwrap();
+		let gc = to_value(&result.gc).unwrap();
+		let score = gc["score"].as_f64().unwrap();
+		let levels = gc["motifs"][0]["levels"].as_array().unwrap();
+		let (low, high) = (levels[0].as_f64().unwrap(), levels[1].as_f64().unwrap());
+		let fold = high / low.max(0.01);
+
+		assert!(score > 0.0, "seed {}", seed);
+		assert!(
+			score <= fold + 1e-9,
+			"seed {}: {} above {}",
+			seed,
+			score,
+			fold
+		);
+		limited_by_gate |= score < fold - 1e-9;
+	}
+	assert!(limited_by_gate);
+}
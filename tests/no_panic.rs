@@ -0,0 +1,169 @@
+use emergence::{Diagnostic, Options};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::panic;
+
+const CASES: usize = 300;
+// Enough iterations to try a few assignments, not to find a good one.
+const FIT_ITERATIONS: usize = 20;
+
+const SOURCES: [&str; 4] = [
+	"mod main { in(a, b); out(c); }
+env main { in(LacI, TetR); out(YFP); }
+impl main { let x = nor(a, b); let c = not(x); }
+test t for main { @0 LacI = true; @200 TetR = true; }
+test u for main auto(50, gray);",
+	"mod main { in(s, r); out(q); }
+env main { in(LacI, TetR); out(YFP); }
+impl main { reg qn; let q = nor(r, qn); let qn = nor(s, q); }",
+	"mod main { in(a); out(o); }
+env main for \"default\" { in(LacI); out(YFP); }
+#[allow(unused_input)]
+impl main { let p = pulse(a); let o = osc(3); }",
+	"part pNewR promoter \"AACGATCGTTGGCTGTGTTGACAATTAATCATCGG\";
+part NewR cds \"ATGGCACGTACCCCGAGCCGTAGCAGCATTGGTAG\";
+gene Z1_NewR { promoter pNewR; parts(RiboJ10, S4, NewR, ECK120029600); ymax = 60.5; ymin = 0.01; K = 0.1; n = 2.5; decay = 0.2; }
+mod main { in(a, b); out(c); }
+env main { in(LacI, TetR); out(YFP); }
+impl main { let c = nor(a, b); }",
+];
+
+// Pieces spliced into the sources, picked to reach the checks a plain typo
+// wouldn't.
+const PIECES: &str = "{ } ( ) ; , = @ #[allow( ] \" main nor not osc pulse let reg mod env impl
+	test in out for auto part gene 0 4294967296 -1 é";
+
+fn compile(source: &str) -> Result<(), Vec<Diagnostic>> {
+	let options = Options {
+		seed: Some(0),
+		iterations: FIT_ITERATIONS,
+		..Options::default()
+	};
+	emergence::compile(source, &options).map(|_| ())
+}
+
+// Words and single signs of a source, mutated as a whole so most mutants
+// still parse and go on to the later checks.
+fn tokens(source: &str) -> Vec<String> {
+	let mut tokens: Vec<String> = Vec::new();
+	let mut word = false;
+	let mut quoted = false;
+	for c in source.chars() {
+		let is_word = c.is_alphanumeric() || c == '_' || c == '.';
+		match tokens.last_mut() {
+			Some(last) if quoted || (is_word && word) => last.push(c),
+			_ if c.is_whitespace() => {}
+			_ => tokens.push(c.to_string()),
+		}
+		if c == '"' {
+			quoted = !quoted;
+		}
+		word = is_word;
+	}
+	tokens
+}
+
+fn mutate(rng: &mut StdRng, source: &str) -> String {
+	let mut tokens = tokens(source);
+	for _ in 0..rng.gen_range(1, 4) {
+		let at = rng.gen_range(0, tokens.len());
+		match rng.gen_range(0, 4) {
+			0 => {
+				tokens.remove(at);
+			}
+			1 => {
+				let copy = tokens[at..(at + 4).min(tokens.len())].to_vec();
+				tokens.splice(at..at, copy);
+			}
+			2 => {
+				let other = rng.gen_range(0, tokens.len());
+				tokens.swap(at, other);
+			}
+			_ => {
+				let pieces: Vec<&str> = PIECES.split_whitespace().collect();
+				tokens.insert(at, pieces.choose(rng).unwrap().to_string());
+			}
+		}
+	}
+	tokens.join(" ")
+}
+
+// Mostly distinct names from `pool`, sometimes one too many or too few.
+fn pick<'a>(rng: &mut StdRng, pool: &[&'a str], len: usize) -> Vec<&'a str> {
+	let len = match rng.gen_range(0, 20) {
+		0 => len + 1,
+		1 => len.saturating_sub(1),
+		_ => len,
+	};
+	let mut names: Vec<&str> = pool.choose_multiple(rng, len).cloned().collect();
+	if rng.gen_bool(0.05) {
+		names.push(pool.choose(rng).unwrap());
+	}
+	names
+}
+
+// A source that parses but whose names, arities and sizes are random, for
+// the checks past the parser.
+fn generate(rng: &mut StdRng) -> String {
+	let num_ins = rng.gen_range(1, 4);
+	let ins = pick(rng, &["a", "b", "c"], num_ins);
+	let outs = pick(rng, &["y", "z"], 1);
+	let mut source = format!(
+		"mod main {{ in({}); out({}); }}\nenv main {{ in({}); out({}); }}\nimpl main {{\n",
+		ins.join(", "),
+		outs.join(", "),
+		pick(rng, &["LacI", "AraC", "TetR"], num_ins).join(", "),
+		pick(rng, &["YFP"], 1).join(", "),
+	);
+
+	let mut wires: Vec<String> = ins.iter().map(|inp| inp.to_string()).collect();
+	let num_gates = rng.gen_range(1, 8);
+	for i in 0..num_gates {
+		let wire = match i + 1 == num_gates {
+			true => outs.first().unwrap_or(&"y").to_string(),
+			false => format!("w{}", rng.gen_range(0, num_gates)),
+		};
+		if rng.gen_bool(0.1) {
+			source += &format!("reg {};\n", wire);
+			continue;
+		}
+		let (gate, arity) = *[("not", 1), ("nor", 2), ("osc", 1), ("pulse", 1)]
+			.choose(rng)
+			.unwrap();
+		let args: Vec<String> = match gate {
+			"osc" => match rng.gen_range(0, 10) {
+				0 => vec!["4".to_string()],
+				stages => vec![(3 + stages % 2 * 2).to_string()],
+			},
+			_ => (0..arity)
+				.map(|_| match wires.choose(rng) {
+					Some(wire) if rng.gen_bool(0.9) => wire.to_string(),
+					_ => format!("w{}", rng.gen_range(0, num_gates)),
+				})
+				.collect(),
+		};
+		source += &format!("let {} = {}({});\n", wire, gate, args.join(", "));
+		wires.push(wire);
+	}
+	source += "}\n";
+
+	match rng.gen_range(0, 3) {
+		0 => source += "test t for main { @0 LacI = true; @100 TetR = true; }",
+		1 => source += "test t for main auto(1, gray);",
+		_ => {}
+	}
+	source
+}
+
+// Any source, however broken, compiles or comes back with errors.
+#[test]
+fn compile_never_panics() {
+	let mut rng = StdRng::seed_from_u64(0);
+	for case in 0..CASES {
+		let source = match case % 3 {
+			0 => mutate(&mut rng, SOURCES[case % SOURCES.len()]),
+			_ => generate(&mut rng),
+		};
+		let res = panic::catch_unwind(|| compile(&source));
+		assert!(res.is_ok(), "compile panicked on:\n{}", source);
+	}
+}
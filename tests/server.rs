@@ -0,0 +1,128 @@
+use emergence::_utils::server::serve;
+use serde_json::{json, Value};
+use std::{
+	io::{Read, Write},
+	net::{TcpListener, TcpStream},
+	thread,
+};
+
+const SOURCE: &str = "mod main { in(a, b); out(c); }
+env main { in(LacI, TetR); out(YFP); }
+impl main { let c = nor(a, b); }";
+
+// The server on a free port, left running for the rest of the tests.
+fn start() -> String {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap().to_string();
+	thread::spawn(move || serve(listener, 4));
+	addr
+}
+
+// A stand-in client, returning the status, the raw head and the body.
+fn request(addr: &str, head: &str, body: &str) -> (u16, String, String) {
+	let mut stream = TcpStream::connect(addr).unwrap();
+	write!(
+		stream,
+		"{}\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+		head,
+		addr,
+		body.len(),
+		body
+	)
+	.unwrap();
+	let mut res = String::new();
+	stream.read_to_string(&mut res).unwrap();
+	let (head, body) = res.split_once("\r\n\r\n").unwrap();
+	let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+	(status, head.to_string(), body.to_string())
+}
+
+#[test]
+fn serves_like_the_lambdas() {
+	let addr = start();
+
+	let (status, _, body) = request(&addr, "GET /health HTTP/1.1", "");
+	let health: Value = serde_json::from_str(&body).unwrap();
+	assert_eq!(status, 200);
+	assert_eq!(health["status"], "ok");
+
+	let (status, head, _) = request(&addr, "OPTIONS /api/compile HTTP/1.1", "");
+	assert_eq!(status, 200);
+	assert!(head.contains("Access-Control-Allow-Origin: *"));
+	assert!(head.contains("Access-Control-Request-Method: POST, OPTIONS, GET"));
+
+	let (status, _, _) = request(&addr, "GET /api/nothing HTTP/1.1", "");
+	assert_eq!(status, 404);
+
+	let (status, _, body) = request(&addr, "GET /api/explain?code=E0002 HTTP/1.1", "");
+	let exp: Value = serde_json::from_str(&body).unwrap();
+	assert_eq!(status, 200);
+	assert_eq!(exp["kind"], "NotFound");
+}
+
+#[test]
+fn compiles_raw_and_base64_bodies() {
+	let addr = start();
+	let broken = SOURCE.replace("TetR", "Tetr");
+
+	let (status, head, body) = request(&addr, "POST /api/compile HTTP/1.1", &broken);
+	let diagnostics: Value = serde_json::from_str(&body).unwrap();
+	assert_eq!(status, 400);
+	assert!(head.contains("Access-Control-Allow-Origin: *"));
+	assert_eq!(diagnostics[0]["code"], "E0002");
+	assert_eq!(diagnostics[0]["fixes"][0]["replacement"], "TetR");
+
+	let encoded = base64::encode(&broken);
+	let head = "POST /api/compile HTTP/1.1\r\nContent-Transfer-Encoding: base64";
+	let (status, _, body) = request(&addr, head, &encoded);
+	let diagnostics: Value = serde_json::from_str(&body).unwrap();
+	assert_eq!(status, 400);
+	assert_eq!(diagnostics[0]["code"], "E0002");
+
+	// Requests are served side by side, so health checks answer while a
+	// circuit compiles.
+	let compile = {
+		let addr = addr.clone();
+		thread::spawn(move || request(&addr, "POST /api/compile HTTP/1.1", SOURCE))
+	};
+	let (status, _, _) = request(&addr, "GET /health HTTP/1.1", "");
+	assert_eq!(status, 200);
+	let (status, _, body) = compile.join().unwrap();
+	let result: Value = serde_json::from_str(&body).unwrap();
+	assert_eq!(status, 200);
+	assert_eq!(result["gc"]["outputs"][0]["name"], "YFP");
+}
+
+#[test]
+fn compiles_json_envelopes() {
+	let addr = start();
+	let post = |body: Value| {
+		let (status, _, body) = request(&addr, "POST /api/compile HTTP/1.1", &body.to_string());
+		(status, serde_json::from_str::<Value>(&body).unwrap())
+	};
+
+	let (status, body) = post(json!({ "source": SOURCE, "options": { "iteration": 50 } }));
+	assert_eq!(status, 400);
+	assert_eq!(body[0]["code"], "E0022");
+	assert_eq!(body[0]["data"]["option"], "iteration");
+
+	let (status, body) = post(json!({ "source": SOURCE, "options": { "top_n": 0 } }));
+	assert_eq!(status, 400);
+	assert_eq!(body[0]["data"]["option"], "top_n");
+
+	let (status, body) = post(json!({ "source": SOURCE, "options": { "formats": ["pdf"] } }));
+	assert_eq!(status, 400);
+	assert_eq!(body[0]["data"]["option"], "formats");
+
+	let options = json!({ "seed": 3, "iterations": 50, "top_n": 3, "formats": ["sbol"] });
+	let (status, first) = post(json!({ "source": SOURCE, "options": options }));
+	assert_eq!(status, 200);
+	assert!(first["dna"].is_null());
+	assert!(first["sbol"].is_object());
+	let assignments = first["assignments"].as_array().unwrap();
+	assert!(!assignments.is_empty() && assignments.len() <= 3);
+
+	// The same seed picks the same genes.
+	let (_, second) = post(json!({ "source": SOURCE, "options": options }));
+	assert_eq!(first["assignments"], second["assignments"]);
+}
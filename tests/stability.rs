@@ -0,0 +1,28 @@
+use emergence::Options;
+use serde_json::to_value;
+
+// An SR latch holds either state with both inputs low, and only one once an
+// input is set. States a few percent apart are the same state settled from
+// different corners, not extra ones.
+#[test]
+fn latch_is_bistable_only_while_held() {
+	let source = "mod main { in(s, r); out(q); }
+env main { in(LacI, TetR); out(YFP); }
+impl main { reg qn; let q = nor(r, qn); let qn = nor(s, q); }";
+	let options = Options {
+		seed: Some(1),
+		iterations: 500,
+		..Options::default()
+	};
+	let result = emergence::compile(source, &options).unwrap();
+	let gc = to_value(&result.gc).unwrap();
+	for stable in gc["stability"].as_array().unwrap() {
+		let held = stable["inputs"]
+			.as_object()
+			.unwrap()
+			.values()
+			.all(|on| on == false);
+		let states = stable["states"].as_array().unwrap();
+		assert_eq!(states.len(), if held { 2 } else { 1 }, "{}", stable);
+	}
+}
@@ -0,0 +1,55 @@
+use emergence::{CompileResult, Diagnostic, Options};
+use serde_json::{to_value, Value};
+
+fn compile(source: &str, seed: u64) -> Result<CompileResult, Vec<Diagnostic>> {
+	let options = Options {
+		seed: Some(seed),
+		iterations: 200,
+		..Options::default()
+	};
+	emergence::compile(source, &options)
+}
+
+// The levels `promoter` went through in the simulation of `test`.
+fn history(result: &CompileResult, test: &str, promoter: &str) -> Vec<f64> {
+	let gc = to_value(&result.gc).unwrap();
+	let history = &gc["simulations"][test]["history"][promoter];
+	let history = history.as_array().unwrap();
+	history
+		.iter()
+		.map(|level| level.as_f64().unwrap())
+		.collect()
+}
+
+// A test bound to another env drives the inputs with the levels of its own
+// signals, exhaustive or not.
+#[test]
+fn tests_use_the_levels_of_their_env() {
+	let source = "mod main { in(a); out(y); }
+env main { in(LacI); out(YFP); }
+env lab { in(AraC); out(YFP); }
+impl main { let y = not(a); }
+test own for main in main { @0 LacI = true; }
+test other for main in lab { @0 AraC = true; }
+test every for main in lab auto(100);";
+	let result = compile(source, 1).unwrap();
+
+	// LacI and AraC switch pLac and pBAD between 0.0034 and 2.8, and between
+	// 0.0082 and 2.5 RPU.
+	assert_eq!(history(&result, "own", "pLac")[0], 2.8);
+	assert_eq!(history(&result, "other", "pLac")[0], 2.5);
+	let every = history(&result, "every", "pLac");
+	assert_eq!((every[0], every[100]), (0.0082, 2.5));
+}
+
+#[test]
+fn rejects_envs_with_other_signals() {
+	let source = "mod main { in(a); out(y); }
+env main { in(LacI); out(YFP); }
+env lab { in(AraC, TetR); out(YFP); }
+impl main { let y = not(a); }
+test other for main in lab { @0 AraC = true; }";
+	let diagnostics = compile(source, 1).unwrap_err();
+	let diagnostic = to_value(&diagnostics[0]).unwrap();
+	assert_eq!(diagnostic["code"], Value::from("E0005"));
+}
@@ -0,0 +1,53 @@
+use emergence::Options;
+use serde_json::{to_value, Value};
+
+// The hazards found in the compiled `source`.
+fn hazards(source: &str) -> Vec<Value> {
+	let options = Options {
+		seed: Some(1),
+		iterations: 200,
+		..Options::default()
+	};
+	let result = emergence::compile(source, &options).unwrap();
+	let gc = to_value(&result.gc).unwrap();
+	gc["timing"]["hazards"].as_array().unwrap().clone()
+}
+
+// `y = a & b | !a & c`, the textbook static-1 hazard: with `b` and `c` high,
+// `a` reaches `s` through `p` and `q` with opposite parity and at different
+// times, so `y` can dip while `a` switches.
+#[test]
+fn finds_static_hazard() {
+	let source = "mod main { in(a, b, c); out(y); }
+env main { in(LacI, TetR, AraC); out(YFP); }
+impl main {
+	let na = not(a);
+	let nb = not(b);
+	let nc = not(c);
+	let p = nor(na, nb);
+	let q = nor(a, nc);
+	let s = nor(p, q);
+	let y = not(s);
+}";
+	let hazards = hazards(source);
+	assert_eq!(hazards.len(), 1, "{:?}", hazards);
+	assert_eq!(hazards[0]["kind"], "Static");
+	assert_eq!(hazards[0]["signal"], "LacI");
+	let delays = hazards[0]["delays"].as_array().unwrap();
+	assert!(hazards[0]["width"].as_f64().unwrap() > 0.0);
+	assert!(delays.len() >= 2);
+}
+
+// `a` reaches `y` through `p` and `q`, both inverting once, so however far
+// apart they arrive the output only switches once.
+#[test]
+fn reconvergence_with_one_parity_is_hazard_free() {
+	let source = "mod main { in(a, b); out(y); }
+env main { in(LacI, TetR); out(YFP); }
+impl main {
+	let p = not(a);
+	let q = nor(a, b);
+	let y = nor(p, q);
+}";
+	assert_eq!(hazards(source), Vec::<Value>::new());
+}